@@ -0,0 +1,290 @@
+//running hexes from other programs. Everything is passed in and returned, nothing is read from
+//the command line or printed to stdout or stderr
+use std::rc::Rc;
+
+use im::Vector;
+use pest::error::{Error, LineColLocation};
+
+use crate::{
+    interpreter::{
+        interpret_with_hook,
+        mishap::Mishap,
+        state::{LogEntry, Stack, State},
+    },
+    iota::Iota,
+    parse_config::Config,
+    parser::{parse, AstNode, Location, Macros, Rule, SourceFile},
+    pattern_registry::{PatternRegistry, PatternRegistryExt},
+};
+
+//a hex that has been parsed and can be run any number of times
+#[derive(Debug, Clone)]
+pub struct Hex {
+    pub ast: AstNode,
+    pub macros: Macros,
+    pub sources: Vec<SourceFile>,
+}
+
+impl Hex {
+    //included files are read relative to `path`, which is also the file errors are reported in
+    pub fn parse(source: &str, path: &str, config: &Config) -> Result<Hex, Vec<Diagnostic>> {
+        let pattern_registry = PatternRegistry::from_config(config);
+        let (ast, macros, sources) = parse(
+            source,
+            path,
+            &pattern_registry,
+            &mut config.entities.clone(),
+        )
+        .map_err(|errors| {
+            errors
+                .iter()
+                .map(Diagnostic::from_parse_error)
+                .collect::<Vec<_>>()
+        })?;
+
+        Ok(Hex {
+            ast,
+            macros,
+            sources,
+        })
+    }
+
+    //limits, media and in-game mishaps are set in the config
+    pub fn run(&self, config: &Config, mut hooks: Hooks) -> RunResult {
+        let mut logged = 0;
+        let mut on_step = |state: &State| {
+            hooks.log(state, &mut logged);
+            hooks.on_step.as_mut().is_none_or(|on_step| on_step(state))
+        };
+
+        let result =
+            interpret_with_hook(self.ast.clone(), config, self.macros.clone(), &mut on_step);
+        let (state, error) = match result {
+            Ok(state) => (state, None),
+            Err((mishap, location, caused_by, state)) => {
+                (state, Some((mishap, location, caused_by)))
+            }
+        };
+        //the step that stopped the hex isn't seen by the step hook
+        hooks.log(&state, &mut logged);
+
+        let finished = error.is_none();
+        let errors = state
+            .mishaps
+            .iter()
+            .chain(error.iter())
+            .map(|err| Diagnostic::from_mishap(err, &self.sources))
+            .collect();
+
+        RunResult {
+            stack: state.stack,
+            ravenmind: state.ravenmind,
+            output: state.log.iter().filter_map(LogEntry::output).collect(),
+            log: state.log,
+            media_spent: state.media_spent,
+            errors,
+            finished,
+        }
+    }
+}
+
+//parses and runs a hex, parse errors are returned as the errors of the run
+pub fn run_hex(source: &str, config: &Config, hooks: Hooks) -> RunResult {
+    match Hex::parse(source, "", config) {
+        Ok(hex) => hex.run(config, hooks),
+        Err(errors) => RunResult {
+            errors,
+            ..Default::default()
+        },
+    }
+}
+
+pub type LogHook<'a> = dyn FnMut(&LogEntry) + 'a;
+
+pub type StepHook<'a> = dyn FnMut(&State) -> bool + 'a;
+
+#[derive(Default)]
+pub struct Hooks<'a> {
+    on_log: Option<Box<LogHook<'a>>>,
+    on_step: Option<Box<StepHook<'a>>>,
+}
+
+impl<'a> Hooks<'a> {
+    //called with everything the hex does that the caster could see, as it happens
+    pub fn on_log(mut self, hook: impl FnMut(&LogEntry) + 'a) -> Hooks<'a> {
+        self.on_log = Some(Box::new(hook));
+        self
+    }
+
+    //called after every step, returning false stops the hex with an "Interrupted" mishap
+    pub fn on_step(mut self, hook: impl FnMut(&State) -> bool + 'a) -> Hooks<'a> {
+        self.on_step = Some(Box::new(hook));
+        self
+    }
+
+    fn log(&mut self, state: &State, logged: &mut usize) {
+        if let Some(on_log) = &mut self.on_log {
+            state.log.iter().skip(*logged).for_each(on_log);
+        }
+        *logged = state.log.len();
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RunResult {
+    pub stack: Stack,
+    pub ravenmind: Option<Rc<dyn Iota>>,
+    //lines printed by Reveal and Make Note
+    pub output: Vec<String>,
+    pub log: Vector<LogEntry>,
+    pub media_spent: f64,
+    //every error in the order it happened. If the hex didn't finish, the last one stopped it
+    pub errors: Vec<Diagnostic>,
+    pub finished: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DiagnosticKind {
+    Parse,
+    Mishap,
+}
+
+//the same information as the errors printed by the command line, see `--message-format=json`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub kind: DiagnosticKind,
+    pub message: String,
+    pub hint: Option<String>,
+    pub caused_by: Option<String>,
+    pub file: Option<String>,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+}
+
+impl Diagnostic {
+    pub fn from_parse_error(err: &Error<Rule>) -> Diagnostic {
+        let (line, column) = match err.line_col {
+            LineColLocation::Pos(pos) | LineColLocation::Span(pos, _) => pos,
+        };
+
+        Diagnostic {
+            kind: DiagnosticKind::Parse,
+            message: err.variant.message().to_string(),
+            hint: None,
+            caused_by: None,
+            file: err.path().map(str::to_string),
+            line: Some(line),
+            column: Some(column),
+        }
+    }
+
+    pub fn from_mishap(
+        (mishap, location, caused_by): &(Mishap, Location, String),
+        sources: &[SourceFile],
+    ) -> Diagnostic {
+        let (file, line, column) = match location {
+            Location::Line(line, col) => (Some(&sources[0].path), Some(*line), Some(*col)),
+            Location::File(file, line, col) => {
+                (Some(&sources[*file].path), Some(*line), Some(*col))
+            }
+            Location::Unknown | Location::List(_) => (None, None, None),
+        };
+
+        Diagnostic {
+            kind: DiagnosticKind::Mishap,
+            message: mishap.error_message(),
+            hint: mishap.error_hint(),
+            caused_by: Some(caused_by.clone()).filter(|caused_by| !caused_by.is_empty()),
+            file: file.cloned(),
+            line,
+            column,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use crate::interpreter::state::Limits;
+
+    use super::*;
+
+    #[test]
+    fn run_hex_test() {
+        let config = Config::default();
+        let output = RefCell::new(vec![]);
+        let hooks = Hooks::default().on_log(|entry| output.borrow_mut().push(entry.display()));
+
+        let result = run_hex(
+            "Numerical Reflection: 1\nReveal\nNumerical Reflection: 2",
+            &config,
+            hooks,
+        );
+        assert!(result.finished && result.errors.is_empty());
+        assert_eq!(result.stack.display(), "[1.000, 2.000]");
+        assert_eq!(result.output, vec!["1.000"]);
+        assert_eq!(*output.borrow(), vec!["1.000"]);
+
+        let result = run_hex(
+            "Numerical Reflection: 1\nAdditive Distillation",
+            &config,
+            Hooks::default(),
+        );
+        assert!(!result.finished);
+        assert_eq!(result.errors[0].line, Some(2));
+        assert_eq!(
+            result.errors[0].caused_by.as_deref(),
+            Some("Additive Distillation")
+        );
+
+        let result = run_hex(
+            "<\\[EAST aqaaeeaww, EAST deddw, Additive Distillation]>\nHermes' Gambit",
+            &config,
+            Hooks::default(),
+        );
+        assert_eq!(result.stack.display(), "[41.000]");
+
+        let result = run_hex("Numerical Reflection:", &config, Hooks::default());
+        assert_eq!(result.errors[0].kind, DiagnosticKind::Parse);
+
+        //stop a hex that would otherwise run until it hits the op limit
+        let mut steps = 0;
+        let hooks = Hooks::default().on_step(|_| {
+            steps += 1;
+            steps < 10
+        });
+        let result = run_hex(
+            "while {True Reflection} do {\n    Numerical Reflection: 1\n}",
+            &config,
+            hooks,
+        );
+        assert_eq!(
+            result.errors[0].message,
+            "The hex was stopped before it finished"
+        );
+
+        //a cyclic wisp never stops on its own, but the hook and the limits still apply to it
+        let wisp = "Vacant Reflection\nVector Reflection Zero\nNumerical Reflection: 10\nSummon Cyclic Wisp";
+        let mut ticks = 0;
+        let hooks = Hooks::default().on_step(|state| {
+            ticks += state.continuation.is_empty() as usize;
+            ticks < 10
+        });
+        let result = run_hex(wisp, &config, hooks);
+        assert_eq!(
+            result.errors[0].message,
+            "The hex was stopped before it finished"
+        );
+
+        let config = Config {
+            limits: Limits {
+                max_ops: 1000,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let result = run_hex(wisp, &config, Hooks::default());
+        assert!(result.errors[0].message.contains("1000"));
+    }
+}
@@ -0,0 +1,517 @@
+use std::{collections::HashMap, fmt::Display, rc::Rc};
+
+use crate::{
+    interpreter::mishap::Mishap,
+    iota::{
+        hex_casting::{
+            bool::BooleanIota, entity::EntityIota, garbage::GarbageIota, list::ListIota,
+            null::NullIota, number::NumberIota, pattern::PatternIota, vector::VectorIota,
+        },
+        more_iotas::{matrix::MatrixIota, string::StringIota},
+        Iota,
+    },
+    parser::{expand_macro, ActionValue, AstNode, Location, Macros, OpName, OpValue},
+    pattern_registry::{PatternRegistry, PatternRegistryExt},
+};
+
+pub type CheckError = (Mishap, Location, String);
+
+//walks the ast with the stack signatures of every pattern and reports any
+//iotas that would be the wrong type or missing when the hex is cast
+pub fn check(
+    node: &AstNode,
+    pattern_registry: &PatternRegistry,
+    macros: &Macros,
+) -> Vec<CheckError> {
+    let mut checker = Checker {
+        pattern_registry,
+        macros,
+        vars: HashMap::new(),
+        errors: vec![],
+    };
+    let mut stack = TypeStack::default();
+
+    checker.check_node(node, &mut stack);
+
+    checker.errors
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IotaType {
+    Number,
+    Vector,
+    Boolean,
+    Entity,
+    List,
+    Pattern,
+    Null,
+    Garbage,
+    String,
+    Matrix,
+}
+
+impl Display for IotaType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IotaType::Number => write!(f, "Number"),
+            IotaType::Vector => write!(f, "Vector"),
+            IotaType::Boolean => write!(f, "Boolean"),
+            IotaType::Entity => write!(f, "Entity"),
+            IotaType::List => write!(f, "List"),
+            IotaType::Pattern => write!(f, "Pattern"),
+            IotaType::Null => write!(f, "Null"),
+            IotaType::Garbage => write!(f, "Garbage"),
+            IotaType::String => write!(f, "String"),
+            IotaType::Matrix => write!(f, "Matrix"),
+        }
+    }
+}
+
+//the possible types of a single iota on the stack
+#[derive(Debug, Clone, PartialEq)]
+pub enum StackType {
+    Any,
+    OneOf(Vec<IotaType>),
+}
+
+impl StackType {
+    fn of(iota_type: IotaType) -> StackType {
+        StackType::OneOf(vec![iota_type])
+    }
+
+    fn from_iota(iota: &Rc<dyn Iota>) -> StackType {
+        let iota_type = if iota.downcast_ref::<NumberIota>().is_some() {
+            IotaType::Number
+        } else if iota.downcast_ref::<VectorIota>().is_some() {
+            IotaType::Vector
+        } else if iota.downcast_ref::<BooleanIota>().is_some() {
+            IotaType::Boolean
+        } else if iota.downcast_ref::<EntityIota>().is_some() {
+            IotaType::Entity
+        } else if iota.downcast_ref::<ListIota>().is_some() {
+            IotaType::List
+        } else if iota.downcast_ref::<PatternIota>().is_some() {
+            IotaType::Pattern
+        } else if iota.downcast_ref::<NullIota>().is_some() {
+            IotaType::Null
+        } else if iota.downcast_ref::<GarbageIota>().is_some() {
+            IotaType::Garbage
+        } else if iota.downcast_ref::<StringIota>().is_some() {
+            IotaType::String
+        } else if iota.downcast_ref::<MatrixIota>().is_some() {
+            IotaType::Matrix
+        } else {
+            return StackType::Any;
+        };
+        StackType::of(iota_type)
+    }
+
+    //parses an argument from the pattern data, eg. "num/vec" or "entity | null"
+    pub fn from_arg(arg: &str) -> StackType {
+        let arg = arg.trim().to_lowercase();
+        let arg = arg.trim_start_matches('(').trim_end_matches(')');
+
+        let mut types = vec![];
+        for option in arg.split(['|', '/']).flat_map(|option| option.split(" or ")) {
+            let option = option.trim();
+            let iota_type = match option {
+                "num" | "number" | "double" => IotaType::Number,
+                _ if option.starts_with("int") => IotaType::Number,
+                "vec" | "vector" | "pos" => IotaType::Vector,
+                "bool" => IotaType::Boolean,
+                "entity" | "villager" => IotaType::Entity,
+                "pattern" => IotaType::Pattern,
+                "str" => IotaType::String,
+                "mat" => IotaType::Matrix,
+                "null" => IotaType::Null,
+                _ if option.starts_with('[') || option.starts_with("list") => IotaType::List,
+                //types the checker doesn't know about (motes, items, etc.) accept anything
+                _ => return StackType::Any,
+            };
+            if !types.contains(&iota_type) {
+                types.push(iota_type);
+            }
+        }
+
+        StackType::OneOf(types)
+    }
+
+    fn fits(&self, expected: &StackType) -> bool {
+        match (self, expected) {
+            (StackType::OneOf(types), StackType::OneOf(expected)) => {
+                types.iter().any(|iota_type| expected.contains(iota_type))
+            }
+            _ => true,
+        }
+    }
+
+    fn union(&self, other: &StackType) -> StackType {
+        match (self, other) {
+            (StackType::OneOf(types), StackType::OneOf(other)) => {
+                let mut types = types.clone();
+                for iota_type in other {
+                    if !types.contains(iota_type) {
+                        types.push(*iota_type);
+                    }
+                }
+                StackType::OneOf(types)
+            }
+            _ => StackType::Any,
+        }
+    }
+}
+
+impl Display for StackType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StackType::Any => write!(f, "Any"),
+            StackType::OneOf(types) => write!(
+                f,
+                "{}",
+                types
+                    .iter()
+                    .map(IotaType::to_string)
+                    .collect::<Vec<_>>()
+                    .join("|")
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+struct TypeStack {
+    known: Vec<StackType>,
+    //set once the checker loses track of the stack, eg. after Flock's Disintegration
+    unknown_below: bool,
+}
+
+impl TypeStack {
+    fn unknown() -> TypeStack {
+        TypeStack {
+            known: vec![],
+            unknown_below: true,
+        }
+    }
+
+    fn pop_n(&mut self, count: usize) -> Vec<StackType> {
+        let split = self.known.len().saturating_sub(count);
+        let mut popped = vec![StackType::Any; count - (self.known.len() - split)];
+        popped.append(&mut self.known.split_off(split));
+        popped
+    }
+
+    fn merge(&self, other: &TypeStack) -> TypeStack {
+        if self.known.len() != other.known.len() || self.unknown_below != other.unknown_below {
+            return TypeStack::unknown();
+        }
+        TypeStack {
+            known: self
+                .known
+                .iter()
+                .zip(&other.known)
+                .map(|(a, b)| a.union(b))
+                .collect(),
+            unknown_below: self.unknown_below,
+        }
+    }
+}
+
+struct Checker<'a> {
+    pattern_registry: &'a PatternRegistry,
+    macros: &'a Macros,
+    vars: HashMap<String, StackType>,
+    errors: Vec<CheckError>,
+}
+
+impl Checker<'_> {
+    fn check_node(&mut self, node: &AstNode, stack: &mut TypeStack) {
+        match node {
+            AstNode::Program(nodes) => self.check_nodes(nodes, stack),
+
+            AstNode::Action {
+                location,
+                name,
+                value,
+            } => {
+                if let Some((_, params, body)) = self.macros.get(name) {
+                    self.check_macro(expand_macro(params, body, &[]), location, name, stack)
+                } else {
+                    self.check_action(name, value, location, stack)
+                }
+            }
+
+            AstNode::MacroCall {
+                location,
+                name,
+                args,
+            } => {
+                let expanded = self
+                    .macros
+                    .get(name)
+                    .ok_or(Mishap::InvalidPattern)
+                    .and_then(|(_, params, body)| expand_macro(params, body, args));
+                self.check_macro(expanded, location, name, stack)
+            }
+
+            //the contents of a block are run later with a stack we know nothing about
+            AstNode::Block { nodes, .. } => {
+                self.check_nodes(nodes, &mut TypeStack::unknown());
+                stack.known.push(StackType::of(IotaType::List));
+            }
+
+            AstNode::Op {
+                location: _,
+                name,
+                arg,
+            } => self.check_op(name, arg, stack),
+
+            AstNode::IfBlock {
+                location,
+                condition,
+                succeed,
+                fail,
+            } => {
+                self.check_inline(condition, stack);
+                self.pop_args("if", &[StackType::of(IotaType::Boolean)], location, stack);
+
+                let mut succeed_stack = stack.clone();
+                self.check_inline(succeed, &mut succeed_stack);
+
+                let mut fail_stack = stack.clone();
+                if let Some(fail) = fail {
+                    self.check_inline(fail, &mut fail_stack);
+                }
+
+                *stack = succeed_stack.merge(&fail_stack);
+            }
+
+            AstNode::WhileBlock {
+                location,
+                condition,
+                block,
+                do_while: _,
+            } => {
+                self.check_inline(condition, stack);
+                self.pop_args("while", &[StackType::of(IotaType::Boolean)], location, stack);
+
+                let mut block_stack = stack.clone();
+                self.check_inline(block, &mut block_stack);
+
+                *stack = stack.merge(&block_stack);
+            }
+        }
+    }
+
+    fn check_nodes(&mut self, nodes: &[AstNode], stack: &mut TypeStack) {
+        for node in nodes {
+            self.check_node(node, stack);
+        }
+    }
+
+    //checks the nodes of a block as if they were run in place
+    fn check_inline(&mut self, node: &AstNode, stack: &mut TypeStack) {
+        match node {
+            AstNode::Block { nodes, .. } => self.check_nodes(nodes, stack),
+            node => self.check_node(node, stack),
+        }
+    }
+
+    fn check_macro(
+        &mut self,
+        expanded: Result<Vec<AstNode>, Mishap>,
+        location: &Location,
+        name: &str,
+        stack: &mut TypeStack,
+    ) {
+        match expanded {
+            Ok(nodes) => self.check_nodes(&nodes, stack),
+            Err(mishap) => {
+                self.errors.push((mishap, *location, name.to_string()));
+                *stack = TypeStack::unknown();
+            }
+        }
+    }
+
+    fn check_action(
+        &mut self,
+        name: &str,
+        value: &Option<ActionValue>,
+        location: &Location,
+        stack: &mut TypeStack,
+    ) {
+        let Some(pattern) = self.pattern_registry.find(name, value) else {
+            self.errors
+                .push((Mishap::InvalidPattern, *location, name.to_string()));
+            *stack = TypeStack::unknown();
+            return;
+        };
+        let display_name = pattern.display_name.as_str();
+        let any = |count| vec![StackType::Any; count];
+
+        //stack manipulation patterns keep the types of the iotas they move around
+        let shuffle: Option<(usize, &[usize])> = match pattern.internal_name.as_str() {
+            "duplicate" => Some((1, &[0, 0])),
+            "swap" => Some((2, &[1, 0])),
+            "rotate" => Some((3, &[1, 2, 0])),
+            "rotate_reverse" => Some((3, &[2, 0, 1])),
+            "over" => Some((2, &[0, 1, 0])),
+            "tuck" => Some((2, &[1, 0, 1])),
+            "2dup" => Some((2, &[0, 1, 0, 1])),
+            "print" => Some((1, &[0])),
+            _ => None,
+        };
+        if let Some((arg_count, order)) = shuffle {
+            let args = self.pop_args(display_name, &any(arg_count), location, stack);
+            stack
+                .known
+                .extend(order.iter().map(|index| args[*index].clone()));
+            return;
+        }
+
+        if let ("mask", Some(ActionValue::Bookkeeper(code))) = (pattern.internal_name.as_str(), value)
+        {
+            let args = self.pop_args(display_name, &any(code.len()), location, stack);
+            stack.known.extend(
+                args.into_iter()
+                    .zip(code.chars())
+                    .filter(|(_, keep)| *keep == '-')
+                    .map(|(arg, _)| arg),
+            );
+            return;
+        }
+
+        let Some(args) = &pattern.args else {
+            *stack = TypeStack::unknown();
+            return;
+        };
+        if args.arity().is_none() {
+            *stack = TypeStack::unknown();
+            return;
+        }
+
+        let inputs = args
+            .inputs
+            .iter()
+            .map(|arg| StackType::from_arg(arg))
+            .collect::<Vec<_>>();
+        self.pop_args(display_name, &inputs, location, stack);
+
+        match args.output_count() {
+            Some(_) => stack
+                .known
+                .extend(args.outputs.iter().map(|arg| StackType::from_arg(arg))),
+            None => *stack = TypeStack::unknown(),
+        }
+    }
+
+    fn check_op(&mut self, name: &OpName, arg: &Option<OpValue>, stack: &mut TypeStack) {
+        match (name, arg) {
+            (OpName::Store, Some(OpValue::Var(var))) => {
+                let iota_type = stack.pop_n(1).remove(0);
+                self.vars.insert(var.clone(), iota_type);
+            }
+            (OpName::Copy, Some(OpValue::Var(var))) => {
+                let iota_type = stack.known.last().cloned().unwrap_or(StackType::Any);
+                self.vars.insert(var.clone(), iota_type);
+            }
+            (OpName::Push, Some(OpValue::Var(var))) => {
+                let iota_type = self.vars.get(var).cloned().unwrap_or(StackType::Any);
+                stack.known.push(iota_type);
+            }
+            (
+                OpName::Embed | OpName::SmartEmbed | OpName::IntroEmbed | OpName::ConsiderEmbed,
+                Some(OpValue::Iota(iota)),
+            ) => stack.known.push(StackType::from_iota(iota)),
+            //malformed ops are reported by the compiler
+            _ => (),
+        }
+    }
+
+    fn pop_args(
+        &mut self,
+        pattern_name: &str,
+        expected: &[StackType],
+        location: &Location,
+        stack: &mut TypeStack,
+    ) -> Vec<StackType> {
+        let arg_count = expected.len();
+        if stack.known.len() < arg_count && !stack.unknown_below {
+            self.errors.push((
+                Mishap::NotEnoughIotas {
+                    arg_count,
+                    stack_height: stack.known.len(),
+                },
+                *location,
+                pattern_name.to_string(),
+            ));
+        }
+
+        let args = stack.pop_n(arg_count);
+        for (index, (received, expected)) in args.iter().zip(expected).enumerate() {
+            if !received.fits(expected) {
+                self.errors.push((
+                    Mishap::IncorrectIotaType {
+                        pattern: pattern_name.to_string(),
+                        index,
+                        expected: expected.to_string(),
+                        received: received.to_string(),
+                    },
+                    *location,
+                    pattern_name.to_string(),
+                ));
+            }
+        }
+        args
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    fn check_source(source: &str) -> Vec<CheckError> {
+        let registry = PatternRegistry::construct(&PatternRegistry::gen_default_great_sigs());
+        let (ast, macros, _) = parse(source, "", &registry, &mut HashMap::new()).unwrap();
+        check(&ast, &registry, &macros)
+    }
+
+    #[test]
+    fn check_test() {
+        assert!(check_source("Mind's Reflection\nCompass' Purification").is_empty());
+
+        let errors = check_source(
+            "Mind's Reflection\nNumerical Reflection: 1\nJester's Gambit\nAdditive Distillation",
+        );
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].0.error_message(),
+            "Additive Distillation expects Number|Vector at index 1 but stack has Entity"
+        );
+        assert_eq!(errors[0].1, Location::Line(4, 1));
+
+        let errors = check_source("Numerical Reflection: 1\nAdditive Distillation");
+        assert!(matches!(
+            errors[0].0,
+            Mishap::NotEnoughIotas {
+                arg_count: 2,
+                stack_height: 1
+            }
+        ));
+
+        //Dioscuri Gambit copies the types of the top two iotas
+        let errors = check_source(
+            "Mind's Reflection\nNumerical Reflection: 1\nDioscuri Gambit\nAdditive Distillation\nAdditive Distillation",
+        );
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].1, Location::Line(4, 1));
+        assert_eq!(
+            errors[0].0.error_message(),
+            "Additive Distillation expects Number|Vector at index 0 but stack has Entity"
+        );
+
+        //the checker can't know what Flock's Disintegration leaves on the stack
+        assert!(check_source("Vacant Reflection\nFlock's Disintegration\nAdditive Distillation")
+            .is_empty());
+    }
+}
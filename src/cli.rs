@@ -0,0 +1,320 @@
+use std::process::ExitCode;
+
+use crate::compiler::nbt::HexItem;
+
+pub const HELP: &str = "\
+Hexagon - a programming language for Hex Casting
+
+Usage:
+  hexagon run [OPTIONS] <FILE>
+  hexagon build [OPTIONS] <FILE>
+  hexagon check [OPTIONS] <FILE>
+  hexagon decompile [OPTIONS] <FILE>
+  hexagon render [OPTIONS] <FILE>
+  hexagon test [OPTIONS] [PATH]
+  hexagon debug [OPTIONS] <FILE>
+  hexagon repl [OPTIONS]
+  hexagon dap [OPTIONS]
+  hexagon lsp [OPTIONS]
+  hexagon send [OPTIONS] <URL> <FILE>
+
+Commands:
+  run      Interpret a hex
+  build    Compile a hex
+  check    Check a hex for stack type errors without running it
+  decompile
+           Turn SNBT or JSON of a hex back into source, '-' reads stdin
+  render   Draw the patterns of a hex as an SVG image
+  test     Run the annotated hexes in a directory and compare their results
+  debug    Step through a hex in an interactive debugger
+  repl     Run patterns a line at a time, keeping the stack between lines
+  dap      Start a Debug Adapter Protocol server on stdin/stdout
+  lsp      Start a Language Server Protocol server on stdin/stdout
+  send     Compile a hex and send it to Hex Server
+
+Options:
+  -c, --config <PATH>      Config file to use [default: config.toml]
+  -o, --output <PATH>      Write the result to a file instead of stdout
+  -f, --format <FORMAT>    Output format of 'build' [default: give]
+                           [possible values: give, give-trinket, give-artifact,
+                           give-cypher, give-spellbook, snbt, json, hexpattern]
+      --message-format <FORMAT>
+                           How errors are printed [default: human]
+                           [possible values: human, json]
+      --in-game-mishaps    Keep casting after a mishap like the mod does
+      --seed <SEED>        Seed for randomised patterns, to replay a run
+  -q, --quiet              Only print the result and errors
+  -h, --help               Print help
+  -V, --version            Print version
+
+Exit codes:
+  0  success
+  1  the hex mishapped while compiling or running, or failed its check
+  2  invalid command line arguments
+  3  the hex or the config could not be parsed
+  4  the hex could not be sent
+  5  a file could not be read or written";
+
+//exit codes are part of the public interface, so scripts can tell failures apart
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExitStatus {
+    Success = 0,
+    Mishap = 1,
+    Usage = 2,
+    Parse = 3,
+    Network = 4,
+    Io = 5,
+}
+
+impl From<ExitStatus> for ExitCode {
+    fn from(status: ExitStatus) -> Self {
+        ExitCode::from(status as u8)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Run,
+    Build,
+    Check,
+    Decompile,
+    Render,
+    Test,
+    Debug,
+    Repl,
+    Dap,
+    Lsp,
+    Send { url: String },
+    Help,
+    Version,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    Give(HexItem),
+    Snbt,
+    Json,
+    HexPattern,
+}
+
+impl Default for OutputFormat {
+    fn default() -> Self {
+        OutputFormat::Give(HexItem::Focus)
+    }
+}
+
+impl OutputFormat {
+    fn parse(format: &str) -> Result<OutputFormat, String> {
+        match format {
+            "give" | "give-focus" => Ok(OutputFormat::Give(HexItem::Focus)),
+            "give-trinket" => Ok(OutputFormat::Give(HexItem::Trinket)),
+            "give-artifact" => Ok(OutputFormat::Give(HexItem::Artifact)),
+            "give-cypher" => Ok(OutputFormat::Give(HexItem::Cypher)),
+            "give-spellbook" => Ok(OutputFormat::Give(HexItem::Spellbook)),
+            "snbt" => Ok(OutputFormat::Snbt),
+            "json" => Ok(OutputFormat::Json),
+            "hexpattern" => Ok(OutputFormat::HexPattern),
+            _ => Err(format!(
+                "invalid format '{format}' (expected one of: give, give-trinket, give-artifact, give-cypher, give-spellbook, snbt, json, hexpattern)"
+            )),
+        }
+    }
+}
+
+//json prints every error to stderr as a single line JSON object, for tools
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum MessageFormat {
+    #[default]
+    Human,
+    Json,
+}
+
+impl MessageFormat {
+    fn parse(format: &str) -> Result<MessageFormat, String> {
+        match format {
+            "human" => Ok(MessageFormat::Human),
+            "json" => Ok(MessageFormat::Json),
+            _ => Err(format!(
+                "invalid message format '{format}' (expected one of: human, json)"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Args {
+    pub command: Command,
+    pub source_path: String,
+    pub config_path: Option<String>,
+    pub output_path: Option<String>,
+    pub format: OutputFormat,
+    pub message_format: MessageFormat,
+    pub in_game_mishaps: bool,
+    pub seed: Option<u64>,
+    pub quiet: bool,
+}
+
+impl Args {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Args, String> {
+        let mut args = args.into_iter();
+
+        let mut command = None;
+        let mut positional = vec![];
+        let mut config_path = None;
+        let mut output_path = None;
+        let mut format = OutputFormat::default();
+        let mut message_format = MessageFormat::default();
+        let mut in_game_mishaps = false;
+        let mut seed = None;
+        let mut quiet = false;
+
+        while let Some(arg) = args.next() {
+            //support both "--flag value" and "--flag=value"
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if arg.starts_with("--") => (flag.to_string(), Some(value)),
+                _ => (arg.clone(), None),
+            };
+            let mut value = |name: &str| {
+                inline_value
+                    .map(str::to_string)
+                    .or_else(|| args.next())
+                    .ok_or(format!("expected a value for {name}"))
+            };
+
+            match &flag[..] {
+                "-h" | "--help" => command = Some(Command::Help),
+                "-V" | "--version" => command = Some(Command::Version),
+                "-q" | "--quiet" => quiet = true,
+                "--in-game-mishaps" => in_game_mishaps = true,
+                "-c" | "--config" => config_path = Some(value(&flag)?),
+                "-o" | "--output" => output_path = Some(value(&flag)?),
+                "-f" | "--format" => format = OutputFormat::parse(&value(&flag)?)?,
+                "--message-format" => message_format = MessageFormat::parse(&value(&flag)?)?,
+                "--seed" => {
+                    let value = value(&flag)?;
+                    seed = Some(
+                        value
+                            .parse()
+                            .map_err(|_| format!("invalid seed '{value}' (expected a number)"))?,
+                    )
+                }
+                _ if flag.starts_with('-') && flag.len() > 1 => {
+                    Err(format!("unexpected argument '{flag}'"))?
+                }
+                _ => positional.push(arg),
+            }
+        }
+
+        let mut positional = positional.into_iter();
+        let cmd = positional.next();
+
+        let command = match (command, cmd.as_deref()) {
+            (Some(command), _) => command,
+            (None, Some("run")) => Command::Run,
+            (None, Some("build")) => Command::Build,
+            (None, Some("check")) => Command::Check,
+            (None, Some("decompile")) => Command::Decompile,
+            (None, Some("render")) => Command::Render,
+            (None, Some("test")) => Command::Test,
+            (None, Some("debug")) => Command::Debug,
+            (None, Some("repl")) => Command::Repl,
+            (None, Some("dap")) => Command::Dap,
+            (None, Some("lsp")) => Command::Lsp,
+            (None, Some("send")) => Command::Send {
+                url: positional.next().ok_or("expected a url")?,
+            },
+            (None, Some("help")) => Command::Help,
+            (None, Some(other)) => Err(format!("invalid command '{other}'"))?,
+            (None, None) => Err("expected a command")?,
+        };
+
+        //the editor picks the files to work on, and the repl reads code from stdin
+        if let Command::Help | Command::Version | Command::Dap | Command::Lsp | Command::Repl =
+            command
+        {
+            return Ok(Args {
+                command,
+                source_path: String::new(),
+                config_path,
+                output_path,
+                format,
+                message_format,
+                in_game_mishaps,
+                seed,
+                quiet,
+            });
+        }
+
+        let source_path = match (&command, positional.next()) {
+            (_, Some(path)) => path,
+            //tests are looked for in the current directory by default
+            (Command::Test, None) => ".".to_string(),
+            (_, None) => Err("expected a file path")?,
+        };
+
+        //older versions took the config path as a trailing positional argument
+        if let Some(legacy_config_path) = positional.next() {
+            config_path = config_path.or(Some(legacy_config_path));
+        }
+
+        if let Some(extra) = positional.next() {
+            Err(format!("unexpected argument '{extra}'"))?
+        }
+
+        Ok(Args {
+            command,
+            source_path,
+            config_path,
+            output_path,
+            format,
+            message_format,
+            in_game_mishaps,
+            seed,
+            quiet,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Args, String> {
+        Args::parse(args.split_whitespace().map(str::to_string))
+    }
+
+    #[test]
+    fn parse_args_test() {
+        let args =
+            parse("send --quiet http://localhost:9000/hexPost hex.hexagon --config=a.toml").unwrap();
+        assert_eq!(
+            args.command,
+            Command::Send {
+                url: "http://localhost:9000/hexPost".to_string()
+            }
+        );
+        assert_eq!(args.source_path, "hex.hexagon");
+        assert_eq!(args.config_path, Some("a.toml".to_string()));
+        assert!(args.quiet);
+
+        let args = parse("build hex.hexagon old_config.toml --output out.txt").unwrap();
+        assert_eq!(args.config_path, Some("old_config.toml".to_string()));
+        assert_eq!(args.output_path, Some("out.txt".to_string()));
+
+        assert_eq!(parse("run --help").unwrap().command, Command::Help);
+        assert!(parse("run").is_err());
+        assert!(parse("compile hex.hexagon").is_err());
+        let args = parse("build hex.hexagon -f give-cypher").unwrap();
+        assert_eq!(args.format, OutputFormat::Give(HexItem::Cypher));
+        assert!(parse("run hex.hexagon --format nbt").is_err());
+        let args = parse("check hex.hexagon --message-format=json").unwrap();
+        assert_eq!(args.message_format, MessageFormat::Json);
+        assert!(parse("run hex.hexagon --in-game-mishaps").unwrap().in_game_mishaps);
+        assert_eq!(parse("run hex.hexagon --seed 42").unwrap().seed, Some(42));
+        assert_eq!(parse("test").unwrap().source_path, ".");
+        assert_eq!(parse("repl").unwrap().command, Command::Repl);
+        assert_eq!(parse("decompile -").unwrap().source_path, "-");
+        assert_eq!(parse("render hex.hexagon").unwrap().command, Command::Render);
+        assert!(parse("run hex.hexagon --seed=abc").is_err());
+    }
+}
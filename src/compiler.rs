@@ -2,8 +2,11 @@ use std::{collections::HashMap, rc::Rc};
 
 use crate::{
     interpreter::{mishap::Mishap, ops::EmbedType},
-    iota::{hex_casting::pattern::PatternIota, Iota},
-    parser::{ActionValue, AstNode, Location, Macros, OpName},
+    iota::{
+        hex_casting::{number::NumberIota, pattern::PatternIota},
+        Iota,
+    },
+    parser::{expand_macro, ActionValue, AstNode, Location, Macros, OpName},
     pattern_registry::{PatternRegistry, PatternRegistryExt},
 };
 
@@ -11,15 +14,20 @@ use self::{
     external::compile_external,
     if_block::compile_if_block,
     init_heap::init_heap,
+    number::{check_embedded_numbers, compile_number},
     ops::{compile_op_copy, compile_op_embed, compile_op_init, compile_op_push, compile_op_store},
     while_block::{compile_do_while_block, compile_while_block},
 };
 
 pub mod external;
+pub mod hexpattern;
 pub mod if_block;
 pub mod init_heap;
+pub mod json;
 pub mod nbt;
+pub mod number;
 pub mod ops;
+pub mod svg;
 pub mod while_block;
 
 pub fn compile_to_iotas(
@@ -48,39 +56,51 @@ pub fn compile_node(
     macros: &Macros,
 ) -> CompileResult {
     match node {
-        AstNode::Program(file) => {
-            let mut result = vec![];
-            for node in file {
-                result.append(&mut compile_node(
-                    node,
-                    heap,
-                    depth,
-                    pattern_registry,
-                    macros,
-                )?)
-            }
-            Ok(result)
-        }
+        AstNode::Program(file) => compile_nodes(file, heap, depth, pattern_registry, macros),
 
         AstNode::Action {
             location,
             name,
             value,
         } => {
-            if let Some((_, AstNode::Block { external: _, nodes })) = macros.get(name) {
+            if let Some((_, params, body)) = macros.get(name) {
+                let nodes = expand_macro(params, body, &[])
+                    .map_err(|mishap| (mishap, location.clone(), name.clone()))?;
                 compile_node(
-                    &AstNode::Program(nodes.clone()),
+                    &AstNode::Program(nodes),
                     heap,
                     depth,
                     pattern_registry,
                     macros,
                 )
             } else {
-                Ok(vec![{
-                    let pattern = pattern_registry
-                        .find(name, value)
-                        .ok_or((Mishap::InvalidPattern, location.clone(), name.clone()))?;
+                let pattern = pattern_registry
+                    .find(name, value)
+                    .ok_or((Mishap::InvalidPattern, location.clone(), name.clone()))?;
 
+                if pattern.internal_name == "number" {
+                    let start_dir = pattern.start_dir.unwrap_or_default();
+                    let invalid_value = |received| Mishap::InvalidValue {
+                        expected: "Number".to_string(),
+                        received,
+                    };
+                    return match value {
+                        Some(ActionValue::Iota(iota)) => match iota.downcast_ref::<NumberIota>() {
+                            Some(number) => {
+                                compile_number(*number, start_dir, pattern_registry, location)
+                            }
+                            None => Err(invalid_value(iota.display())),
+                        },
+                        Some(ActionValue::Bookkeeper(code)) => Err(invalid_value(code.clone())),
+                        None => Err(Mishap::ExpectedValue {
+                            caused_by: pattern.display_name,
+                            expected: "Number".to_string(),
+                        }),
+                    }
+                    .map_err(|mishap| (mishap, *location, name.clone()));
+                }
+
+                check_emitted(vec![{
                     //remove output values used by the interpreter
                     //once signature generation exists for number, all values can be ignored
                     let new_value =
@@ -89,12 +109,11 @@ pub fn compile_node(
                         } else {
                             value.clone()
                         };
-                    Rc::new(PatternIota::from_sig(
-                        &pattern.signature,
-                        new_value,
-                        location.clone(),
-                    ))
-                }])
+                    Rc::new(PatternIota {
+                        start_dir: pattern.start_dir.unwrap_or_default(),
+                        ..PatternIota::from_sig(&pattern.signature, new_value, location.clone())
+                    })
+                }], location, name)
             }
         }
 
@@ -137,7 +156,27 @@ pub fn compile_node(
                 compile_op_embed(pattern_registry, depth, arg, EmbedType::Consider)
             }
         }
-        .map_err(|mishap| (mishap, location.clone(), name.to_string())),
+        .map_err(|mishap| (mishap, location.clone(), name.to_string()))
+        .and_then(|iotas| check_emitted(iotas, location, &name.to_string())),
+
+        AstNode::MacroCall {
+            location,
+            name,
+            args,
+        } => {
+            let nodes = macros
+                .get(name)
+                .ok_or(Mishap::InvalidPattern)
+                .and_then(|(_, params, body)| expand_macro(params, body, args))
+                .map_err(|mishap| (mishap, location.clone(), name.clone()))?;
+            compile_node(
+                &AstNode::Program(nodes),
+                heap,
+                depth,
+                pattern_registry,
+                macros,
+            )
+        }
 
         AstNode::IfBlock {
             location,
@@ -187,6 +226,48 @@ pub fn compile_node(
 
 pub type CompileResult = Result<Vec<Rc<dyn Iota>>, (Mishap, Location, String)>;
 
+fn compile_nodes(
+    nodes: &[AstNode],
+    heap: &mut HashMap<String, i32>,
+    depth: u32,
+    pattern_registry: &PatternRegistry,
+    macros: &Macros,
+) -> CompileResult {
+    let mut result = vec![];
+    let mut considered = false;
+    for node in nodes {
+        let mut compiled = compile_node(node, heap, depth, pattern_registry, macros)?;
+
+        //Consideration only escapes the pattern after it, so a number there has to be a single one
+        if let AstNode::Action { location, name, value: Some(ActionValue::Iota(iota)) } = node {
+            match iota.downcast_ref::<NumberIota>() {
+                Some(number) if considered && compiled.len() > 1 => {
+                    return Err((Mishap::NoNumberPattern { number: *number }, *location, name.clone()))
+                }
+                _ => (),
+            }
+        }
+        considered = match node {
+            AstNode::Action { name, value, .. } => pattern_registry
+                .find(name, value)
+                .is_some_and(|pattern| pattern.internal_name == "escape"),
+            _ => false,
+        };
+
+        result.append(&mut compiled);
+    }
+    Ok(result)
+}
+
+//every iota that ends up in the hex has to be drawn, including numbers nested in lists
+fn check_emitted(iotas: Vec<Rc<dyn Iota>>, location: &Location, name: &str) -> CompileResult {
+    iotas
+        .iter()
+        .try_for_each(check_embedded_numbers)
+        .map_err(|mishap| (mishap, *location, name.to_string()))?;
+    Ok(iotas)
+}
+
 fn compile_hex_node(
     hex: &Vec<AstNode>,
     heap: &mut HashMap<String, i32>,
@@ -198,16 +279,7 @@ fn compile_hex_node(
 
     let mut result: Vec<Rc<dyn Iota>> = vec![];
 
-    let mut inner = vec![];
-    for node in hex {
-        inner.append(&mut compile_node(
-            node,
-            heap,
-            depth,
-            pattern_registry,
-            macros,
-        )?)
-    }
+    let mut inner = compile_nodes(hex, heap, depth, pattern_registry, macros)?;
 
     result.push(Rc::new(
         PatternIota::from_name(pattern_registry, "open_paren", None, Location::Unknown).unwrap(),
@@ -0,0 +1,39 @@
+use std::rc::Rc;
+
+use crate::iota::{
+    hex_casting::{
+        list::ListIota,
+        pattern::{PatternIota, SignatureExt},
+    },
+    Iota,
+};
+
+//lists the angle signature of every pattern, one per line
+pub fn compile_hexpattern(iota_list: Vec<Rc<dyn Iota>>) -> String {
+    let mut lines = vec![];
+    for iota in &iota_list {
+        list_iota(iota, 0, &mut lines);
+    }
+    lines.join("\n")
+}
+
+fn list_iota(iota: &Rc<dyn Iota>, depth: usize, lines: &mut Vec<String>) {
+    let indent = "    ".repeat(depth);
+
+    if let Some(pattern) = iota.downcast_ref::<PatternIota>() {
+        lines.push(format!(
+            "{indent}{} {}",
+            pattern.start_dir,
+            pattern.signature.as_str()
+        ));
+    } else if let Some(list) = iota.downcast_ref::<ListIota>() {
+        lines.push(format!("{indent}["));
+        for inner in list {
+            list_iota(inner, depth + 1, lines);
+        }
+        lines.push(format!("{indent}]"));
+    } else {
+        //non-pattern iotas can't be drawn, so they are written the way they would be embedded
+        lines.push(format!("{indent}<{}>", iota.display()));
+    }
+}
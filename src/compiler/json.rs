@@ -0,0 +1,14 @@
+use std::rc::Rc;
+
+use serde_json::Value;
+
+use crate::iota::Iota;
+
+pub fn compile_json(iota_list: Vec<Rc<dyn Iota>>) -> String {
+    let iotas = iota_list
+        .iter()
+        .map(|iota| iota.serialize_to_json())
+        .collect::<Vec<_>>();
+
+    serde_json::to_string_pretty(&Value::Array(iotas)).unwrap()
+}
@@ -2,15 +2,56 @@ use std::rc::Rc;
 
 use crate::iota::Iota;
 
+//media given to packaged hex items, 64 amethyst dust
+const PACKAGED_HEX_MEDIA: u32 = 640000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum HexItem {
+    #[default]
+    Focus,
+    Trinket,
+    Artifact,
+    Cypher,
+    Spellbook,
+}
+
 pub fn compile_nbt(iota_list: Vec<Rc<dyn Iota>>) -> String {
     let out = serialize_hex(&iota_list);
     // print!("{iota_list:?}");
     format!("{out}")
 }
 
-pub fn gen_give_cmd(iota_list: Vec<Rc<dyn Iota>>) -> String {
-    let data = compile_nbt(iota_list);
-    format!("/give @p hexcasting:focus{{data: {data}}} 1")
+pub fn gen_give_cmd(iota_list: Vec<Rc<dyn Iota>>, item: HexItem) -> String {
+    match item {
+        HexItem::Focus => {
+            let data = compile_nbt(iota_list);
+            format!("/give @p hexcasting:focus{{data: {data}}} 1")
+        }
+        HexItem::Trinket => gen_packaged_hex_cmd("trinket", &iota_list),
+        HexItem::Artifact => gen_packaged_hex_cmd("artifact", &iota_list),
+        HexItem::Cypher => gen_packaged_hex_cmd("cypher", &iota_list),
+        HexItem::Spellbook => {
+            let page = serialize_list(&iota_list);
+            format!("/give @p hexcasting:spellbook{{pages: {{\"1\": {page}}}, page_idx: 1}} 1")
+        }
+    }
+}
+
+fn gen_packaged_hex_cmd(item: &str, iota_list: &Vec<Rc<dyn Iota>>) -> String {
+    let patterns = iota_list
+        .iter()
+        .map(|iota| iota.serialize_to_nbt())
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    format!(
+        "/give @p hexcasting:{item}{{patterns: [{patterns}], \"hexcasting:media\": {PACKAGED_HEX_MEDIA}, \"hexcasting:start_media\": {PACKAGED_HEX_MEDIA}}} 1"
+    )
+}
+
+fn serialize_list(iota_list: &Vec<Rc<dyn Iota>>) -> String {
+    let list = iota_list.iter().cloned().collect::<im::Vector<_>>();
+    list.serialize_to_nbt()
 }
 
 fn serialize_hex(iota_list: &Vec<Rc<dyn Iota>>) -> String {
@@ -0,0 +1,133 @@
+use std::rc::Rc;
+
+use crate::{
+    interpreter::mishap::Mishap,
+    iota::{
+        hex_casting::{
+            list::ListIota,
+            number::NumberIota,
+            pattern::{Direction, PatternIota},
+        },
+        Iota,
+    },
+    parser::{ActionValue, Location},
+    pattern_registry::{decode_number, numbers, PatternRegistry},
+};
+
+//a number that doesn't fit in a single Numerical Reflection is built from several
+pub fn compile_number(
+    number: NumberIota,
+    start_dir: Direction,
+    registry: &PatternRegistry,
+    location: &Location,
+) -> Result<Vec<Rc<dyn Iota>>, Mishap> {
+    let encoding = numbers::encode(number).ok_or(Mishap::NoNumberPattern { number })?;
+
+    encoding
+        .iter()
+        .map(|step| -> Result<Rc<dyn Iota>, Mishap> {
+            Ok(Rc::new(match decode_number(step) {
+                Some(value) => PatternIota {
+                    start_dir,
+                    ..PatternIota::from_sig(
+                        step,
+                        Some(ActionValue::Iota(Rc::new(value))),
+                        *location,
+                    )
+                },
+                None => PatternIota::from_name(registry, step, None, *location)?,
+            }))
+        })
+        .collect()
+}
+
+//embedded numbers have to be a single pattern, which the registry leaves empty if it can't find one
+pub fn check_embedded_numbers(iota: &Rc<dyn Iota>) -> Result<(), Mishap> {
+    if let Some(pattern) = iota.downcast_ref::<PatternIota>() {
+        if let Some(ActionValue::Iota(value)) = pattern.value.as_ref() {
+            match value.downcast_ref::<NumberIota>() {
+                Some(number) if pattern.signature.is_empty() => {
+                    Err(Mishap::NoNumberPattern { number: *number })?
+                }
+                _ => (),
+            }
+        }
+    } else if let Some(list) = iota.downcast_ref::<ListIota>() {
+        list.iter().try_for_each(check_embedded_numbers)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{
+        compiler::compile_node,
+        interpreter::interpret,
+        parse_config::Config,
+        iota::hex_casting::pattern::SignatureExt,
+        parser::parse,
+        pattern_registry::{numbers::evaluate, PatternRegistryExt},
+    };
+
+    use super::*;
+
+    fn compile(source: &str) -> Result<Vec<String>, Mishap> {
+        let registry = PatternRegistry::construct(&PatternRegistry::gen_default_great_sigs());
+        let (ast, macros, _) = parse(source, "", &registry, &mut HashMap::new()).unwrap();
+        let iotas =
+            compile_node(&ast, &mut HashMap::new(), 0, &registry, &macros).map_err(|err| err.0)?;
+        Ok(iotas
+            .iter()
+            .map(|iota| {
+                let signature = iota
+                    .downcast_ref::<PatternIota>()
+                    .unwrap()
+                    .signature
+                    .as_str();
+                match decode_number(&signature) {
+                    Some(_) => signature,
+                    None => registry.find(&signature, &None).unwrap().internal_name,
+                }
+            })
+            .collect())
+    }
+
+    #[test]
+    fn compile_number_test() {
+        //a tenth can't be halved out of an integer, so it's a division
+        let steps = compile("Numerical Reflection: 0.1").unwrap();
+        assert!(steps.len() > 1);
+        assert_eq!(evaluate(&steps), Some(0.1));
+
+        assert!(matches!(
+            compile("<\\[Numerical Reflection: 0.1]>"),
+            Err(Mishap::NoNumberPattern { .. })
+        ));
+        assert!(matches!(
+            compile("Numerical Reflection"),
+            Err(Mishap::ExpectedValue { .. })
+        ));
+
+        //Consideration would only escape the first of the patterns
+        assert!(matches!(
+            compile("Consideration\nNumerical Reflection: 0.1"),
+            Err(Mishap::NoNumberPattern { .. })
+        ));
+        assert!(compile("Consideration\nNumerical Reflection: 2").is_ok());
+
+        //a list built with introspection holds every pattern, the same as the compiled one
+        let config = Config::default();
+        let registry = PatternRegistry::from_config(&config);
+        let (ast, macros, _) = parse(
+            "{\n    Numerical Reflection: 0.1\n}\nAbacus Purification",
+            "",
+            &registry,
+            &mut HashMap::new(),
+        )
+        .unwrap();
+        let state = interpret(ast, &config, macros).unwrap();
+        assert_eq!(state.stack.display(), format!("[{}.000]", steps.len()));
+    }
+}
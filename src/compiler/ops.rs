@@ -1,5 +1,6 @@
 use std::{collections::HashMap, rc::Rc, vec};
 
+use super::number::check_embedded_numbers;
 use crate::{
     interpreter::{mishap::Mishap, ops::EmbedType},
     iota::{hex_casting::pattern::PatternIota, Iota},
@@ -157,6 +158,7 @@ pub fn compile_op_embed(
             Err(Mishap::OpExpectedIota)?
         }
     };
+    check_embedded_numbers(&iota)?;
 
     //handle smart embed
     let embed_type = match embed_type {
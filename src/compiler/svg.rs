@@ -0,0 +1,267 @@
+use std::rc::Rc;
+
+use crate::{
+    iota::{
+        hex_casting::{
+            list::ListIota,
+            pattern::{PatternIota, SignatureExt},
+        },
+        Iota,
+    },
+    parser::ActionValue,
+    pattern_registry::{PatternRegistry, PatternRegistryExt},
+};
+
+//pixels between two points of the grid when a single pattern is rendered
+const PATTERN_SCALE: f64 = 40.0;
+const CELL_WIDTH: f64 = 160.0;
+const CELL_HEIGHT: f64 = 140.0;
+const LABEL_HEIGHT: f64 = 24.0;
+const COLUMNS: usize = 8;
+
+//the line fades from the first colour to the second like in the hexbook
+const START_COLOR: (u8, u8, u8) = (0xa8, 0x1e, 0xe3);
+const END_COLOR: (u8, u8, u8) = (0x64, 0x90, 0xed);
+const DOT_COLOR: &str = "#c8c8d0";
+
+enum Cell<'a> {
+    Pattern(&'a PatternIota),
+    Text(String),
+}
+
+//a single pattern on the hex grid, sized to fit it
+pub fn render_pattern(pattern: &PatternIota) -> String {
+    let (min, max) = bounds(&points(pattern));
+    let width = (max.0 - min.0 + 2.0) * PATTERN_SCALE;
+    let height = (max.1 - min.1 + 2.0) * PATTERN_SCALE;
+
+    svg(
+        width,
+        height,
+        &draw_pattern(pattern, (0.0, 0.0), (width, height), PATTERN_SCALE),
+    )
+}
+
+//every pattern of a compiled hex in a grid, with its name underneath
+pub fn compile_svg(iota_list: Vec<Rc<dyn Iota>>, registry: &PatternRegistry) -> String {
+    let mut cells = vec![];
+    for iota in &iota_list {
+        list_cells(iota, &mut cells);
+    }
+
+    let columns = cells.len().clamp(1, COLUMNS);
+    let rows = cells.len().div_ceil(columns);
+    let mut body = String::new();
+
+    for (index, cell) in cells.iter().enumerate() {
+        let x = (index % columns) as f64 * CELL_WIDTH;
+        let y = (index / columns) as f64 * (CELL_HEIGHT + LABEL_HEIGHT);
+
+        body += &format!(
+            "<rect x=\"{:.2}\" y=\"{:.2}\" width=\"{CELL_WIDTH}\" height=\"{}\" fill=\"none\" stroke=\"#e0e0e8\"/>\n",
+            x,
+            y,
+            CELL_HEIGHT + LABEL_HEIGHT
+        );
+        match cell {
+            Cell::Pattern(pattern) => {
+                body += &draw_pattern(pattern, (x, y), (CELL_WIDTH, CELL_HEIGHT), PATTERN_SCALE);
+                body += &label(
+                    &pattern_name(pattern, registry),
+                    (x + CELL_WIDTH / 2.0, y + CELL_HEIGHT + LABEL_HEIGHT / 2.0),
+                );
+            }
+            Cell::Text(text) => {
+                body += &label(
+                    text,
+                    (x + CELL_WIDTH / 2.0, y + (CELL_HEIGHT + LABEL_HEIGHT) / 2.0),
+                )
+            }
+        }
+    }
+
+    svg(
+        columns as f64 * CELL_WIDTH,
+        rows as f64 * (CELL_HEIGHT + LABEL_HEIGHT),
+        &body,
+    )
+}
+
+fn list_cells<'a>(iota: &'a Rc<dyn Iota>, cells: &mut Vec<Cell<'a>>) {
+    if let Some(pattern) = iota.downcast_ref::<PatternIota>() {
+        cells.push(Cell::Pattern(pattern));
+    } else if let Some(list) = iota.downcast_ref::<ListIota>() {
+        cells.push(Cell::Text("[".to_string()));
+        for inner in list {
+            list_cells(inner, cells);
+        }
+        cells.push(Cell::Text("]".to_string()));
+    } else {
+        //non-pattern iotas can't be drawn, so they are written the way they would be embedded
+        cells.push(Cell::Text(format!("<{}>", iota.display())));
+    }
+}
+
+//named with the given registry so great spells use the signatures from the config
+fn pattern_name(pattern: &PatternIota, registry: &PatternRegistry) -> String {
+    let signature = pattern.signature.as_str();
+    let name = registry
+        .find(&signature, &pattern.value)
+        .map_or(format!("{} {signature}", pattern.start_dir), |pattern| {
+            pattern.display_name
+        });
+
+    match pattern.value.as_ref() {
+        Some(ActionValue::Iota(value)) => format!("{name}: {}", value.display()),
+        Some(ActionValue::Bookkeeper(code)) => format!("{name}: {code}"),
+        None => name,
+    }
+}
+
+//the pattern and the grid dots around it, scaled down if needed to fit in the area
+fn draw_pattern(pattern: &PatternIota, origin: (f64, f64), size: (f64, f64), scale: f64) -> String {
+    let points = points(pattern);
+    let (min, max) = bounds(&points);
+    let scale = scale
+        .min(size.0 / (max.0 - min.0 + 2.0))
+        .min(size.1 / (max.1 - min.1 + 2.0));
+    let center = ((min.0 + max.0) / 2.0, (min.1 + max.1) / 2.0);
+    let to_pixel = |(x, y): (f64, f64)| {
+        (
+            origin.0 + size.0 / 2.0 + (x - center.0) * scale,
+            origin.1 + size.1 / 2.0 + (y - center.1) * scale,
+        )
+    };
+
+    let mut body = String::new();
+
+    //every point of the grid that lands inside the area, leaving half a line of space at the edges
+    let half = (size.0 / scale / 2.0 - 0.5, size.1 / scale / 2.0 - 0.5);
+    let row_height = 3f64.sqrt() / 2.0;
+    let rows = ((center.1 - half.1) / row_height).ceil() as i32
+        ..=((center.1 + half.1) / row_height).floor() as i32;
+    for r in rows {
+        let columns = (center.0 - half.0 - r as f64 / 2.0).ceil() as i32
+            ..=(center.0 + half.0 - r as f64 / 2.0).floor() as i32;
+        for q in columns {
+            let (x, y) = to_pixel(axial_to_point((q, r)));
+            body += &format!(
+                "<circle cx=\"{x:.2}\" cy=\"{y:.2}\" r=\"{:.2}\" fill=\"{DOT_COLOR}\"/>\n",
+                scale * 0.06
+            );
+        }
+    }
+
+    let last_line = (points.len() - 2).max(1);
+    for (index, line) in points.windows(2).enumerate() {
+        let (x1, y1) = to_pixel(line[0]);
+        let (x2, y2) = to_pixel(line[1]);
+        body += &format!(
+            "<line x1=\"{x1:.2}\" y1=\"{y1:.2}\" x2=\"{x2:.2}\" y2=\"{y2:.2}\" stroke=\"{}\" stroke-width=\"{:.2}\" stroke-linecap=\"round\"/>\n",
+            color(index as f64 / last_line as f64),
+            scale * 0.12
+        );
+    }
+
+    //the start of the pattern is marked, which way it goes is shown by the colour
+    let (x, y) = to_pixel(points[0]);
+    body += &format!(
+        "<circle cx=\"{x:.2}\" cy=\"{y:.2}\" r=\"{:.2}\" fill=\"{}\"/>\n",
+        scale * 0.15,
+        color(0.0)
+    );
+
+    body
+}
+
+fn label(text: &str, (x, y): (f64, f64)) -> String {
+    //long names are squeezed to the width of the cell instead of running into the next one
+    let max_width = CELL_WIDTH - 8.0;
+    let fit = if text.chars().count() as f64 * 7.0 > max_width {
+        format!(" textLength=\"{max_width}\" lengthAdjust=\"spacingAndGlyphs\"")
+    } else {
+        String::new()
+    };
+    format!(
+        "<text x=\"{x:.2}\" y=\"{y:.2}\" text-anchor=\"middle\" dominant-baseline=\"middle\" font-family=\"sans-serif\" font-size=\"12\"{fit}>{}</text>\n",
+        escape(text)
+    )
+}
+
+fn svg(width: f64, height: f64, body: &str) -> String {
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width:.0}\" height=\"{height:.0}\" viewBox=\"0 0 {width:.2} {height:.2}\">\n<rect width=\"100%\" height=\"100%\" fill=\"white\"/>\n{body}</svg>"
+    )
+}
+
+fn points(pattern: &PatternIota) -> Vec<(f64, f64)> {
+    pattern
+        .signature
+        .points(pattern.start_dir)
+        .into_iter()
+        .map(axial_to_point)
+        .collect()
+}
+
+//rows of the grid are offset by half a line, with a line between two points being 1 long
+fn axial_to_point((q, r): (i32, i32)) -> (f64, f64) {
+    (q as f64 + r as f64 / 2.0, r as f64 * 3f64.sqrt() / 2.0)
+}
+
+fn bounds(points: &[(f64, f64)]) -> ((f64, f64), (f64, f64)) {
+    points.iter().fold(
+        ((f64::MAX, f64::MAX), (f64::MIN, f64::MIN)),
+        |(min, max), &(x, y)| ((min.0.min(x), min.1.min(y)), (max.0.max(x), max.1.max(y))),
+    )
+}
+
+fn color(progress: f64) -> String {
+    let mix = |start: u8, end: u8| (start as f64 + (end as f64 - start as f64) * progress) as u8;
+    format!(
+        "#{:02x}{:02x}{:02x}",
+        mix(START_COLOR.0, END_COLOR.0),
+        mix(START_COLOR.1, END_COLOR.1),
+        mix(START_COLOR.2, END_COLOR.2)
+    )
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{iota::hex_casting::pattern::Direction, parser::Location};
+
+    use super::*;
+
+    #[test]
+    fn render_test() {
+        let registry = PatternRegistry::construct(&PatternRegistry::gen_default_great_sigs());
+        let pattern =
+            PatternIota::from_angles(&registry, "qaq", Direction::NorthEast, Location::Unknown);
+
+        //a line from the start point plus one for each angle
+        let svg = render_pattern(&pattern);
+        assert_eq!(svg.matches("<line").count(), 4);
+        assert!(svg.contains(&color(0.0)) && svg.contains(&color(1.0)));
+
+        let hex: Vec<Rc<dyn Iota>> = vec![
+            Rc::new(pattern),
+            Rc::new(PatternIota::from_angles(
+                &registry,
+                "aqaaeeaww",
+                Default::default(),
+                Location::Unknown,
+            )),
+            Rc::new(ListIota::from(vec![Rc::new(1.0) as Rc<dyn Iota>])),
+        ];
+        let svg = compile_svg(hex, &registry);
+        assert!(svg.contains(">Mind's Reflection</text>"));
+        assert!(svg.contains(">Numerical Reflection: 42.000</text>"));
+        assert!(svg.contains(">&lt;1.000&gt;</text>"));
+    }
+}
@@ -0,0 +1,579 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, BufRead, Write},
+    path::Path,
+};
+
+use serde_json::{json, Value};
+
+use crate::{
+    debugger::{Debugger, StopReason},
+    interpreter::continuation::ContinuationFrame,
+    iota::hex_casting::list::ListIota,
+    parse_config::{parse_config, Config},
+    parser::{self, AstNode, Location},
+    pattern_registry::{PatternRegistry, PatternRegistryExt},
+};
+
+//the interpreter only ever runs a single thread
+const THREAD_ID: i64 = 1;
+
+const STACK_REFERENCE: i64 = 1;
+const RAVENMIND_REFERENCE: i64 = 2;
+const VARIABLES_REFERENCE: i64 = 3;
+
+//serves the Debug Adapter Protocol until the client disconnects
+pub fn serve(config: Config) -> io::Result<()> {
+    let stdin = io::stdin();
+    let mut server = DapServer::new(config, io::stdout());
+    server.serve(&mut stdin.lock())
+}
+
+pub struct DapServer<W: Write> {
+    writer: W,
+    seq: i64,
+    config: Config,
+    debugger: Option<Debugger>,
+    //breakpoints set before the program is launched, by path
+    pending_breakpoints: HashMap<String, Vec<usize>>,
+    stop_on_entry: bool,
+    //entries of the hex's log already sent as output events
+    logged: usize,
+}
+
+impl<W: Write> DapServer<W> {
+    pub fn new(config: Config, writer: W) -> DapServer<W> {
+        DapServer {
+            writer,
+            seq: 1,
+            config,
+            debugger: None,
+            pending_breakpoints: HashMap::new(),
+            stop_on_entry: false,
+            logged: 0,
+        }
+    }
+
+    pub fn serve(&mut self, reader: &mut impl BufRead) -> io::Result<()> {
+        while let Some(message) = read_message(reader)? {
+            if message["type"] != "request" {
+                continue;
+            }
+            let command = message["command"].as_str().unwrap_or_default().to_string();
+            let arguments = &message["arguments"];
+
+            let result = self.handle_request(&command, arguments);
+            match result {
+                Ok(body) => self.send_response(&message, true, body, None)?,
+                Err(err) => self.send_response(&message, false, Value::Null, Some(err))?,
+            }
+            self.after_request(&command)?;
+
+            if command == "disconnect" {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_request(&mut self, command: &str, arguments: &Value) -> Result<Value, String> {
+        match command {
+            "initialize" => Ok(json!({
+                "supportsConfigurationDoneRequest": true,
+            })),
+            "launch" => {
+                self.launch(arguments)?;
+                Ok(Value::Null)
+            }
+            "setBreakpoints" => self.set_breakpoints(arguments),
+            "configurationDone" | "disconnect" | "terminate" | "pause" => Ok(Value::Null),
+            "threads" => Ok(json!({ "threads": [{ "id": THREAD_ID, "name": "hex" }] })),
+            "stackTrace" => Ok(self.stack_trace()),
+            "scopes" => Ok(json!({
+                "scopes": [
+                    { "name": "Stack", "variablesReference": STACK_REFERENCE, "expensive": false },
+                    { "name": "Ravenmind", "variablesReference": RAVENMIND_REFERENCE, "expensive": false },
+                    { "name": "Variables", "variablesReference": VARIABLES_REFERENCE, "expensive": false },
+                ]
+            })),
+            "variables" => Ok(self.variables(arguments["variablesReference"].as_i64())),
+            "continue" => Ok(json!({ "allThreadsContinued": true })),
+            "next" | "stepIn" | "stepOut" => Ok(Value::Null),
+            _ => Err(format!("unsupported request '{command}'")),
+        }
+    }
+
+    //events that have to be sent after the response to a request
+    fn after_request(&mut self, command: &str) -> io::Result<()> {
+        match command {
+            "initialize" => self.send_event("initialized", Value::Null),
+            "configurationDone" => {
+                if self.stop_on_entry {
+                    self.send_output()?;
+                    self.send_stopped("entry")
+                } else if self.debugger.as_ref().is_some_and(Debugger::at_breakpoint) {
+                    //the debugger already stopped on the first line, resuming would step past it
+                    self.send_stopped("breakpoint")
+                } else {
+                    self.run(Debugger::resume)
+                }
+            }
+            "continue" => self.run(Debugger::resume),
+            "next" => self.run(Debugger::step_over),
+            "stepIn" => self.run(Debugger::step_into),
+            "stepOut" => self.run(Debugger::step_out),
+            _ => Ok(()),
+        }
+    }
+
+    fn launch(&mut self, arguments: &Value) -> Result<(), String> {
+        let program = arguments["program"]
+            .as_str()
+            .ok_or("expected a program to debug")?;
+        self.stop_on_entry = arguments["stopOnEntry"].as_bool().unwrap_or(false);
+
+        let mut config = match arguments["config"].as_str() {
+            Some(path) => {
+                parse_config(fs::read_to_string(path).map_err(|err| format!("{path}: {err}"))?)
+                    .map_err(|err| format!("{path}: {err}"))?
+            }
+            None => self.config.clone(),
+        };
+        let pattern_registry = PatternRegistry::from_config(&config);
+
+        let source = fs::read_to_string(program).map_err(|err| format!("{program}: {err}"))?;
+        let (ast, macros, sources) =
+            parser::parse(&source, program, &pattern_registry, &mut config.entities).map_err(
+                |errors| {
+                    errors
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                },
+            )?;
+
+        let mut debugger = Debugger::new(ast, &config, macros, sources)
+            .map_err(|(mishap, _, caused_by)| format!("{caused_by}: {}", mishap.error_message()))?;
+
+        for (path, lines) in self.pending_breakpoints.drain() {
+            if let Some(file) = source_index(&debugger, &path) {
+                debugger
+                    .breakpoints
+                    .extend(lines.into_iter().map(|line| (file, line)));
+            }
+        }
+
+        self.debugger = Some(debugger);
+        self.logged = 0;
+        Ok(())
+    }
+
+    fn set_breakpoints(&mut self, arguments: &Value) -> Result<Value, String> {
+        let path = arguments["source"]["path"]
+            .as_str()
+            .ok_or("expected a source path")?
+            .to_string();
+        let lines = arguments["breakpoints"]
+            .as_array()
+            .map(|breakpoints| {
+                breakpoints
+                    .iter()
+                    .filter_map(|breakpoint| breakpoint["line"].as_u64())
+                    .map(|line| line as usize)
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        let verified = match &mut self.debugger {
+            Some(debugger) => match source_index(debugger, &path) {
+                Some(file) => {
+                    debugger.breakpoints.retain(|(other, _)| *other != file);
+                    debugger
+                        .breakpoints
+                        .extend(lines.iter().map(|line| (file, *line)));
+                    true
+                }
+                None => false,
+            },
+            None => {
+                self.pending_breakpoints.insert(path, lines.clone());
+                true
+            }
+        };
+
+        Ok(json!({
+            "breakpoints": lines
+                .iter()
+                .map(|line| json!({ "verified": verified, "line": line }))
+                .collect::<Vec<_>>()
+        }))
+    }
+
+    fn stack_trace(&self) -> Value {
+        let Some(debugger) = &self.debugger else {
+            return json!({ "stackFrames": [], "totalFrames": 0 });
+        };
+
+        //every evaluate frame is a hex that is being run, the innermost one is on top
+        let frames = debugger
+            .state
+            .continuation
+            .iter()
+            .rev()
+            .filter_map(|frame| match frame {
+                ContinuationFrame::Evaluate(frame) => frame.nodes_queue.front(),
+                _ => None,
+            })
+            .filter_map(|node| {
+                let location = node.location()?;
+                let (file, line) = location.file_line()?;
+                Some((node_name(node), file, line, column(&location)))
+            })
+            .enumerate()
+            .map(|(id, (name, file, line, column))| {
+                let path = &debugger.sources[file].path;
+                json!({
+                    "id": id,
+                    "name": name,
+                    "source": { "name": file_name(path), "path": absolute_path(path) },
+                    "line": line,
+                    "column": column,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        json!({ "totalFrames": frames.len(), "stackFrames": frames })
+    }
+
+    fn variables(&self, reference: Option<i64>) -> Value {
+        let Some(debugger) = &self.debugger else {
+            return json!({ "variables": [] });
+        };
+
+        let variables: Vec<(String, String)> = match reference {
+            Some(STACK_REFERENCE) => debugger
+                .state
+                .stack
+                .iter()
+                .enumerate()
+                .map(|(index, iota)| (index.to_string(), iota.display()))
+                .collect(),
+            Some(RAVENMIND_REFERENCE) => match &debugger.state.ravenmind {
+                Some(ravenmind) => match ravenmind.clone().downcast_rc::<ListIota>() {
+                    Ok(list) => list
+                        .iter()
+                        .enumerate()
+                        .map(|(index, iota)| (index.to_string(), iota.display()))
+                        .collect(),
+                    Err(ravenmind) => vec![("value".to_string(), ravenmind.display())],
+                },
+                None => vec![],
+            },
+            Some(VARIABLES_REFERENCE) => debugger.variables(),
+            _ => vec![],
+        };
+
+        json!({
+            "variables": variables
+                .into_iter()
+                .map(|(name, value)| json!({ "name": name, "value": value, "variablesReference": 0 }))
+                .collect::<Vec<_>>()
+        })
+    }
+
+    fn run(&mut self, action: impl FnOnce(&mut Debugger) -> StopReason) -> io::Result<()> {
+        let Some(debugger) = &mut self.debugger else {
+            return Ok(());
+        };
+        let stop_reason = action(debugger);
+        self.send_output()?;
+
+        match stop_reason {
+            StopReason::Step => self.send_stopped("step"),
+            StopReason::Breakpoint => self.send_stopped("breakpoint"),
+            StopReason::Finished => self.send_terminated(0),
+            StopReason::Mishap(mishap, location, caused_by) => {
+                let position = match (location.file_line(), &self.debugger) {
+                    (Some((file, line)), Some(debugger)) => {
+                        format!(" at {}:{line}", debugger.sources[file].path)
+                    }
+                    _ => String::new(),
+                };
+                let message = format!(
+                    "Error: {} ({caused_by}){position}\n",
+                    mishap.error_message()
+                );
+                self.send_event("output", json!({ "category": "stderr", "output": message }))?;
+                self.send_terminated(1)
+            }
+        }
+    }
+
+    //sends what the hex did since the last time, lines it printed go to stdout
+    fn send_output(&mut self) -> io::Result<()> {
+        let Some(debugger) = &self.debugger else {
+            return Ok(());
+        };
+        let log = debugger.state.log.clone();
+
+        for entry in log.iter().skip(self.logged) {
+            let category = match entry.output() {
+                Some(_) => "stdout",
+                None => "console",
+            };
+            let output = format!("{}\n", entry.display());
+            self.send_event("output", json!({ "category": category, "output": output }))?;
+        }
+        self.logged = log.len();
+        Ok(())
+    }
+
+    fn send_stopped(&mut self, reason: &str) -> io::Result<()> {
+        self.send_event(
+            "stopped",
+            json!({ "reason": reason, "threadId": THREAD_ID, "allThreadsStopped": true }),
+        )
+    }
+
+    fn send_terminated(&mut self, exit_code: i32) -> io::Result<()> {
+        self.send_event("exited", json!({ "exitCode": exit_code }))?;
+        self.send_event("terminated", Value::Null)
+    }
+
+    fn send_response(
+        &mut self,
+        request: &Value,
+        success: bool,
+        body: Value,
+        message: Option<String>,
+    ) -> io::Result<()> {
+        let mut response = json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": success,
+        });
+        if !body.is_null() {
+            response["body"] = body;
+        }
+        if let Some(message) = message {
+            response["message"] = Value::String(message);
+        }
+        self.send(response)
+    }
+
+    fn send_event(&mut self, event: &str, body: Value) -> io::Result<()> {
+        let mut message = json!({ "type": "event", "event": event });
+        if !body.is_null() {
+            message["body"] = body;
+        }
+        self.send(message)
+    }
+
+    fn send(&mut self, mut message: Value) -> io::Result<()> {
+        message["seq"] = json!(self.seq);
+        self.seq += 1;
+        write_message(&mut self.writer, &message)
+    }
+}
+
+fn node_name(node: &AstNode) -> String {
+    match node {
+        AstNode::Action { name, .. } | AstNode::MacroCall { name, .. } => name.clone(),
+        AstNode::Op { name, .. } => name.to_string(),
+        AstNode::IfBlock { .. } => "if".to_string(),
+        AstNode::WhileBlock { .. } => "while".to_string(),
+        AstNode::Program(_) | AstNode::Block { .. } => "hex".to_string(),
+    }
+}
+
+fn column(location: &Location) -> usize {
+    match location {
+        Location::Line(_, column) | Location::File(_, _, column) => *column,
+        _ => 0,
+    }
+}
+
+fn file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map_or(path.to_string(), |name| name.to_string_lossy().to_string())
+}
+
+fn absolute_path(path: &str) -> String {
+    fs::canonicalize(path).map_or(path.to_string(), |path| path.display().to_string())
+}
+
+//finds the source file that a path from the client refers to
+fn source_index(debugger: &Debugger, path: &str) -> Option<usize> {
+    let path = absolute_path(path);
+    debugger
+        .sources
+        .iter()
+        .position(|source| absolute_path(&source.path) == path)
+}
+
+pub(crate) fn read_message(reader: &mut impl BufRead) -> io::Result<Option<Value>> {
+    let mut content_length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                content_length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+
+    let content_length = content_length.ok_or(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "missing Content-Length header",
+    ))?;
+    let mut content = vec![0; content_length];
+    reader.read_exact(&mut content)?;
+
+    serde_json::from_slice(&content)
+        .map(Some)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+pub(crate) fn write_message(writer: &mut impl Write, message: &Value) -> io::Result<()> {
+    let content = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{content}", content.len())?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn request(seq: i64, command: &str, arguments: Value) -> Vec<u8> {
+        let mut message = vec![];
+        write_message(
+            &mut message,
+            &json!({ "seq": seq, "type": "request", "command": command, "arguments": arguments }),
+        )
+        .unwrap();
+        message
+    }
+
+    #[test]
+    fn dap_test() {
+        let path = std::env::temp_dir().join("hexagon_dap_test.hexagon");
+        fs::write(
+            &path,
+            "Numerical Reflection: 1\nNumerical Reflection: 2\nReveal",
+        )
+        .unwrap();
+        let path = path.display().to_string();
+
+        let input = [
+            request(1, "initialize", json!({})),
+            request(2, "launch", json!({ "program": path })),
+            request(
+                3,
+                "setBreakpoints",
+                json!({ "source": { "path": path }, "breakpoints": [{ "line": 2 }] }),
+            ),
+            request(4, "configurationDone", json!({})),
+            request(
+                5,
+                "variables",
+                json!({ "variablesReference": STACK_REFERENCE }),
+            ),
+            request(6, "stackTrace", json!({ "threadId": THREAD_ID })),
+            request(7, "continue", json!({ "threadId": THREAD_ID })),
+            request(8, "disconnect", json!({})),
+        ]
+        .concat();
+
+        let messages = serve(input);
+
+        let events = messages
+            .iter()
+            .filter(|message| message["type"] == "event")
+            .map(|message| message["event"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            vec!["initialized", "stopped", "output", "exited", "terminated"]
+        );
+
+        let response = |command: &str| {
+            messages
+                .iter()
+                .find(|message| message["type"] == "response" && message["command"] == command)
+                .unwrap()
+        };
+        assert_eq!(
+            response("variables")["body"]["variables"][0]["value"],
+            "1.000"
+        );
+        assert_eq!(response("stackTrace")["body"]["stackFrames"][0]["line"], 2);
+
+        //Reveal doesn't print to stdout, which is the protocol stream
+        let output = messages
+            .iter()
+            .find(|message| message["event"] == "output")
+            .unwrap();
+        assert_eq!(output["body"]["category"], "stdout");
+        assert_eq!(output["body"]["output"], "2.000\n");
+    }
+
+    #[test]
+    fn first_line_breakpoint_test() {
+        let path = std::env::temp_dir().join("hexagon_dap_breakpoint_test.hexagon");
+        fs::write(&path, "Numerical Reflection: 1\nNumerical Reflection: 2").unwrap();
+        let path = path.display().to_string();
+
+        let input = [
+            request(1, "initialize", json!({})),
+            request(2, "launch", json!({ "program": path, "stopOnEntry": false })),
+            request(
+                3,
+                "setBreakpoints",
+                json!({ "source": { "path": path }, "breakpoints": [{ "line": 1 }] }),
+            ),
+            request(4, "configurationDone", json!({})),
+            request(5, "stackTrace", json!({ "threadId": THREAD_ID })),
+            request(6, "disconnect", json!({})),
+        ]
+        .concat();
+        let messages = serve(input);
+
+        let stopped = messages
+            .iter()
+            .find(|message| message["event"] == "stopped")
+            .unwrap();
+        assert_eq!(stopped["body"]["reason"], "breakpoint");
+        let stack_trace = messages
+            .iter()
+            .find(|message| message["command"] == "stackTrace")
+            .unwrap();
+        assert_eq!(stack_trace["body"]["stackFrames"][0]["line"], 1);
+    }
+
+    fn serve(input: Vec<u8>) -> Vec<Value> {
+        let mut output = vec![];
+        DapServer::new(Config::default(), &mut output)
+            .serve(&mut Cursor::new(input))
+            .unwrap();
+
+        let mut reader = Cursor::new(output);
+        let mut messages = vec![];
+        while let Some(message) = read_message(&mut reader).unwrap() {
+            messages.push(message);
+        }
+        messages
+    }
+}
@@ -0,0 +1,463 @@
+use std::{
+    collections::HashSet,
+    io::{self, BufRead, Write},
+};
+
+use owo_colors::OwoColorize;
+
+use crate::{
+    interpreter::{
+        continuation::ContinuationFrame, init_state, mishap::Mishap, run_wisps, state::State,
+        step_vm,
+    },
+    iota::{hex_casting::list::ListIota, Iota},
+    parse_config::Config,
+    parser::{AstNode, Location, Macros, SourceFile},
+    pattern_registry::{PatternRegistry, PatternRegistryExt},
+};
+
+const DEBUG_HELP: &str = "\
+Commands:
+  break <line>, b         Add a breakpoint (use <file>:<line> for included files)
+  delete <line>, d        Remove a breakpoint
+  breakpoints             List breakpoints
+  step, s                 Step into the next pattern
+  next, n                 Step over the next pattern
+  out, o                  Step out of the current hex
+  continue, c             Run until the next breakpoint
+  stack                   Print the stack
+  ravenmind               Print the ravenmind
+  buffer                  Print the introspection buffer
+  vars                    Print the variables
+  where, w                Print the current line
+  help, h                 Print help
+  quit, q                 Stop debugging
+
+An empty line repeats the last command.";
+
+#[derive(Debug, Clone)]
+pub enum StopReason {
+    Step,
+    Breakpoint,
+    Finished,
+    Mishap(Mishap, Location, String),
+}
+
+pub struct Debugger {
+    pub state: State,
+    pub sources: Vec<SourceFile>,
+    //(source file index, line)
+    pub breakpoints: HashSet<(usize, usize)>,
+    pattern_registry: PatternRegistry,
+    macros: Macros,
+    in_game_mishaps: bool,
+}
+
+impl Debugger {
+    pub fn new(
+        node: AstNode,
+        config: &Config,
+        macros: Macros,
+        sources: Vec<SourceFile>,
+    ) -> Result<Debugger, (Mishap, Location, String)> {
+        let pattern_registry = PatternRegistry::from_config(config);
+        let state = init_state(node, config, &pattern_registry, &macros)
+            .map_err(|(mishap, location, caused_by, _)| (mishap, location, caused_by))?;
+
+        let mut debugger = Debugger {
+            state,
+            sources,
+            breakpoints: HashSet::new(),
+            pattern_registry,
+            macros,
+            in_game_mishaps: config.in_game_mishaps,
+        };
+        //skip to the first line
+        if !debugger.is_paused() {
+            debugger.run_until(|_| true);
+        }
+        Ok(debugger)
+    }
+
+    //the node that will be evaluated next
+    pub fn current_node(&self) -> Option<&AstNode> {
+        match self.state.continuation.last() {
+            Some(ContinuationFrame::Evaluate(frame)) => frame.nodes_queue.front(),
+            _ => None,
+        }
+    }
+
+    pub fn current_location(&self) -> Option<Location> {
+        self.current_node()
+            .and_then(AstNode::location)
+            .filter(|location| location.file_line().is_some())
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.state.continuation.is_empty()
+    }
+
+    fn is_paused(&self) -> bool {
+        self.current_location().is_some()
+    }
+
+    pub fn at_breakpoint(&self) -> bool {
+        self.current_location()
+            .and_then(|location| location.file_line())
+            .is_some_and(|file_line| self.breakpoints.contains(&file_line))
+    }
+
+    //like `run_vm`, the wisps the hex summoned are ticked once the hex itself is done
+    fn tick_wisps(&mut self) -> StopReason {
+        match run_wisps(&mut self.state, &self.pattern_registry, &self.macros, &mut |_| true) {
+            Ok(()) => StopReason::Finished,
+            Err((mishap, location, caused_by)) => {
+                self.state.wisps.clear();
+                StopReason::Mishap(mishap, location, caused_by)
+            }
+        }
+    }
+
+    //evaluates frames until the debugger reaches a line where `pause` is true or a breakpoint
+    fn run_until(&mut self, pause: impl Fn(&Debugger) -> bool) -> StopReason {
+        loop {
+            if self.is_finished() {
+                return self.tick_wisps();
+            }
+            match step_vm(&mut self.state, &self.pattern_registry, &self.macros) {
+                Ok(()) => (),
+                //the same as `run_vm`, the next pattern of the hex is still cast
+                Err((mishap, location, caused_by))
+                    if self.in_game_mishaps && mishap.keeps_casting() =>
+                {
+                    self.state.continuation.truncate(1);
+                    self.state.consider_next = false;
+                    self.state.mishaps.push_back((mishap, location, caused_by));
+                }
+                Err((mishap, location, caused_by)) => {
+                    self.state.continuation.clear();
+                    return StopReason::Mishap(mishap, location, caused_by);
+                }
+            }
+
+            if self.is_paused() {
+                if self.at_breakpoint() {
+                    return StopReason::Breakpoint;
+                }
+                if pause(self) {
+                    return StopReason::Step;
+                }
+            }
+        }
+    }
+
+    pub fn step_into(&mut self) -> StopReason {
+        self.run_until(|_| true)
+    }
+
+    //nested hexes (eg. from Hermes' Gambit) push new frames, so stepping over waits until
+    //the continuation is back to the same depth
+    pub fn step_over(&mut self) -> StopReason {
+        let depth = self.state.continuation.len();
+        self.run_until(|debugger| debugger.state.continuation.len() <= depth)
+    }
+
+    pub fn step_out(&mut self) -> StopReason {
+        let depth = self.state.continuation.len();
+        self.run_until(|debugger| debugger.state.continuation.len() < depth)
+    }
+
+    pub fn resume(&mut self) -> StopReason {
+        self.run_until(|_| false)
+    }
+
+    pub fn variables(&self) -> Vec<(String, String)> {
+        self.state.variables()
+    }
+
+    pub fn display_stack(&self) -> String {
+        self.state.stack.display()
+    }
+
+    pub fn display_ravenmind(&self) -> String {
+        self.state
+            .ravenmind
+            .as_ref()
+            .map_or("None".to_string(), |ravenmind| ravenmind.display())
+    }
+
+    pub fn display_buffer(&self) -> String {
+        self.state.buffer.as_ref().map_or("None".to_string(), |buffer| {
+            buffer
+                .iter()
+                .map(|(iota, _)| iota.clone())
+                .collect::<ListIota>()
+                .display()
+        })
+    }
+
+    //finds the source file a breakpoint like "12" or "lib/macros.hexagon:12" refers to
+    pub fn parse_breakpoint(&self, breakpoint: &str) -> Result<(usize, usize), String> {
+        let (file, line) = match breakpoint.rsplit_once(':') {
+            Some((path, line)) => {
+                let file = self
+                    .sources
+                    .iter()
+                    .position(|source| source.path == path || source.path.ends_with(path))
+                    .ok_or(format!("no source file named '{path}'"))?;
+                (file, line)
+            }
+            None => (0, breakpoint),
+        };
+        let line = line
+            .parse()
+            .map_err(|_| format!("invalid line number '{line}'"))?;
+        Ok((file, line))
+    }
+}
+
+pub fn debug(
+    node: AstNode,
+    config: &Config,
+    macros: Macros,
+    sources: &[SourceFile],
+) -> Result<State, (Mishap, Location, String, State)> {
+    let mut debugger = Debugger::new(node, config, macros, sources.to_vec())
+        .map_err(|(mishap, location, caused_by)| (mishap, location, caused_by, State::default()))?;
+
+    println!("{}", "Debugging hex, type 'help' for a list of commands".bold());
+    let mut printed = 0;
+    print_output(&debugger, &mut printed);
+    print_position(&debugger);
+
+    let stdin = io::stdin();
+    let mut last_command = String::new();
+
+    loop {
+        print!("{} ", "(hexagon)".magenta().bold());
+        io::stdout().flush().unwrap();
+
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line).unwrap_or(0) == 0 {
+            //end of input
+            return Ok(debugger.state);
+        }
+        let line = match line.trim() {
+            "" => last_command.clone(),
+            line => line.to_string(),
+        };
+        last_command = line.clone();
+
+        let (command, arg) = line.split_once(' ').unwrap_or((&line, ""));
+        let arg = arg.trim();
+
+        let stop_reason = match command {
+            "break" | "b" => {
+                match debugger.parse_breakpoint(arg) {
+                    Ok(breakpoint) => {
+                        debugger.breakpoints.insert(breakpoint);
+                        println!("Breakpoint set at {}", display_breakpoint(&debugger, breakpoint));
+                    }
+                    Err(err) => eprintln!("{} {err}", "Error:".red().bold()),
+                }
+                None
+            }
+            "delete" | "d" => {
+                match debugger.parse_breakpoint(arg) {
+                    Ok(breakpoint) if debugger.breakpoints.remove(&breakpoint) => {
+                        println!("Breakpoint removed")
+                    }
+                    Ok(_) => eprintln!("{} no breakpoint at {arg}", "Error:".red().bold()),
+                    Err(err) => eprintln!("{} {err}", "Error:".red().bold()),
+                }
+                None
+            }
+            "breakpoints" => {
+                let mut breakpoints = debugger.breakpoints.iter().copied().collect::<Vec<_>>();
+                breakpoints.sort();
+                for breakpoint in breakpoints {
+                    println!("{}", display_breakpoint(&debugger, breakpoint));
+                }
+                None
+            }
+            "step" | "s" => Some(debugger.step_into()),
+            "next" | "n" => Some(debugger.step_over()),
+            "out" | "o" => Some(debugger.step_out()),
+            "continue" | "c" => Some(debugger.resume()),
+            "stack" => {
+                println!("{}", debugger.display_stack());
+                None
+            }
+            "ravenmind" => {
+                println!("{}", debugger.display_ravenmind());
+                None
+            }
+            "buffer" => {
+                println!("{}", debugger.display_buffer());
+                None
+            }
+            "vars" => {
+                print_vars(&debugger);
+                None
+            }
+            "where" | "w" => {
+                print_position(&debugger);
+                None
+            }
+            "help" | "h" => {
+                println!("{DEBUG_HELP}");
+                None
+            }
+            "quit" | "q" => return Ok(debugger.state),
+            _ => {
+                eprintln!(
+                    "{} unknown command '{command}', type 'help' for a list of commands",
+                    "Error:".red().bold()
+                );
+                None
+            }
+        };
+
+        if stop_reason.is_some() {
+            print_output(&debugger, &mut printed);
+        }
+
+        match stop_reason {
+            Some(StopReason::Finished) => {
+                println!("{}", "Hex finished".bold());
+                return Ok(debugger.state);
+            }
+            Some(StopReason::Mishap(mishap, location, caused_by)) => {
+                return Err((mishap, location, caused_by, debugger.state));
+            }
+            Some(StopReason::Breakpoint) => {
+                println!("{}", "Hit breakpoint".yellow().bold());
+                print_state(&debugger);
+                print_position(&debugger);
+            }
+            Some(StopReason::Step) => {
+                print_state(&debugger);
+                print_position(&debugger);
+            }
+            None => (),
+        }
+    }
+}
+
+//prints the lines the hex printed since the last time
+fn print_output(debugger: &Debugger, printed: &mut usize) {
+    for entry in debugger.state.log.iter().skip(*printed) {
+        if let Some(line) = entry.output() {
+            println!("{line}");
+        }
+    }
+    *printed = debugger.state.log.len();
+}
+
+fn display_breakpoint(debugger: &Debugger, (file, line): (usize, usize)) -> String {
+    format!("{}:{line}", debugger.sources[file].path)
+}
+
+fn print_position(debugger: &Debugger) {
+    let Some((file, line)) = debugger.current_location().and_then(|location| location.file_line())
+    else {
+        return;
+    };
+    let source_file = &debugger.sources[file];
+    let line_content = source_file.source.lines().nth(line - 1).unwrap_or("");
+
+    println!(" {} {}:{line}", "@".magenta().bold(), source_file.path);
+    println!(" {} {} {line_content}", line.magenta().bold(), ">".magenta().bold());
+}
+
+fn print_state(debugger: &Debugger) {
+    println!("{} {}", "stack:".bold(), debugger.display_stack());
+    println!("{} {}", "ravenmind:".bold(), debugger.display_ravenmind());
+    if debugger.state.buffer.is_some() {
+        println!("{} {}", "buffer:".bold(), debugger.display_buffer());
+    }
+    print_vars(debugger);
+}
+
+fn print_vars(debugger: &Debugger) {
+    for (name, value) in debugger.variables() {
+        println!("{} = {value}", name.bold());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::{interpreter::state::Limits, parser::parse};
+
+    #[test]
+    fn step_test() {
+        let source = "Numerical Reflection: 1\n{\n    Numerical Reflection: 2\n}\nHermes' Gambit\nNumerical Reflection: 3\nNumerical Reflection: 4";
+        let config = Config::default();
+        let registry = PatternRegistry::from_config(&config);
+        let (ast, macros, sources) = parse(source, "", &registry, &mut HashMap::new()).unwrap();
+        let mut debugger = Debugger::new(ast, &config, macros, sources).unwrap();
+
+        let line = |debugger: &Debugger| debugger.current_location().unwrap().file_line().unwrap().1;
+        assert_eq!(line(&debugger), 1);
+
+        //the block has no location, so stepping goes straight to Hermes' Gambit
+        debugger.step_into();
+        assert_eq!(line(&debugger), 5);
+
+        debugger.step_into();
+        assert_eq!(line(&debugger), 3);
+
+        debugger.step_out();
+        assert_eq!(line(&debugger), 6);
+
+        debugger.breakpoints.insert((0, 7));
+        assert!(matches!(debugger.resume(), StopReason::Breakpoint));
+        assert!(matches!(debugger.resume(), StopReason::Finished));
+        assert_eq!(debugger.display_stack(), "[1.000, 2.000, 3.000, 4.000]");
+    }
+
+    #[test]
+    fn in_game_mishaps_test() {
+        let source = "Numerical Reflection: 1\nAdditive Distillation\nNumerical Reflection: 2";
+        let config = Config {
+            in_game_mishaps: true,
+            ..Default::default()
+        };
+        let registry = PatternRegistry::from_config(&config);
+        let (ast, macros, sources) = parse(source, "", &registry, &mut HashMap::new()).unwrap();
+        let mut debugger = Debugger::new(ast, &config, macros, sources).unwrap();
+
+        //the mishap is kept, and the hex goes on to the last line
+        debugger.step_into();
+        assert!(matches!(debugger.step_into(), StopReason::Step));
+        assert_eq!(debugger.state.mishaps.len(), 1);
+        assert!(matches!(debugger.resume(), StopReason::Finished));
+        assert!(debugger.display_stack().ends_with("2.000]"));
+    }
+
+    #[test]
+    fn wisp_test() {
+        let source = "{\n    Numerical Reflection: 1\n    Reveal\n}\nVector Reflection Zero\nNumerical Reflection: 10\nSummon Cyclic Wisp";
+        let config = Config {
+            limits: Limits {
+                max_ops: 100,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let registry = PatternRegistry::from_config(&config);
+        let (ast, macros, sources) = parse(source, "", &registry, &mut HashMap::new()).unwrap();
+        let mut debugger = Debugger::new(ast, &config, macros, sources).unwrap();
+
+        //the wisp keeps casting after the hex is done, until it goes over the limit
+        let StopReason::Mishap(mishap, _, _) = debugger.resume() else {
+            panic!("expected the wisp to be stopped")
+        };
+        assert!(matches!(mishap, Mishap::TooManyPatterns { limit: 100 }));
+        assert!(debugger.state.log.len() > 1);
+        assert!(debugger.is_finished() && debugger.state.wisps.is_empty());
+    }
+}
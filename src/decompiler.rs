@@ -0,0 +1,898 @@
+//turns compiled hexes back into source. The patterns the compiler adds for blocks, variables, if
+//and while are turned back into those where the source would compile to the same patterns
+use std::{collections::HashMap, ops::Range, rc::Rc};
+
+use pest::Parser;
+use serde_json::Value;
+
+use crate::{
+    interpreter::state::Entity,
+    iota::{
+        hex_casting::{
+            entity::EntityIota,
+            garbage::GarbageIota,
+            list::ListIota,
+            null::NullIota,
+            number::NumberIota,
+            pattern::{PatternIota, SignatureExt},
+            vector::VectorIota,
+        },
+        more_iotas::{matrix::MatrixIota, string::StringIota},
+        Iota,
+    },
+    parse_config::Config,
+    parser::{ActionValue, AstNode, HexParser, Location, OpName, OpValue, Rule},
+    pattern_registry::{PatternRegistry, PatternRegistryExt},
+};
+
+use self::{
+    json::json_to_iota,
+    snbt::{nbt_to_iota, parse_snbt, Nbt},
+};
+
+pub mod json;
+pub mod snbt;
+
+const INDENT: &str = "    ";
+
+pub fn decompile(input: &str, config: &Config) -> Result<String, String> {
+    let pattern_registry = PatternRegistry::from_config(config);
+    let iotas = read_hex(input, &pattern_registry, &config.entities)?;
+    let decompiler = Decompiler::new(&iotas, &pattern_registry);
+    decompiler.print(&decompiler.decompile())
+}
+
+//reads the iotas of a hex from SNBT (a whole /give command works too) or JSON
+pub fn read_hex(
+    input: &str,
+    pattern_registry: &PatternRegistry,
+    entities: &HashMap<String, Entity>,
+) -> Result<Vec<Rc<dyn Iota>>, String> {
+    let input = input.trim();
+
+    if let Ok(json) = serde_json::from_str::<Value>(input) {
+        return match json {
+            Value::Array(iotas) => iotas
+                .iter()
+                .map(|iota| json_to_iota(iota, pattern_registry, entities))
+                .collect(),
+            iota => Ok(hex_from_iota(json_to_iota(
+                &iota,
+                pattern_registry,
+                entities,
+            )?)),
+        };
+    }
+
+    //only the data of the item is needed from a give command
+    let snbt = if input.starts_with("/give") || input.starts_with("give") {
+        let start = input
+            .find('{')
+            .ok_or("expected item data in the give command")?;
+        input[start..].trim_end_matches(|char: char| char.is_ascii_digit() || char.is_whitespace())
+    } else {
+        input
+    };
+
+    find_hex(&parse_snbt(snbt)?, pattern_registry, entities)
+}
+
+//finds the hex in the data of any item that can hold one
+fn find_hex(
+    nbt: &Nbt,
+    pattern_registry: &PatternRegistry,
+    entities: &HashMap<String, Entity>,
+) -> Result<Vec<Rc<dyn Iota>>, String> {
+    let read_list = |iotas: &Nbt| match iotas {
+        Nbt::List(iotas) => iotas
+            .iter()
+            .map(|iota| nbt_to_iota(iota, pattern_registry, entities))
+            .collect(),
+        _ => Err("expected a list of iotas".to_string()),
+    };
+
+    if let Some(hex) = nbt.get("hex").or(nbt.get("patterns")) {
+        read_list(hex)
+    } else if let Some(data) = nbt.get("data") {
+        find_hex(data, pattern_registry, entities)
+    } else if let Some(Nbt::Compound(pages)) = nbt.get("pages") {
+        //the open page of a spellbook
+        let page_idx = match nbt.get("page_idx") {
+            Some(Nbt::Number(page_idx)) => page_idx.to_string(),
+            _ => "1".to_string(),
+        };
+        let page = pages
+            .iter()
+            .find(|(key, _)| *key == page_idx)
+            .or(pages.first())
+            .ok_or("expected a spellbook page")?;
+        find_hex(&page.1, pattern_registry, entities)
+    } else if let Nbt::List(_) = nbt {
+        read_list(nbt)
+    } else {
+        Ok(hex_from_iota(nbt_to_iota(nbt, pattern_registry, entities)?))
+    }
+}
+
+//a list is the hex it holds, anything else is a hex of one iota
+fn hex_from_iota(iota: Rc<dyn Iota>) -> Vec<Rc<dyn Iota>> {
+    match iota.clone().downcast_rc::<ListIota>() {
+        Ok(list) => list.iter().cloned().collect(),
+        Err(_) => vec![iota],
+    }
+}
+
+//entities are saved by uuid, so they have to be in the config to get their name back
+fn find_entity(uuid: &str, entities: &HashMap<String, Entity>) -> Result<EntityIota, String> {
+    let normalise = |uuid: &str| uuid.replace(char::is_whitespace, "");
+    entities
+        .values()
+        .find(|entity| normalise(&entity.uuid) == normalise(uuid))
+        .map(|entity| EntityIota {
+            name: Rc::from(entity.name.as_str()),
+            uuid: entity.uuid.clone(),
+        })
+        .ok_or(format!(
+            "no entity in the config has the uuid {uuid}, add it to the config to decompile this hex"
+        ))
+}
+
+//variables are named after their place in the ravenmind
+type Heap = HashMap<String, i32>;
+
+pub struct Decompiler<'a> {
+    iotas: &'a [Rc<dyn Iota>],
+    pattern_registry: &'a PatternRegistry,
+}
+
+impl<'a> Decompiler<'a> {
+    pub fn new(iotas: &'a [Rc<dyn Iota>], pattern_registry: &'a PatternRegistry) -> Self {
+        Decompiler {
+            iotas,
+            pattern_registry,
+        }
+    }
+
+    pub fn decompile(&self) -> Vec<AstNode> {
+        //the compiler starts every hex by clearing the ravenmind
+        let start = if self.matches(0, self.iotas.len(), &["empty_list", "write/local"]) {
+            2
+        } else {
+            0
+        };
+        self.decompile_range(start..self.iotas.len(), &mut HashMap::new())
+    }
+
+    fn decompile_range(&self, range: Range<usize>, heap: &mut Heap) -> Vec<AstNode> {
+        let mut nodes = vec![];
+        let mut pos = range.start;
+        let end = range.end;
+
+        while pos < end {
+            //an escaped pattern is only an iota, even if it is a bracket
+            if self.is(pos, "escape") && pos + 1 < end {
+                nodes.push(self.plain(pos));
+                nodes.push(self.plain(pos + 1));
+                pos += 2;
+                continue;
+            }
+
+            let sugared = self
+                .while_block(pos, end, heap)
+                .or_else(|| self.external_block(pos, end, heap))
+                .or_else(|| self.block(pos, end, heap))
+                .or_else(|| self.op(pos, end, heap));
+            if let Some((node, next)) = sugared {
+                nodes.push(node);
+                pos = next;
+                continue;
+            }
+
+            if !(self.is(pos, "if") && if_block(&mut nodes, self.pattern_registry)) {
+                nodes.push(self.plain(pos));
+            }
+            pos += 1;
+        }
+
+        nodes
+    }
+
+    fn plain(&self, pos: usize) -> AstNode {
+        let iota = &self.iotas[pos];
+        let value = self.value(pos);
+        match iota
+            .downcast_ref::<PatternIota>()
+            .and_then(|pattern| self.pattern_name(pattern))
+        {
+            Some(name) => AstNode::Action {
+                location: Location::Unknown,
+                name,
+                value,
+            },
+            None => AstNode::Op {
+                location: Location::Unknown,
+                name: OpName::Embed,
+                arg: Some(OpValue::Iota(iota.clone())),
+            },
+        }
+    }
+
+    //the display name, if the pattern is found by it when the source is parsed again
+    fn pattern_name(&self, pattern: &PatternIota) -> Option<String> {
+        let signature = pattern.signature.as_str();
+        //Numerical Reflection and Bookkeeper's Gambit are written with the value they have
+        if pattern.value.is_some() {
+            return self
+                .pattern_registry
+                .find(&signature, &pattern.value)
+                .map(|entry| entry.display_name);
+        }
+        if signature.is_empty() {
+            return None;
+        }
+        let name = self
+            .pattern_registry
+            .iter()
+            .find(|entry| entry.signature == signature)?
+            .display_name
+            .clone();
+
+        let parses = HexParser::parse(Rule::Action, &name)
+            .is_ok_and(|mut pairs| pairs.next().unwrap().as_str() == name);
+        let found = self
+            .pattern_registry
+            .find(&name, &None)
+            .is_some_and(|entry| entry.signature == signature);
+        (parses && found).then_some(name)
+    }
+
+    fn signature(&self, pos: usize) -> Option<String> {
+        self.iotas
+            .get(pos)?
+            .downcast_ref::<PatternIota>()
+            .map(|pattern| pattern.signature.as_str())
+    }
+
+    fn is(&self, pos: usize, name: &str) -> bool {
+        self.signature(pos) == self.pattern_registry.find(name, &None).map(|p| p.signature)
+    }
+
+    fn value(&self, pos: usize) -> Option<ActionValue> {
+        *self
+            .iotas
+            .get(pos)?
+            .downcast_ref::<PatternIota>()?
+            .value
+            .clone()
+    }
+
+    fn is_number(&self, pos: usize, number: usize) -> bool {
+        self.value(pos) == Some(ActionValue::Iota(Rc::new(number as f64)))
+    }
+
+    fn is_mask(&self, pos: usize, code: &str) -> bool {
+        self.value(pos) == Some(ActionValue::Bookkeeper(code.to_string()))
+    }
+
+    //whether the patterns starting at `pos` are the ones named, `number 2` and `mask vv` have values
+    fn matches(&self, pos: usize, end: usize, names: &[&str]) -> bool {
+        pos + names.len() <= end
+            && names
+                .iter()
+                .enumerate()
+                .all(|(offset, name)| match name.split_once(' ') {
+                    Some(("number", number)) => {
+                        self.is_number(pos + offset, number.parse().unwrap())
+                    }
+                    Some(("mask", code)) => self.is_mask(pos + offset, code),
+                    _ => self.is(pos + offset, name),
+                })
+    }
+
+    //the index of the retrospection that closes the introspection at `open`
+    fn find_close(&self, open: usize, end: usize) -> Option<usize> {
+        let mut depth = 0;
+        let mut pos = open;
+        while pos < end {
+            if self.is(pos, "escape") {
+                pos += 1;
+            } else if self.is(pos, "open_paren") {
+                depth += 1;
+            } else if self.is(pos, "close_paren") {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(pos);
+                }
+            }
+            pos += 1;
+        }
+        None
+    }
+
+    fn block(&self, pos: usize, end: usize, heap: &Heap) -> Option<(AstNode, usize)> {
+        if !self.is(pos, "open_paren") {
+            return None;
+        }
+        let close = self.find_close(pos, end)?;
+        let nodes = self.decompile_range(pos + 1..close, &mut heap.clone());
+
+        //variables made in the block are removed from the ravenmind after it
+        let mut next = close + 1;
+        if !heap.is_empty() {
+            let block_end = [
+                "read/local",
+                "number 0",
+                &format!("number {}", heap.len()),
+                "slice",
+                "write/local",
+            ];
+            if !self.matches(next, end, &block_end) {
+                return None;
+            }
+            next += block_end.len();
+        }
+
+        let block = AstNode::Block {
+            external: false,
+            nodes,
+        };
+        Some((block, next))
+    }
+
+    fn external_block(&self, pos: usize, end: usize, heap: &Heap) -> Option<(AstNode, usize)> {
+        if !self.matches(pos, end, EXTERNAL) {
+            return None;
+        }
+        let (block, next) = self.block(pos + EXTERNAL.len(), end, heap)?;
+        if !self.matches(next, end, &["concat"]) {
+            return None;
+        }
+        let AstNode::Block { nodes, .. } = block else {
+            unreachable!()
+        };
+        let block = AstNode::Block {
+            external: true,
+            nodes,
+        };
+        Some((block, next + 1))
+    }
+
+    fn while_block(&self, pos: usize, end: usize, heap: &Heap) -> Option<(AstNode, usize)> {
+        let (condition, next) = self.block(pos, end, heap)?;
+        let condition_len = next - pos;
+        if !self.matches(next, end, &["eval", "open_paren", "open_paren"]) {
+            return None;
+        }
+        let (block, next) = self.block(next + 3, end, heap)?;
+        if !self.matches(next, end, WHILE_LOOP) {
+            return None;
+        }
+
+        //the condition is checked again at the end of every loop
+        let next = next + WHILE_LOOP.len();
+        let repeated = next + condition_len <= end
+            && (0..condition_len).all(|offset| {
+                self.iotas[pos + offset].tolerates_other(self.iotas[next + offset].as_ref())
+            });
+        let next = next + condition_len;
+        if !repeated || !self.matches(next, end, WHILE_EXIT) {
+            return None;
+        }
+
+        let while_block = AstNode::WhileBlock {
+            do_while: false,
+            location: Location::Unknown,
+            condition: Box::new(condition),
+            block: Box::new(block),
+        };
+        Some((while_block, next + WHILE_EXIT.len()))
+    }
+
+    fn op(&self, pos: usize, end: usize, heap: &mut Heap) -> Option<(AstNode, usize)> {
+        let op = |name, var: String, next| {
+            let node = AstNode::Op {
+                location: Location::Unknown,
+                name,
+                arg: Some(OpValue::Var(var)),
+            };
+            Some((node, next))
+        };
+
+        if self.is(pos, "duplicate") {
+            if let Some((
+                AstNode::Op {
+                    arg: Some(OpValue::Var(var)),
+                    ..
+                },
+                next,
+            )) = self.store(pos + 1, end, heap)
+            {
+                return op(OpName::Copy, var, next);
+            }
+        }
+        if let Some(store) = self.store(pos, end, heap) {
+            return Some(store);
+        }
+
+        let (var, _) = self.var_at(pos + 1, heap)?;
+        if self.matches(pos, end, &["read/local"]) && self.matches(pos + 2, end, &["index"]) {
+            return op(OpName::Push, var, pos + 3);
+        }
+        None
+    }
+
+    fn store(&self, pos: usize, end: usize, heap: &mut Heap) -> Option<(AstNode, usize)> {
+        let store = |var, next| {
+            let node = AstNode::Op {
+                location: Location::Unknown,
+                name: OpName::Store,
+                arg: Some(OpValue::Var(var)),
+            };
+            Some((node, next))
+        };
+
+        if self.matches(pos, end, &["read/local", "swap", "append", "write/local"]) {
+            let var = format!("$var{}", heap.len());
+            heap.insert(var.clone(), heap.len() as i32);
+            return store(var, pos + 4);
+        }
+
+        let (var, _) = self.var_at(pos + 1, heap)?;
+        let assign = ["rotate", "modify_in_place", "write/local"];
+        if self.matches(pos, end, &["read/local"]) && self.matches(pos + 2, end, &assign) {
+            return store(var, pos + 5);
+        }
+        None
+    }
+
+    //the variable whose index is pushed at `pos`
+    fn var_at(&self, pos: usize, heap: &Heap) -> Option<(String, i32)> {
+        heap.iter()
+            .find(|(_, index)| self.is_number(pos, **index as usize))
+            .map(|(var, index)| (var.clone(), *index))
+    }
+
+    pub fn print(&self, nodes: &[AstNode]) -> Result<String, String> {
+        let mut out = String::new();
+        for node in nodes {
+            self.print_node(node, 0, &mut out)?;
+            out.push('\n');
+        }
+        Ok(out)
+    }
+
+    fn print_node(&self, node: &AstNode, depth: usize, out: &mut String) -> Result<(), String> {
+        out.push_str(&INDENT.repeat(depth));
+        match node {
+            AstNode::Program(nodes) => {
+                for node in nodes {
+                    self.print_node(node, depth, out)?;
+                    out.push('\n');
+                }
+            }
+            AstNode::Action { name, value, .. } => out.push_str(&self.action_source(name, value)?),
+            AstNode::Block { external, nodes } => {
+                if *external {
+                    out.push_str("Extern ");
+                }
+                self.print_block(nodes, depth, out)?
+            }
+            AstNode::Op { name, arg, .. } => {
+                let arg = match arg {
+                    Some(OpValue::Var(var)) => var.clone(),
+                    Some(OpValue::Iota(iota)) => self.iota_source(iota.as_ref())?,
+                    None => String::new(),
+                };
+                out.push_str(&match name {
+                    OpName::Push if arg.starts_with('$') => arg,
+                    OpName::Embed => format!("<{arg}>"),
+                    OpName::SmartEmbed => format!("<<{arg}>>"),
+                    OpName::IntroEmbed => format!("<{{{arg}}}>"),
+                    OpName::ConsiderEmbed => format!("<\\{arg}>"),
+                    _ => format!("{name}({arg})"),
+                })
+            }
+            AstNode::MacroCall { name, args, .. } => {
+                let args = args
+                    .iter()
+                    .map(|arg| {
+                        let mut out = String::new();
+                        self.print_node(arg, 0, &mut out).map(|_| out)
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                out.push_str(&format!("{name}({})", args.join(", ")))
+            }
+            AstNode::IfBlock {
+                condition,
+                succeed,
+                fail,
+                ..
+            } => {
+                out.push_str("if ");
+                self.print_condition(condition, depth, out)?;
+                out.push_str(" then ");
+                self.print_branch(succeed, depth, out)?;
+                match fail.as_deref() {
+                    Some(AstNode::IfBlock { .. }) => {
+                        out.push_str(" else ");
+                        let start = out.len();
+                        self.print_node(fail.as_ref().unwrap(), depth, out)?;
+                        //the nested if is on the same line as the else
+                        out.replace_range(start..start + INDENT.len() * depth, "");
+                    }
+                    Some(fail) => {
+                        out.push_str(" else ");
+                        self.print_branch(fail, depth, out)?;
+                    }
+                    None => (),
+                }
+            }
+            AstNode::WhileBlock {
+                condition, block, ..
+            } => {
+                out.push_str("while ");
+                self.print_condition(condition, depth, out)?;
+                out.push_str(" do ");
+                self.print_branch(block, depth, out)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn print_block(&self, nodes: &[AstNode], depth: usize, out: &mut String) -> Result<(), String> {
+        if nodes.is_empty() {
+            out.push_str("{}");
+            return Ok(());
+        }
+        out.push_str("{\n");
+        for node in nodes {
+            self.print_node(node, depth + 1, out)?;
+            out.push('\n');
+        }
+        out.push_str(&INDENT.repeat(depth));
+        out.push('}');
+        Ok(())
+    }
+
+    fn print_branch(&self, node: &AstNode, depth: usize, out: &mut String) -> Result<(), String> {
+        match node {
+            AstNode::Block { nodes, .. } => self.print_block(nodes, depth, out),
+            node => self.print_block(std::slice::from_ref(node), depth, out),
+        }
+    }
+
+    //short conditions are kept on the line of the if or while
+    fn print_condition(
+        &self,
+        node: &AstNode,
+        depth: usize,
+        out: &mut String,
+    ) -> Result<(), String> {
+        match node {
+            AstNode::Block { nodes, .. } if nodes.len() == 1 && is_simple(&nodes[0]) => {
+                out.push('{');
+                self.print_node(&nodes[0], 0, out)?;
+                out.push('}');
+                Ok(())
+            }
+            node => self.print_branch(node, depth, out),
+        }
+    }
+
+    fn action_source(&self, name: &str, value: &Option<ActionValue>) -> Result<String, String> {
+        Ok(match value {
+            Some(ActionValue::Iota(iota)) => {
+                format!("{name}: {}", self.iota_source(iota.as_ref())?)
+            }
+            Some(ActionValue::Bookkeeper(code)) => format!("{name}: {code}"),
+            None => name.to_string(),
+        })
+    }
+
+    fn iota_source(&self, iota: &dyn Iota) -> Result<String, String> {
+        let number = |number: f64| {
+            if number.is_finite() {
+                Ok(number.to_string())
+            } else {
+                Err(format!("{number} can't be written in hexagon source"))
+            }
+        };
+
+        if let Some(value) = iota.downcast_ref::<NumberIota>() {
+            number(*value)
+        } else if let Some(vector) = iota.downcast_ref::<VectorIota>() {
+            Ok(format!(
+                "({}, {}, {})",
+                number(vector.x)?,
+                number(vector.y)?,
+                number(vector.z)?
+            ))
+        } else if let Some(value) = iota.downcast_ref::<bool>() {
+            Ok(if *value { "True" } else { "False" }.to_string())
+        } else if iota.downcast_ref::<NullIota>().is_some() {
+            Ok("Null".to_string())
+        } else if iota.downcast_ref::<GarbageIota>().is_some() {
+            Ok("Garbage".to_string())
+        } else if let Some(string) = iota.downcast_ref::<StringIota>() {
+            Ok(format!(
+                "\"{}\"",
+                string.replace('\\', "\\\\").replace('"', "\\\"")
+            ))
+        } else if let Some(entity) = iota.downcast_ref::<EntityIota>() {
+            Ok(format!("@{}", entity.name))
+        } else if let Some(list) = iota.downcast_ref::<ListIota>() {
+            let iotas = list
+                .iter()
+                .map(|iota| self.iota_source(iota.as_ref()))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(format!("[{}]", iotas.join(", ")))
+        } else if let Some(matrix) = iota.downcast_ref::<MatrixIota>() {
+            //the parser reads the values a column at a time
+            let values = matrix
+                .iter()
+                .map(|value| number(*value))
+                .collect::<Result<Vec<_>, _>>()?;
+            let size = format!("({}, {})", matrix.nrows(), matrix.ncols());
+            if values.is_empty() {
+                Ok(format!("[{size}]"))
+            } else {
+                Ok(format!("[{size} | {}]", values.join(", ")))
+            }
+        } else if let Some(pattern) = iota.downcast_ref::<PatternIota>() {
+            match self.pattern_name(pattern) {
+                Some(name) => self.action_source(&name, &pattern.value),
+                None => Ok(format!(
+                    "{} {}",
+                    pattern.start_dir,
+                    pattern.signature.as_str()
+                )),
+            }
+        } else {
+            Err(format!(
+                "{} can't be written in hexagon source",
+                iota.display()
+            ))
+        }
+    }
+}
+
+//Augur's Exaltation after two blocks is an if, the nodes before them are the condition
+fn if_block(nodes: &mut Vec<AstNode>, pattern_registry: &PatternRegistry) -> bool {
+    let is_eval = |node: &AstNode| match node {
+        AstNode::Action { name, .. } => pattern_registry
+            .find(name, &None)
+            .is_some_and(|pattern| pattern.internal_name == "eval"),
+        _ => false,
+    };
+    let is_block = |node: Option<&AstNode>| {
+        matches!(
+            node,
+            Some(AstNode::Block {
+                external: false,
+                ..
+            })
+        )
+    };
+
+    //an else if is run by Hermes' Gambit
+    let else_if = nodes.last().is_some_and(is_eval);
+    let len = nodes.len() - else_if as usize;
+    if len < 2 || !is_block(nodes.get(len - 2)) || !is_block(nodes.get(len - 1)) {
+        return false;
+    }
+
+    let fail = match &nodes[len - 1] {
+        AstNode::Block { nodes: fail, .. } if else_if => match fail.split_last() {
+            //the rest of the block is part of the condition of the nested if
+            Some((
+                AstNode::IfBlock {
+                    location,
+                    condition,
+                    succeed,
+                    fail: nested_fail,
+                },
+                prefix,
+            )) => {
+                let AstNode::Block {
+                    nodes: condition, ..
+                } = condition.as_ref()
+                else {
+                    return false;
+                };
+                Some(Box::new(AstNode::IfBlock {
+                    location: *location,
+                    condition: Box::new(AstNode::Block {
+                        external: false,
+                        nodes: prefix.iter().chain(condition).cloned().collect(),
+                    }),
+                    succeed: succeed.clone(),
+                    fail: nested_fail.clone(),
+                }))
+            }
+            _ => return false,
+        },
+        AstNode::Block { nodes: fail, .. } if fail.is_empty() => None,
+        fail => Some(Box::new(fail.clone())),
+    };
+
+    let succeed = Box::new(nodes[len - 2].clone());
+    nodes.truncate(len - 2);
+
+    //the compiler doesn't keep track of where the condition started, so only the last
+    //pattern before the blocks is used
+    let condition = match nodes.last() {
+        Some(node) if is_simple(node) => vec![nodes.pop().unwrap()],
+        _ => vec![],
+    };
+
+    nodes.push(AstNode::IfBlock {
+        location: Location::Unknown,
+        condition: Box::new(AstNode::Block {
+            external: false,
+            nodes: condition,
+        }),
+        succeed,
+        fail,
+    });
+    true
+}
+
+fn is_simple(node: &AstNode) -> bool {
+    matches!(
+        node,
+        AstNode::Action { .. }
+            | AstNode::Op {
+                name: OpName::Push | OpName::Embed,
+                ..
+            }
+    )
+}
+
+//what `Extern` blocks start with, see compiler/external.rs
+const EXTERNAL: &[&str] = &[
+    "open_paren",
+    "read/local",
+    "const/null",
+    "equals",
+    "open_paren",
+    "open_paren",
+    "mask -",
+    "close_paren",
+    "splat",
+    "write/local",
+    "close_paren",
+    "empty_list",
+    "if",
+    "eval",
+    "close_paren",
+    "number 5",
+    "read/local",
+    "modify_in_place",
+];
+
+//what comes after the block of a while loop, see compiler/while_block.rs
+const WHILE_LOOP: &[&str] = &[
+    "open_paren",
+    "mask vv",
+    "close_paren",
+    "swap",
+    "concat",
+    "const/null",
+    "singleton",
+    "for_each",
+    "number 2",
+    "last_n_list",
+    "stack_len",
+    "last_n_list",
+    "reverse_list",
+    "deconstruct",
+    "swap",
+    "mask v",
+    "splat",
+    "swap",
+    "append",
+    "splat",
+];
+
+//what comes after the repeated condition of a while loop
+const WHILE_EXIT: &[&str] = &[
+    "eval",
+    "open_paren",
+    "duplicate",
+    "eval",
+    "close_paren",
+    "open_paren",
+    "mask v",
+    "close_paren",
+    "splat",
+    "if",
+    "eval",
+    "close_paren",
+    "duplicate",
+    "eval",
+    "close_paren",
+    "empty_list",
+    "if",
+    "eval",
+];
+
+#[cfg(test)]
+mod tests {
+    use crate::{compiler::compile_to_iotas, parser::parse};
+
+    use super::*;
+
+    fn round_trip(source: &str) -> String {
+        let config = Config::default();
+        let registry = PatternRegistry::from_config(&config);
+        let compile = |source: &str| {
+            let (ast, macros, _) = parse(source, "", &registry, &mut HashMap::new()).unwrap();
+            compile_to_iotas(&ast, None, &registry, &macros).unwrap()
+        };
+
+        let iotas = compile(source);
+        let snbt = crate::compiler::nbt::compile_nbt(iotas.clone());
+        let decompiled = decompile(&snbt, &config).unwrap();
+
+        //the decompiled source has to compile to the same hex
+        let recompiled = compile(&decompiled);
+        assert_eq!(
+            iotas
+                .iter()
+                .map(|iota| iota.serialize_to_nbt())
+                .collect::<Vec<_>>(),
+            recompiled
+                .iter()
+                .map(|iota| iota.serialize_to_nbt())
+                .collect::<Vec<_>>(),
+            "{decompiled}"
+        );
+        decompiled
+    }
+
+    #[test]
+    fn decompile_test() {
+        assert_eq!(
+            round_trip("Mind's Reflection\n<(1, 0, 0)>\n{\n    Reveal\n}"),
+            "Mind's Reflection\n<(1, 0, 0)>\n{\n    Reveal\n}\n"
+        );
+
+        let source = "\
+Mind's Reflection
+Store($var0)
+Copy($var0)
+if {Equality Distillation} then {
+    $var0
+    Store($var1)
+} else if {$var0} then {
+    Reveal
+} else {
+    True Reflection
+}
+while {Vacant Reflection} do {
+    Store($var0)
+}
+Extern {
+    Reveal
+}
+";
+        assert_eq!(round_trip(source), source);
+
+        let json = r#"[{"iota_type": "pattern", "value": "qaq", "start_dir": "NORTH_EAST"}, {"iota_type": "string", "value": "hi"}]"#;
+        assert_eq!(
+            decompile(json, &Config::default()).unwrap(),
+            "Mind's Reflection\n<\"hi\">\n"
+        );
+        //hand drawn numbers and masks get their values from the angles
+        let json = r#"[{"iota_type": "pattern", "value": "aqaaeeaww"}, {"iota_type": "list", "value": [{"iota_type": "pattern", "value": "ae"}]}]"#;
+        assert_eq!(
+            decompile(json, &Config::default()).unwrap(),
+            "Numerical Reflection: 42\n<[Bookkeeper's Gambit: v-]>\n"
+        );
+        assert!(decompile(
+            "{hex: [{type: \"hexcasting:entity\", value: {uuid: [I; 1, 2, 3, 4]}}]}",
+            &Config::default()
+        )
+        .is_err());
+    }
+}
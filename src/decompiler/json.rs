@@ -0,0 +1,84 @@
+//reads the iotas written by `hexagon build --format json`
+use std::{collections::HashMap, rc::Rc};
+
+use im::Vector;
+use serde_json::Value;
+
+use crate::{
+    interpreter::state::Entity,
+    iota::{
+        hex_casting::{
+            garbage::GarbageIota, null::NullIota, pattern::PatternIota, vector::VectorIota,
+        },
+        Iota,
+    },
+    parser::Location,
+    pattern_registry::PatternRegistry,
+};
+
+use super::find_entity;
+
+pub fn json_to_iota(
+    json: &Value,
+    registry: &PatternRegistry,
+    entities: &HashMap<String, Entity>,
+) -> Result<Rc<dyn Iota>, String> {
+    let iota_type = json
+        .get("iota_type")
+        .and_then(Value::as_str)
+        .ok_or("expected an iota with an iota_type")?;
+    let value = json.get("value");
+    let invalid = || format!("invalid {iota_type} iota");
+
+    Ok(match iota_type {
+        "number" => Rc::new(value.and_then(Value::as_f64).ok_or_else(invalid)?),
+        "boolean" => Rc::new(value.and_then(Value::as_bool).ok_or_else(invalid)?),
+        "null" => Rc::new(NullIota),
+        "garbage" => Rc::new(GarbageIota),
+        "vector" => {
+            let coord = |axis| {
+                value
+                    .and_then(|value| value.get(axis))
+                    .and_then(Value::as_f64)
+                    .ok_or_else(invalid)
+            };
+            Rc::new(VectorIota::new(coord("x")?, coord("y")?, coord("z")?))
+        }
+        "list" => Rc::new(
+            value
+                .and_then(Value::as_array)
+                .ok_or_else(invalid)?
+                .iter()
+                .map(|iota| json_to_iota(iota, registry, entities))
+                .collect::<Result<Vector<_>, _>>()?,
+        ),
+        "pattern" => {
+            let signature = value
+                .and_then(Value::as_str)
+                .filter(|sig| sig.chars().all(|char| "qaswed".contains(char)))
+                .ok_or_else(invalid)?;
+            let start_dir = json
+                .get("start_dir")
+                .and_then(Value::as_str)
+                .map_or(Ok(Default::default()), str::parse)
+                .map_err(|_| invalid())?;
+            Rc::new(PatternIota::from_angles(
+                registry,
+                signature,
+                start_dir,
+                Location::Unknown,
+            ))
+        }
+        "entity" => Rc::new(find_entity(
+            value.and_then(Value::as_str).ok_or_else(invalid)?,
+            entities,
+        )?),
+        "string" => Rc::new(
+            value
+                .and_then(Value::as_str)
+                .ok_or_else(invalid)?
+                .to_string(),
+        ),
+        _ => Err(format!("unsupported iota type '{iota_type}'"))?,
+    })
+}
@@ -0,0 +1,373 @@
+//a reader for stringified NBT, the format of `/give` commands and `/data get`
+use std::{collections::HashMap, rc::Rc};
+
+use im::Vector;
+
+use crate::{
+    interpreter::state::Entity,
+    iota::{
+        hex_casting::{
+            garbage::GarbageIota,
+            null::NullIota,
+            pattern::{PatternIota, DIRECTIONS},
+            vector::VectorIota,
+        },
+        more_iotas::matrix::MatrixIota,
+        Iota,
+    },
+    parser::Location,
+    pattern_registry::PatternRegistry,
+};
+
+use super::find_entity;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Nbt {
+    Compound(Vec<(String, Nbt)>),
+    List(Vec<Nbt>),
+    //byte, int and long arrays
+    Array(Vec<i64>),
+    String(String),
+    Number(f64),
+}
+
+impl Nbt {
+    pub fn get(&self, key: &str) -> Option<&Nbt> {
+        match self {
+            Nbt::Compound(entries) => entries
+                .iter()
+                .find(|(entry_key, _)| entry_key == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Nbt::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    fn as_number(&self) -> Option<f64> {
+        match self {
+            Nbt::Number(number) => Some(*number),
+            _ => None,
+        }
+    }
+}
+
+pub fn parse_snbt(input: &str) -> Result<Nbt, String> {
+    let mut reader = Reader {
+        chars: input.chars().collect(),
+        pos: 0,
+    };
+    let value = reader.value()?;
+    reader.skip_whitespace();
+    if reader.pos < reader.chars.len() {
+        Err(reader.error("expected the end of the input"))?
+    }
+    Ok(value)
+}
+
+struct Reader {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Reader {
+    fn error(&self, message: &str) -> String {
+        format!("{message} at character {}", self.pos + 1)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        self.skip_whitespace();
+        if self.peek() == Some(expected) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{expected}'")))
+        }
+    }
+
+    //reads a comma separated sequence up to the closing bracket
+    fn sequence<T>(
+        &mut self,
+        close: char,
+        mut item: impl FnMut(&mut Reader) -> Result<T, String>,
+    ) -> Result<Vec<T>, String> {
+        let mut items = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some(close) {
+            self.pos += 1;
+            return Ok(items);
+        }
+        loop {
+            items.push(item(self)?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some(char) if char == close => {
+                    self.pos += 1;
+                    return Ok(items);
+                }
+                _ => Err(self.error(&format!("expected ',' or '{close}'")))?,
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<Nbt, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') => {
+                self.pos += 1;
+                let entries = self.sequence('}', |reader| {
+                    let key = reader.string()?;
+                    reader.expect(':')?;
+                    Ok((key, reader.value()?))
+                })?;
+                Ok(Nbt::Compound(entries))
+            }
+            Some('[') => {
+                self.pos += 1;
+                let typed = matches!(self.peek(), Some('B' | 'I' | 'L'))
+                    && self.chars.get(self.pos + 1) == Some(&';');
+                if typed {
+                    self.pos += 2;
+                    let values = self.sequence(']', |reader| {
+                        let number = reader.string()?;
+                        number
+                            .trim_end_matches(['b', 'B', 's', 'S', 'l', 'L'])
+                            .parse()
+                            .map_err(|_| reader.error(&format!("invalid integer '{number}'")))
+                    })?;
+                    Ok(Nbt::Array(values))
+                } else {
+                    Ok(Nbt::List(self.sequence(']', Reader::value)?))
+                }
+            }
+            Some('"' | '\'') => Ok(Nbt::String(self.string()?)),
+            _ => {
+                let word = self.string()?;
+                Ok(parse_number(&word).map_or(Nbt::String(word), Nbt::Number))
+            }
+        }
+    }
+
+    //a quoted string, or a word that isn't quoted like a key or a number
+    fn string(&mut self) -> Result<String, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(quote @ ('"' | '\'')) => {
+                self.pos += 1;
+                let mut string = String::new();
+                loop {
+                    match self.peek() {
+                        Some('\\') => {
+                            self.pos += 1;
+                            string.extend(self.peek());
+                        }
+                        Some(char) if char == quote => {
+                            self.pos += 1;
+                            return Ok(string);
+                        }
+                        Some(char) => string.push(char),
+                        None => Err(self.error("unclosed string"))?,
+                    }
+                    self.pos += 1;
+                }
+            }
+            _ => {
+                let start = self.pos;
+                while self
+                    .peek()
+                    .is_some_and(|char| char.is_alphanumeric() || "_-.+".contains(char))
+                {
+                    self.pos += 1;
+                }
+                if start == self.pos {
+                    Err(self.error("expected a value"))?
+                }
+                Ok(self.chars[start..self.pos].iter().collect())
+            }
+        }
+    }
+}
+
+fn parse_number(word: &str) -> Option<f64> {
+    match word {
+        "true" => return Some(1.0),
+        "false" => return Some(0.0),
+        _ => (),
+    }
+    let number = word
+        .strip_suffix(['b', 'B', 's', 'S', 'l', 'L', 'f', 'F', 'd', 'D'])
+        .unwrap_or(word);
+    number.parse().ok().filter(|_| {
+        number
+            .chars()
+            .all(|char| char.is_ascii_digit() || "-.eE".contains(char))
+    })
+}
+
+//the angles of a pattern in the order the mod numbers them
+const ANGLES: [char; 6] = ['w', 'e', 'd', 's', 'a', 'q'];
+
+//reads both the iotas written by `hexagon build` and the ones saved by the mod
+pub fn nbt_to_iota(
+    nbt: &Nbt,
+    registry: &PatternRegistry,
+    entities: &HashMap<String, Entity>,
+) -> Result<Rc<dyn Iota>, String> {
+    let iota_type = nbt
+        .get("type")
+        .or(nbt.get("hexcasting:type"))
+        .and_then(Nbt::as_str)
+        .ok_or("expected an iota with a type")?;
+    let value = nbt.get("value").or(nbt.get("hexcasting:data"));
+    let invalid = || format!("invalid {iota_type} iota");
+    let value_or_invalid = || value.ok_or_else(invalid);
+
+    Ok(match iota_type {
+        "hexcasting:double" => Rc::new(value_or_invalid()?.as_number().ok_or_else(invalid)?),
+        "hexcasting:boolean" => {
+            Rc::new(value_or_invalid()?.as_number().ok_or_else(invalid)? != 0.0)
+        }
+        "hexcasting:null" => Rc::new(NullIota),
+        "hexcasting:garbage" => Rc::new(GarbageIota),
+        "hexcasting:vec3" => match value_or_invalid()? {
+            Nbt::Array(values) if values.len() == 3 => {
+                let [x, y, z] = [0, 1, 2].map(|index| f64::from_bits(values[index] as u64));
+                Rc::new(VectorIota::new(x, y, z))
+            }
+            _ => Err(invalid())?,
+        },
+        "hexcasting:list" => match value_or_invalid()? {
+            Nbt::List(iotas) => Rc::new(
+                iotas
+                    .iter()
+                    .map(|iota| nbt_to_iota(iota, registry, entities))
+                    .collect::<Result<Vector<_>, _>>()?,
+            ),
+            _ => Err(invalid())?,
+        },
+        "hexcasting:pattern" => {
+            let value = value_or_invalid()?;
+            let signature: String = match value.get("angles").ok_or_else(invalid)? {
+                Nbt::String(angles) if angles.chars().all(|char| ANGLES.contains(&char)) => {
+                    angles.clone()
+                }
+                Nbt::Array(angles) => angles
+                    .iter()
+                    .map(|angle| ANGLES.get(*angle as usize).ok_or_else(invalid))
+                    .collect::<Result<_, _>>()?,
+                _ => Err(invalid())?,
+            };
+            let start_dir = match value.get("start_dir").ok_or_else(invalid)? {
+                Nbt::String(dir) => dir.parse().map_err(|_| invalid())?,
+                Nbt::Number(dir) => *DIRECTIONS.get(*dir as usize).ok_or_else(invalid)?,
+                _ => Err(invalid())?,
+            };
+            Rc::new(PatternIota::from_angles(
+                registry,
+                &signature,
+                start_dir,
+                Location::Unknown,
+            ))
+        }
+        "hexcasting:entity" => {
+            let uuid = match value_or_invalid()?.get("uuid").ok_or_else(invalid)? {
+                Nbt::Array(uuid) => format!(
+                    "[I;{}]",
+                    uuid.iter()
+                        .map(i64::to_string)
+                        .collect::<Vec<_>>()
+                        .join(",")
+                ),
+                Nbt::String(uuid) => uuid.clone(),
+                _ => Err(invalid())?,
+            };
+            Rc::new(find_entity(&uuid, entities)?)
+        }
+        "moreiotas:string" => Rc::new(
+            value_or_invalid()?
+                .as_str()
+                .ok_or_else(invalid)?
+                .to_string(),
+        ),
+        "moreiotas:matrix" => {
+            let value = value_or_invalid()?;
+            let size = |key| value.get(key).and_then(Nbt::as_number).ok_or_else(invalid);
+            let (nrows, ncols) = (size("rows")? as usize, size("cols")? as usize);
+            //the values are stored a column at a time
+            let data = match value.get("mat").ok_or_else(invalid)? {
+                Nbt::List(cols) => cols
+                    .iter()
+                    .flat_map(|col| match col {
+                        Nbt::List(col) => col.iter().map(Nbt::as_number).collect(),
+                        _ => vec![None],
+                    })
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(invalid)?,
+                _ => Err(invalid())?,
+            };
+            if data.len() != nrows * ncols {
+                Err(invalid())?
+            }
+            Rc::new(MatrixIota::from_vec(nrows, ncols, data))
+        }
+        _ => Err(format!("unsupported iota type '{iota_type}'"))?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        iota::hex_casting::pattern::{Direction, SignatureExt},
+        pattern_registry::PatternRegistryExt,
+    };
+
+    use super::*;
+
+    #[test]
+    fn parse_snbt_test() {
+        let nbt = parse_snbt(
+            r#"{hex: [{type: "hexcasting:double", value: 1.5d}], 'a b': [B; 1b, 0b], c: "\"x\""}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            nbt.get("hex"),
+            Some(&Nbt::List(vec![Nbt::Compound(vec![
+                (
+                    "type".to_string(),
+                    Nbt::String("hexcasting:double".to_string())
+                ),
+                ("value".to_string(), Nbt::Number(1.5))
+            ])]))
+        );
+        assert_eq!(nbt.get("a b"), Some(&Nbt::Array(vec![1, 0])));
+        assert_eq!(nbt.get("c"), Some(&Nbt::String("\"x\"".to_string())));
+        assert!(parse_snbt("{hex: [}").is_err());
+
+        //the way the mod saves patterns
+        let pattern = parse_snbt(
+            r#"{"hexcasting:type": "hexcasting:pattern", "hexcasting:data": {angles: [B; 5b, 5b, 1b], start_dir: 1b}}"#,
+        )
+        .unwrap();
+        let registry = PatternRegistry::construct(&PatternRegistry::gen_default_great_sigs());
+        let pattern = nbt_to_iota(&pattern, &registry, &HashMap::new()).unwrap();
+        let pattern = pattern.downcast_ref::<PatternIota>().unwrap();
+        assert_eq!(pattern.signature.as_str(), "qqe");
+        assert_eq!(pattern.start_dir, Direction::East);
+    }
+}
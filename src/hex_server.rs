@@ -7,7 +7,7 @@ use crate::{compiler::nbt::compile_nbt, iota::Iota};
 pub fn send_hex(iota_list: Vec<Rc<dyn Iota>>, url: &str) -> Result<String, reqwest::Error> {
     let client = reqwest::blocking::Client::new();
     let content = [("SNBT", compile_nbt(iota_list))];
-    let response = client.post(url).form(&content).send()?.text()?;
+    let response = client.post(url).form(&content).send()?.error_for_status()?.text()?;
 
     Ok(response)
 }
\ No newline at end of file
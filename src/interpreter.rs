@@ -12,6 +12,7 @@ use crate::{
     compiler::{
         compile_node,
         if_block::compile_if_block,
+        number::compile_number,
         ops::{compile_op_copy, compile_op_embed, compile_op_push, compile_op_store},
         while_block::{compile_do_while_block, compile_while_block},
     },
@@ -19,12 +20,15 @@ use crate::{
     iota::{
         hex_casting::{
             null::NullIota,
+            number::NumberIota,
             pattern::{PatternIota, Signature, SignatureExt},
         },
         Iota,
     },
     parse_config::Config,
-    parser::{ActionValue, AstNode, Location, Macros, OpName, OpValue},
+    parser::{
+        expand_macro, ActionValue, AstNode, Location, Macros, OpName, OpValue,
+    },
     pattern_registry::{PatternRegistry, PatternRegistryExt},
 };
 
@@ -32,29 +36,55 @@ use self::{
     continuation::{
         iota_list_to_ast_node_list, ContinuationFrame, ContinuationFrameTrait, FrameEvaluate,
     },
-    error::print_interpreter_error,
     mishap::Mishap,
-    state::{Considered, Entity, EntityType, Holding, State},
+    state::{Considered, Entity, EntityType, Holding, Rng, State},
 };
 
 pub fn interpret(
     node: AstNode,
     config: &Config,
     macros: Macros,
-    source: &str,
-    source_path: &str,
+) -> Result<State, (Mishap, Location, String, State)> {
+    interpret_with_hook(node, config, macros, &mut |_| true)
+}
+
+//like interpret, but calls `on_step` after every step, the hex is interrupted if it returns false
+pub fn interpret_with_hook(
+    node: AstNode,
+    config: &Config,
+    macros: Macros,
+    on_step: &mut dyn FnMut(&State) -> bool,
+) -> Result<State, (Mishap, Location, String, State)> {
+    let pattern_registry = PatternRegistry::from_config(config);
+    let mut state = init_state(node, config, &pattern_registry, &macros)?;
+
+    (run_vm(&mut state, &pattern_registry, &macros, config.in_game_mishaps, on_step))
+        .map(|state| state.clone())
+}
+
+//sets up a state that is ready to evaluate the program
+pub fn init_state(
+    node: AstNode,
+    config: &Config,
+    pattern_registry: &PatternRegistry,
+    macros: &Macros,
 ) -> Result<State, (Mishap, Location, String, State)> {
     let mut state = State {
         ..Default::default()
     };
     state.entities = config.entities.clone();
     state.libraries = config.libraries.clone();
-
-    let pattern_registry = PatternRegistry::construct(&config.great_spell_sigs);
+    state.media_budget = config.media_budget;
+    state.limits = config.limits;
+    if let Some(seed) = config.seed {
+        state.rng = Rng::seeded(seed);
+    }
 
     //compile to get heap size so that the ravenmind can be set to the right length
     //TODO: replace this with a thing that just looks for var nodes and counts them or something
-    compile_node(&node, &mut state.heap, 0, &pattern_registry, &macros).unwrap();
+    compile_node(&node, &mut state.heap, 0, pattern_registry, macros).map_err(
+        |(mishap, location, caused_by)| (mishap, location, caused_by, state.clone()),
+    )?;
     let null: Rc<dyn Iota> = Rc::new(NullIota);
     state.ravenmind = Some(Rc::new(Vector::from(vec![null; state.heap.keys().len()])));
 
@@ -74,25 +104,6 @@ pub fn interpret(
         }
     }
 
-    (run_vm(
-        node,
-        &mut state,
-        &pattern_registry,
-        &macros,
-        source,
-        source_path,
-    ))
-    .map(|state| state.clone())
-}
-
-fn run_vm<'a>(
-    node: AstNode,
-    state: &'a mut State,
-    pattern_registry: &PatternRegistry,
-    macros: &Macros,
-    source: &str,
-    source_path: &str,
-) -> Result<&'a mut State, (Mishap, Location, String, State)> {
     match node {
         AstNode::Program(nodes) => {
             //initialize the vm
@@ -101,42 +112,113 @@ fn run_vm<'a>(
                 .push_back(ContinuationFrame::Evaluate(FrameEvaluate {
                     nodes_queue: Vector::from(nodes),
                 }));
+        }
+        _ => unreachable!(),
+    }
 
-            //loop through every frame until there aren't any more
-            while !state.continuation.is_empty() {
-                //get top frame and remove it from the stack
-                let frame = state.continuation.pop_back().unwrap();
-
-                //evaluate the top frame (mutates state)
-                frame.evaluate(state, pattern_registry, macros).map_err(
-                    |(mishap, location, caused_by)| {
-                        state.stack = mishap.apply_to_stack(&state.stack);
-                        (mishap, location, caused_by, state.clone())
-                    },
-                )?;
+    Ok(state)
+}
+
+//evaluates the top frame of the continuation
+pub fn step_vm(
+    state: &mut State,
+    pattern_registry: &PatternRegistry,
+    macros: &Macros,
+) -> Result<(), (Mishap, Location, String)> {
+    //get top frame and remove it from the stack
+    let frame = state.continuation.pop_back().unwrap();
+
+    //evaluate the top frame (mutates state)
+    frame
+        .evaluate(state, pattern_registry, macros)
+        .map_err(|(mishap, location, caused_by)| {
+            state.stack = mishap.apply_to_stack(&state.stack);
+            (mishap, location, caused_by)
+        })
+}
+
+fn run_vm<'a>(
+    state: &'a mut State,
+    pattern_registry: &PatternRegistry,
+    macros: &Macros,
+    in_game_mishaps: bool,
+    on_step: &mut dyn FnMut(&State) -> bool,
+) -> Result<&'a mut State, (Mishap, Location, String, State)> {
+    //loop through every frame until there aren't any more
+    while !state.continuation.is_empty() {
+        match step_vm(state, pattern_registry, macros) {
+            Ok(()) => (),
+            Err((mishap, location, caused_by)) if in_game_mishaps && mishap.keeps_casting() => {
+                //the mishap ends any evaluation it happened in, but like drawing with a staff,
+                //the next pattern of the hex is still cast
+                state.continuation.truncate(1);
+                state.consider_next = false;
+                state.mishaps.push_back((mishap, location, caused_by));
             }
+            Err((mishap, location, caused_by)) => {
+                return Err((mishap, location, caused_by, state.clone()))
+            }
+        }
 
-            while !state.wisps.is_empty() {
-                std::thread::sleep(Duration::from_millis(50));
-                //technically this means that a destroyed wisp can still execute once more in some situations
-                for (name, wisp) in state.wisps.clone().iter() {
-                    let result = wisp.evaluate(state, pattern_registry, macros);
-                    match result {
-                        Ok(wisp) => {
-                            state.wisps.insert(name.clone(), wisp);
-                        }
-                        Err(err) => {
-                            print_interpreter_error(err, source, source_path);
-                            state.wisps.remove(name);
-                        }
-                    }
+        if !on_step(state) {
+            let location = match state.continuation.last() {
+                Some(ContinuationFrame::Evaluate(frame)) => frame.nodes_queue.front().and_then(AstNode::location),
+                _ => None,
+            };
+            return Err((
+                Mishap::Interrupted,
+                location.unwrap_or(Location::Unknown),
+                String::new(),
+                state.clone(),
+            ));
+        }
+    }
+
+    run_wisps(state, pattern_registry, macros, on_step)
+        .map_err(|(mishap, location, caused_by)| (mishap, location, caused_by, state.clone()))?;
+
+    Ok(state)
+}
+
+//the length of a game tick, the command line waits this long between wisp ticks so that they
+//run at the same pace as in-game
+pub const WISP_TICK: Duration = Duration::from_millis(50);
+
+//ticks every wisp until there are none left, calling `on_step` after every tick. The wisps are
+//stopped if it returns false, or when they go over the limits of the hex that summoned them
+pub fn run_wisps(
+    state: &mut State,
+    pattern_registry: &PatternRegistry,
+    macros: &Macros,
+    on_step: &mut dyn FnMut(&State) -> bool,
+) -> Result<(), (Mishap, Location, String)> {
+    while !state.wisps.is_empty() {
+        //technically this means that a destroyed wisp can still execute once more in some situations
+        for (name, wisp) in state.wisps.clone().iter() {
+            //a tick counts as a pattern, so even a wisp that casts nothing runs out
+            state.op_count += 1;
+            let result = wisp.evaluate(state, pattern_registry, macros);
+            match result {
+                Ok(wisp) => {
+                    state.wisps.insert(name.clone(), wisp);
+                }
+                //a wisp that mishaps is destroyed, the rest of the hex carries on
+                Err(err) => {
+                    state.mishaps.push_back(err);
+                    state.wisps.remove(name);
                 }
             }
+        }
 
-            Ok(state)
+        if state.op_count > state.limits.max_ops {
+            let limit = state.limits.max_ops;
+            return Err((Mishap::TooManyPatterns { limit }, Location::Unknown, "wisp".to_string()));
+        }
+        if !on_step(state) {
+            return Err((Mishap::Interrupted, Location::Unknown, "wisp".to_string()));
         }
-        _ => unreachable!(),
     }
+    Ok(())
 }
 
 fn interpret_node<'a>(
@@ -317,6 +399,18 @@ fn interpret_node<'a>(
             }
             Ok(state)
         }
+        AstNode::MacroCall {
+            location,
+            name,
+            args,
+        } => {
+            let nodes = macros
+                .get(&name)
+                .ok_or(Mishap::InvalidPattern)
+                .and_then(|(_, params, body)| expand_macro(params, body, &args))
+                .map_err(|mishap| (mishap, location, name))?;
+            interpret_macro(nodes, state, pattern_registry, macros)
+        }
         AstNode::Program(_) => unreachable!(),
     }
 }
@@ -386,6 +480,41 @@ pub fn interpret_op<'a>(
     Ok(state)
 }
 
+fn interpret_macro<'a>(
+    nodes: Vec<AstNode>,
+    state: &'a mut State,
+    pattern_registry: &PatternRegistry,
+    macros: &Macros,
+) -> Result<&'a mut State, (Mishap, Location, String)> {
+    if let Some(ref mut buffer) = state.buffer {
+        let compiled = compile_node(
+            &AstNode::Program(nodes),
+            &mut state.heap,
+            calc_buffer_depth(&pattern_registry, &Some(buffer.clone())),
+            pattern_registry,
+            macros,
+        )?
+        .into_iter()
+        .map(|x| (x, false))
+        .collect::<Vector<_>>();
+        buffer.append(compiled);
+    } else {
+        //the macro is evaluated before the rest of the frame it was called in, which isn't on
+        //the continuation anymore if the macro was the last node of the frame
+        let mut new_frame = Vector::from(nodes);
+        if let Some(ContinuationFrame::Evaluate(eval_frame)) = state.continuation.last() {
+            new_frame.append(eval_frame.nodes_queue.clone());
+            state.continuation.pop_back();
+        }
+        state
+            .continuation
+            .push_back(ContinuationFrame::Evaluate(FrameEvaluate {
+                nodes_queue: new_frame,
+            }));
+    }
+    Ok(state)
+}
+
 pub fn interpret_action<'a>(
     name: String,
     value: Option<ActionValue>,
@@ -394,34 +523,10 @@ pub fn interpret_action<'a>(
     macros: &Macros,
     location: Location,
 ) -> Result<&'a mut State, (Mishap, Location, String)> {
-    if let Some((_, AstNode::Block { external: _, nodes })) = macros.get(&name) {
+    if let Some((_, params, body)) = macros.get(&name) {
         //check for macro and apply it
-        if let Some(ref mut buffer) = state.buffer {
-            let compiled = compile_node(
-                &AstNode::Program(nodes.clone()),
-                &mut state.heap,
-                calc_buffer_depth(&pattern_registry, &Some(buffer.clone())),
-                pattern_registry,
-                macros,
-            )
-            .unwrap()
-            .into_iter()
-            .map(|x| (x, false))
-            .collect::<Vector<_>>();
-            buffer.append(compiled);
-            return Ok(state);
-        } else if let ContinuationFrame::Evaluate(eval_frame) =
-            state.continuation.pop_back().unwrap()
-        {
-            let mut new_frame = Vector::from(nodes);
-            new_frame.append(eval_frame.nodes_queue);
-            state
-                .continuation
-                .push_back(ContinuationFrame::Evaluate(FrameEvaluate {
-                    nodes_queue: new_frame,
-                }));
-            return Ok(state);
-        }
+        let nodes = expand_macro(params, body, &[]).map_err(|mishap| (mishap, location, name))?;
+        return interpret_macro(nodes, state, pattern_registry, macros);
     }
 
     let patterns = pattern_registry.find_all(&name, &value);
@@ -442,6 +547,22 @@ pub fn interpret_action<'a>(
     }
 
     if state.buffer.is_some() && !(is_escape || is_retro) {
+        //a number that needs several patterns is introspected as all of them, the same as when compiled
+        let number = match &value {
+            Some(ActionValue::Iota(iota)) if patterns[0].internal_name == "number" => {
+                iota.downcast_ref::<NumberIota>().copied()
+            }
+            _ => None,
+        };
+        if let Some(number) = number {
+            let start_dir = patterns[0].start_dir.unwrap_or_default();
+            for iota in compile_number(number, start_dir, pattern_registry, &location)
+                .map_err(|mishap| (mishap, location, name.clone()))?
+            {
+                push_iota(iota, state, false);
+            }
+            return Ok(state);
+        }
         push_pattern(name, value, state, pattern_registry, false, location);
         return Ok(state);
     }
@@ -82,10 +82,16 @@ impl ContinuationFrameTrait for FrameEvaluate {
         match node {
             //if there are still nodes left in the frame:
             Some(n) => {
-                //push a new frame to the continuation containing the rest of this frame
-                state
-                    .continuation
-                    .push_back(ContinuationFrame::Evaluate(new_frame));
+                check_limits(&n, state)?;
+
+                //push a new frame to the continuation containing the rest of this frame.
+                //like the mod, an empty rest is skipped so that tail calls don't recurse,
+                //but the bottom frame is kept so the hex can be carried on after a mishap
+                if !new_frame.nodes_queue.is_empty() || state.continuation.is_empty() {
+                    state
+                        .continuation
+                        .push_back(ContinuationFrame::Evaluate(new_frame));
+                }
 
                 interpret_node(n.clone(), state, pattern_registry, macros)?;
                 Ok(())
@@ -101,6 +107,36 @@ impl ContinuationFrameTrait for FrameEvaluate {
     }
 }
 
+//every node evaluated counts as an op, and every frame on the continuation as a level of recursion
+fn check_limits(node: &AstNode, state: &mut State) -> Result<(), (Mishap, Location, String)> {
+    state.op_count += 1;
+
+    let mishap = if state.op_count > state.limits.max_ops {
+        Mishap::TooManyPatterns {
+            limit: state.limits.max_ops,
+        }
+    } else if state.continuation.len() >= state.limits.max_depth {
+        Mishap::RecursedTooDeep {
+            limit: state.limits.max_depth,
+        }
+    } else {
+        return Ok(());
+    };
+
+    let caused_by = match node {
+        AstNode::Action { name, .. } | AstNode::MacroCall { name, .. } => name.clone(),
+        AstNode::Op { name, .. } => name.to_string(),
+        AstNode::IfBlock { .. } => "if".to_string(),
+        AstNode::WhileBlock { .. } => "while".to_string(),
+        AstNode::Program(_) | AstNode::Block { .. } => "block".to_string(),
+    };
+    Err((
+        mishap,
+        node.location().unwrap_or(Location::Unknown),
+        caused_by,
+    ))
+}
+
 #[derive(Clone, Debug)]
 pub struct FrameEndEval {}
 
@@ -1,15 +1,19 @@
 use std::rc::Rc;
 
 use owo_colors::OwoColorize;
+use pest::error::{Error, LineColLocation};
+use serde_json::{json, Value};
 
-use crate::{iota::Iota, parser::Location};
+use crate::{
+    iota::Iota,
+    parser::{Location, Rule, SourceFile},
+};
 
-use super::mishap::Mishap;
+use super::{mishap::Mishap, state::Stack};
 
 pub fn print_interpreter_error(
     (err, location, caused_by): (Mishap, Location, String),
-    source: &str,
-    source_path: &str,
+    sources: &[SourceFile],
 ) {
     match location {
         Location::Unknown => {
@@ -17,19 +21,9 @@ pub fn print_interpreter_error(
             print_caused_by(caused_by.as_str());
             print_mishap_hint(&err, &String::new());
         }
-        Location::Line(line, col) => {
-            let location = format!("{source_path}:{line}:{col}");
-            let line_content = source.lines().collect::<Vec<_>>()[line - 1];
-            let pad_len = line.to_string().len();
-            let padding = vec![" "; pad_len].concat();
-
-            print_err_msg(&err, &padding);
-            print_location(&location, &padding);
-            eprintln!(" {padding} {}", "|".magenta().bold());
-            print_mishap_content(line, line_content, &padding);
-
-            print_mishap_hint(&err, &padding);
-        }
+        //locations without a file are in the file that was run
+        Location::Line(line, col) => print_line_error(&err, &sources[0], line, col),
+        Location::File(file, line, col) => print_line_error(&err, &sources[file], line, col),
         Location::List(_) => {
             print_err_msg(&err, &String::new());
             print_caused_by(caused_by.as_str());
@@ -38,6 +32,62 @@ pub fn print_interpreter_error(
     }
 }
 
+//machine readable version of print_interpreter_error, the stack is only known for runtime mishaps
+pub fn mishap_json(
+    kind: &str,
+    (err, location, caused_by): &(Mishap, Location, String),
+    stack: Option<&Stack>,
+    sources: &[SourceFile],
+) -> Value {
+    let (file, line, column) = match location {
+        Location::Line(line, col) => (Some(&sources[0].path), Some(line), Some(col)),
+        Location::File(file, line, col) => (Some(&sources[*file].path), Some(line), Some(col)),
+        Location::Unknown | Location::List(_) => (None, None, None),
+    };
+
+    json!({
+        "kind": kind,
+        "message": err.error_message(),
+        "hint": err.error_hint(),
+        "caused_by": caused_by,
+        "file": file,
+        "line": line,
+        "column": column,
+        "stack": stack.map(|stack| stack.iter().map(|iota| iota.display()).collect::<Vec<_>>()),
+    })
+}
+
+pub fn parse_error_json(err: &Error<Rule>) -> Value {
+    let (line, column) = match err.line_col {
+        LineColLocation::Pos(pos) | LineColLocation::Span(pos, _) => pos,
+    };
+
+    json!({
+        "kind": "parse",
+        "message": err.variant.message(),
+        "hint": null,
+        "caused_by": null,
+        "file": err.path(),
+        "line": line,
+        "column": column,
+        "stack": null,
+    })
+}
+
+fn print_line_error(err: &Mishap, source_file: &SourceFile, line: usize, col: usize) {
+    let location = format!("{}:{line}:{col}", source_file.path);
+    let line_content = source_file.source.lines().collect::<Vec<_>>()[line - 1];
+    let pad_len = line.to_string().len();
+    let padding = vec![" "; pad_len].concat();
+
+    print_err_msg(err, &padding);
+    print_location(&location, &padding);
+    eprintln!(" {padding} {}", "|".magenta().bold());
+    print_mishap_content(line, line_content, &padding);
+
+    print_mishap_hint(err, &padding);
+}
+
 fn print_caused_by(caused_by: &str) {
     eprintln!(
         "{} {caused_by}",
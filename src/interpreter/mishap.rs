@@ -40,7 +40,10 @@ pub enum Mishap {
         expected: String,
         received: Rc<dyn Iota>,
     },
-    MathematicalError,
+    //what was attempted, like "divide 1.000 by 0.000"
+    MathematicalError {
+        operation: String,
+    },
     HastyRetrospection,
     InvalidPattern,
     ExpectedPattern {
@@ -77,31 +80,62 @@ pub enum Mishap {
         row_count: MatrixSize,
         col_count: MatrixSize,
     },
+    UnsupportedAction {
+        name: String,
+    },
+    MacroWrongArgCount {
+        expected: usize,
+        received: usize,
+    },
+    IncorrectIotaType {
+        pattern: String,
+        index: usize,
+        expected: String,
+        received: String,
+    },
+    MacroExpectedVar {
+        param: String,
+    },
+    NotEnoughMedia {
+        cost: f64,
+        remaining: f64,
+    },
+    TooManyPatterns {
+        limit: usize,
+    },
+    RecursedTooDeep {
+        limit: usize,
+    },
+    //no patterns could be found to compile a Numerical Reflection to
+    NoNumberPattern {
+        number: f64,
+    },
+    //stopped by a hook of the program running the hex
+    Interrupted,
 }
 
 impl Mishap {
     pub fn apply_to_stack(&self, stack: &Stack) -> Stack {
+        let garbage: Rc<dyn Iota> = Rc::new(GarbageIota);
         match self {
+            //the missing arguments are filled in with garbage
             Mishap::NotEnoughIotas {
-                arg_count: _,
+                arg_count,
                 stack_height,
             } => {
                 let mut new_stack = stack.clone();
-                let garbage = Rc::new(GarbageIota);
-                let garbages: Vec<Rc<dyn Iota>> = vec![garbage; *stack_height];
+                let garbages = vec![garbage; arg_count.saturating_sub(*stack_height)];
                 new_stack.append(Vector::from(garbages));
                 new_stack
             }
-            Mishap::IncorrectIota {
-                index,
-                expected: _,
-                received: _,
-            } => {
+            //the offending iota is replaced with garbage
+            Mishap::IncorrectIota { received, .. } => {
                 let mut new_stack = stack.clone();
-                new_stack[*index] = Rc::new(GarbageIota);
+                if let Some(index) = stack.iter().rposition(|iota| Rc::ptr_eq(iota, received)) {
+                    new_stack[index] = garbage;
+                }
                 new_stack
             }
-            Mishap::MathematicalError => todo!(),
             Mishap::HastyRetrospection => {
                 let retro_sig: &str = "eee";
                 let mut new_stack = stack.clone();
@@ -112,42 +146,63 @@ impl Mishap {
                 )));
                 new_stack
             }
-            Mishap::InvalidPattern => {
+            Mishap::MathematicalError { .. } | Mishap::InvalidPattern | Mishap::MatrixWrongSize { .. } => {
                 let mut new_stack = stack.clone();
-                new_stack.push_back(Rc::new(GarbageIota));
+                new_stack.push_back(garbage);
                 new_stack
             }
-            Mishap::ExpectedPattern { iota } => todo!(),
+            //these leave the stack as it was before the pattern was cast
+            Mishap::ExpectedPattern { .. }
+            | Mishap::NoAkashicRecord { .. }
+            | Mishap::HoldingIncorrectItem => stack.clone(),
 
-            Mishap::OpCannotBeConsidered => todo!(),
-            Mishap::OpNotEnoughArgs { arg_count } => todo!(),
-            Mishap::OpExpectedVar { received: expected } => todo!(),
-            Mishap::VariableNotAssigned { variable_name } => todo!(),
-            Mishap::OpExpectedIota => todo!(),
-            Mishap::NoIotaAtIndex { index } => todo!(),
-            Mishap::NoAkashicRecord { location } => todo!(),
-            Mishap::HoldingIncorrectItem => todo!(),
-            Mishap::ExpectedValue {
-                caused_by,
-                expected,
-            } => stack.clone(),
-            Mishap::InvalidValue { expected, received: recieved } => todo!(),
-            Mishap::MatrixWrongSize {
-                iota,
-                row_count,
-                col_count,
-            } => todo!(),
+            //these only exist in hexagon, so there is no in-game side effect
+            Mishap::OpCannotBeConsidered
+            | Mishap::OpNotEnoughArgs { .. }
+            | Mishap::OpExpectedVar { .. }
+            | Mishap::OpExpectedIota
+            | Mishap::VariableNotAssigned { .. }
+            | Mishap::NoIotaAtIndex { .. }
+            | Mishap::ExpectedValue { .. }
+            | Mishap::InvalidValue { .. }
+            | Mishap::UnsupportedAction { .. }
+            | Mishap::MacroWrongArgCount { .. }
+            | Mishap::MacroExpectedVar { .. }
+            | Mishap::IncorrectIotaType { .. }
+            | Mishap::NoNumberPattern { .. } => stack.clone(),
+
+            //the spell isn't cast, and neither is the rest of the hex
+            Mishap::NotEnoughMedia { .. } => stack.clone(),
+            Mishap::TooManyPatterns { .. } | Mishap::RecursedTooDeep { .. } => stack.clone(),
+            Mishap::Interrupted => stack.clone(),
         }
     }
 
+    //whether the mod carries on casting after the mishap. Mishaps that only exist in hexagon
+    //have no in-game behaviour to copy, and running out of media ends the whole cast
+    pub fn keeps_casting(&self) -> bool {
+        matches!(
+            self,
+            Mishap::NotEnoughIotas { .. }
+                | Mishap::IncorrectIota { .. }
+                | Mishap::MathematicalError { .. }
+                | Mishap::HastyRetrospection
+                | Mishap::InvalidPattern
+                | Mishap::ExpectedPattern { .. }
+                | Mishap::NoAkashicRecord { .. }
+                | Mishap::HoldingIncorrectItem
+                | Mishap::MatrixWrongSize { .. }
+        )
+    }
+
     pub fn error_message(&self) -> String {
         match self {
             Mishap::NotEnoughIotas {
                 arg_count,
                 stack_height,
-            } => {
-                format!("Expected {arg_count} arguments but the stack was only {stack_height} tall")
-            }
+            } => format!(
+                "Expected {arg_count} or more arguments but the stack was only {stack_height} tall"
+            ),
             Mishap::IncorrectIota {
                 index,
                 expected,
@@ -157,11 +212,13 @@ impl Mishap {
                 expected,
                 recieved.display()
             ),
-            Mishap::MathematicalError => todo!(),
-            Mishap::HastyRetrospection => "Expected preceding Introspection".to_string(),
-            Mishap::InvalidPattern => "This pattern isn't associated with any action".to_string(),
+            Mishap::MathematicalError { operation } => format!("Attempted to {operation}"),
+            Mishap::HastyRetrospection => {
+                "Used Retrospection without first using Introspection".to_string()
+            }
+            Mishap::InvalidPattern => "That pattern isn't associated with any action".to_string(),
             Mishap::ExpectedPattern { iota } => {
-                format!("Expected Pattern but got {}", iota.display())
+                format!("Expected to evaluate a pattern, but got {}", iota.display())
             }
             Mishap::OpCannotBeConsidered => "Ops cannot be considered".to_string(),
             Mishap::OpNotEnoughArgs { arg_count } => format!("Expected {arg_count} arguments"),
@@ -170,19 +227,23 @@ impl Mishap {
                 expected.display()
             ),
             Mishap::OpExpectedIota => "Expected argument to be an iota".to_string(),
-            Mishap::VariableNotAssigned { variable_name } => "Variable never assigned".to_string(),
-            Mishap::NoIotaAtIndex { index } => "No iota found at pointed location".to_string(),
+            Mishap::VariableNotAssigned { variable_name } => {
+                format!("Variable {variable_name} was never assigned")
+            }
+            Mishap::NoIotaAtIndex { index } => {
+                format!("No iota found at index {index} of the Ravenmind")
+            }
             Mishap::NoAkashicRecord { location } => {
-                format!("No akashic record found at {location}")
+                format!("No akashic record found at {}", location.display())
             }
             Mishap::HoldingIncorrectItem => "Entity is not holding the right item".to_string(),
-            Mishap::ExpectedValue {
-                caused_by,
-                expected,
-            } => {
+            Mishap::ExpectedValue { expected, .. } => {
                 format!("Expected {expected} value to be supplied but got Nothing")
             }
-            Mishap::InvalidValue { expected, received: recieved } => {
+            Mishap::InvalidValue {
+                expected,
+                received: recieved,
+            } => {
                 format!("Expected {expected} value to be supplied but got {recieved}")
             }
             Mishap::MatrixWrongSize {
@@ -193,61 +254,365 @@ impl Mishap {
                 "Expected {row_count} by {col_count} matrix but found {}",
                 iota.display()
             ),
+            Mishap::UnsupportedAction { name } => {
+                format!("{name} is not supported by the interpreter")
+            }
+            Mishap::MacroWrongArgCount { expected, received } => {
+                format!("Expected {expected} macro arguments but got {received}")
+            }
+            Mishap::MacroExpectedVar { param } => {
+                format!("Expected the argument for {param} to be a variable")
+            }
+            Mishap::IncorrectIotaType {
+                pattern,
+                index,
+                expected,
+                received,
+            } => format!("{pattern} expects {expected} at index {index} but stack has {received}"),
+            Mishap::NotEnoughMedia { cost, remaining } => format!(
+                "Not enough media: the spell costs {} dust but only {} dust is left",
+                cost.display(),
+                remaining.display()
+            ),
+            Mishap::TooManyPatterns { limit } => {
+                format!("Evaluated too many patterns (more than {limit})")
+            }
+            Mishap::RecursedTooDeep { limit } => {
+                format!("Recursively evaluated too deeply (more than {limit} levels)")
+            }
+            Mishap::NoNumberPattern { number } => {
+                format!("No patterns could be found that draw the number {number}")
+            }
+            Mishap::Interrupted => "The hex was stopped before it finished".to_string(),
         }
     }
 
     pub fn error_hint(&self) -> Option<String> {
-        match self {
+        let hint = match self {
             Mishap::NotEnoughIotas {
                 arg_count,
                 stack_height,
-            } => None,
+            } => format!(
+                "Push {} more iota(s) before this pattern",
+                arg_count.saturating_sub(*stack_height)
+            ),
             Mishap::IncorrectIota {
-                index,
-                expected,
-                received: recieved,
-            } => None,
-            Mishap::MathematicalError => None,
-            Mishap::HastyRetrospection => None,
-            Mishap::InvalidPattern => None,
-            Mishap::ExpectedPattern { iota } => None,
-            Mishap::OpCannotBeConsidered => None,
-            Mishap::OpNotEnoughArgs { arg_count } => {
-                Some("Provide arguments inside the parentheses: Op(arg)".to_string())
-            }
-            Mishap::OpExpectedVar { received: expected } => {
-                Some("Use a variable as the argument: Op($var)".to_string())
+                index, expected, ..
+            } => format!(
+                "Index {index} counts up from the deepest argument, so it needs to be {expected}"
+            ),
+            Mishap::MathematicalError { .. } => {
+                "Check for division or modulo by zero, and logarithms of zero or in base 0 or 1"
+                    .to_string()
+            }
+            Mishap::HastyRetrospection => {
+                "Use Introspection first, or push Retrospection with Consideration".to_string()
+            }
+            Mishap::InvalidPattern => {
+                "Check the signature, or define great spells in a 'config.toml' file".to_string()
+            }
+            Mishap::ExpectedPattern { .. } => {
+                "Use <\\iota> or <<iota>> to push an iota instead of evaluating it".to_string()
             }
+            Mishap::OpCannotBeConsidered => {
+                "Ops aren't patterns, so only actions can follow Consideration".to_string()
+            }
+            Mishap::OpNotEnoughArgs { .. } => {
+                "Provide arguments inside the parentheses: Op(arg)".to_string()
+            }
+            Mishap::OpExpectedVar { .. } => "Use a variable as the argument: Op($var)".to_string(),
             Mishap::OpExpectedIota => {
-                Some("Use an Iota as the argument: Op(1), Op([1, 1, 1]), ect.".to_string())
+                "Use an Iota as the argument: Op(1), Op([1, 1, 1]), ect.".to_string()
             }
-            Mishap::VariableNotAssigned { variable_name } => Some(format!(
-                "Assign the variable using Store({variable_name}) or Copy({variable_name})"
-            )),
-            Mishap::NoIotaAtIndex { index } => Some(
+            Mishap::VariableNotAssigned { variable_name } => {
+                format!("Assign the variable using Store({variable_name}) or Copy({variable_name})")
+            }
+            Mishap::NoIotaAtIndex { .. } => {
                 "This is typically caused by the Ravenmind being overwritten via Huginn's Gambit"
-                    .to_string(),
-            ),
-            Mishap::NoAkashicRecord { location } => {
-                Some("Define an akashic record in a 'config.toml' file".to_string())
+                    .to_string()
             }
-            Mishap::HoldingIncorrectItem => {
-                Some("Define held items in a 'config.toml' file".to_string())
+            Mishap::NoAkashicRecord { .. } => {
+                "Define an akashic record in a 'config.toml' file".to_string()
             }
+            Mishap::HoldingIncorrectItem => "Define held items in a 'config.toml' file".to_string(),
             //TODO: make expectedValue show iota instead of type of iota in example
             Mishap::ExpectedValue {
                 caused_by,
                 expected,
-            } => Some(format!(
-                "Set a value for this action. Example: {}: {}",
-                caused_by, expected
-            )),
-            Mishap::InvalidValue { expected, received: recieved } => None,
-            Mishap::MatrixWrongSize {
-                iota,
-                row_count,
-                col_count,
-            } => None,
+            } => format!("Set a value for this action. Example: {caused_by}: {expected}"),
+            Mishap::InvalidValue { expected, .. } => {
+                format!("Replace the value after the colon with a {expected} value")
+            }
+            Mishap::MatrixWrongSize { .. } => {
+                "Use Restoration Purification to check the shape of the matrix".to_string()
+            }
+            Mishap::UnsupportedAction { .. } => {
+                "This pattern can still be compiled with 'hexagon build'".to_string()
+            }
+            Mishap::MacroWrongArgCount { .. } => {
+                "Provide arguments inside the parentheses: Macro Name(arg, ...)".to_string()
+            }
+            Mishap::MacroExpectedVar { param } => format!(
+                "{param} is stored to inside the macro, so pass a variable: Macro Name($var)"
+            ),
+            Mishap::IncorrectIotaType { pattern, .. } => {
+                format!("Check what is pushed to the stack before {pattern}")
+            }
+            Mishap::NotEnoughMedia { .. } => {
+                "Give the caster more media in the [media] table of a 'config.toml' file"
+                    .to_string()
+            }
+            Mishap::TooManyPatterns { .. } => {
+                "Check for a loop that never ends, or raise max_ops in the [limits] table of a 'config.toml' file".to_string()
+            }
+            Mishap::RecursedTooDeep { .. } => {
+                "Check for a Hermes' Gambit that evaluates itself forever, or raise max_depth in the [limits] table of a 'config.toml' file".to_string()
+            }
+            Mishap::NoNumberPattern { .. } => {
+                "Only finite numbers can be drawn, and a number inside a list or after Consideration has to fit in a single pattern".to_string()
+            }
+            Mishap::Interrupted => "The program running the hex decided to stop it".to_string(),
+        };
+        Some(hint)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::{
+        interpreter::{
+            interpret, interpret_op,
+            state::{Limits, State},
+        },
+        parse_config::Config,
+        parser::{parse, OpName, OpValue},
+        pattern_registry::{PatternRegistry, PatternRegistryExt},
+    };
+
+    fn run(source: &str) -> (Mishap, Vec<String>) {
+        let config = Config::default();
+        let registry = PatternRegistry::from_config(&config);
+        let (ast, macros, _) = parse(source, "", &registry, &mut HashMap::new()).unwrap();
+        match interpret(ast, &config, macros) {
+            Ok(_) => panic!("expected a mishap from {source:?}"),
+            Err((mishap, _, _, state)) => {
+                assert!(mishap.error_hint().is_some());
+                let stack = state.stack.iter().map(|iota| iota.display()).collect();
+                (mishap, stack)
+            }
         }
     }
+
+    #[test]
+    fn mishap_test() {
+        let cases = [
+            (
+                "Additive Distillation",
+                "Expected 2 or more arguments but the stack was only 0 tall",
+                vec!["Garbage", "Garbage"],
+            ),
+            (
+                "Mind's Reflection\nNumerical Reflection: 1\nAdditive Distillation",
+                "expected Number or Vector at index 0 of the stack, but got @Caster",
+                vec!["Garbage", "1.000"],
+            ),
+            (
+                "Numerical Reflection: 1\nNumerical Reflection: 0\nDivision Distillation",
+                "Attempted to divide 1.000 by 0.000",
+                vec!["1.000", "0.000", "Garbage"],
+            ),
+            (
+                "Numerical Reflection: 0\nNumerical Reflection: 2\nLogarithmic Distillation",
+                "Attempted to get the logarithm of 0.000 in base 2.000",
+                vec!["0.000", "2.000", "Garbage"],
+            ),
+            (
+                "Retrospection",
+                "Used Retrospection without first using Introspection",
+                vec!["Retrospection"],
+            ),
+            (
+                "<WEST wdwdwdwdwqwq>",
+                "That pattern isn't associated with any action",
+                vec!["Garbage"],
+            ),
+            (
+                "Numerical Reflection: 1\n<1>",
+                "Expected to evaluate a pattern, but got 1.000",
+                vec!["1.000"],
+            ),
+            (
+                "Numerical Reflection",
+                "Expected Number value to be supplied but got Nothing",
+                vec![],
+            ),
+            (
+                "Numerical Reflection: \"hi\"",
+                "Expected Number value to be supplied but got \"hi\"",
+                vec![],
+            ),
+            (
+                "Consideration\nStore($x)",
+                "Ops cannot be considered",
+                vec![],
+            ),
+            ("Store()", "Expected 1 arguments", vec![]),
+            (
+                "Store(1)",
+                "Expected argument to be a variable but got iota 1.000",
+                vec![],
+            ),
+            ("Push($x)", "Variable $x was never assigned", vec![]),
+            (
+                "Numerical Reflection: 1\nStore($x)\nNumerical Reflection: 2\nHuginn's Gambit\nPush($x)",
+                "No iota found at index 0 of the Ravenmind",
+                vec![],
+            ),
+            (
+                "Vector Reflection Zero\n<\\Mind's Reflection>\nAkasha's Distillation",
+                "No akashic record found at (0.000, 0.000, 0.000)",
+                vec!["(0.000, 0.000, 0.000)", "Mind's Reflection"],
+            ),
+            (
+                "Numerical Reflection: 1\nScribe's Gambit",
+                "Entity is not holding the right item",
+                vec!["1.000"],
+            ),
+            (
+                "Vector Reflection Zero\nVector Reflection Zero\nMultiplication Distillation: Matrix",
+                "Expected 1 by n matrix but found (0.000, 0.000, 0.000)",
+                vec!["Garbage"],
+            ),
+            (
+                "Sorter's Reflection",
+                "Sorter's Reflection is not supported by the interpreter",
+                vec![],
+            ),
+            (
+                "#define Save($v) (SOUTH_EAST ded) {\n    Store($v)\n}\nSave()",
+                "Expected 1 macro arguments but got 0",
+                vec![],
+            ),
+            (
+                "#define Save($v) (SOUTH_EAST ded) {\n    Store($v)\n}\nSave(1)",
+                "Expected the argument for $v to be a variable",
+                vec![],
+            ),
+        ];
+
+        for (source, message, stack) in cases {
+            let (mishap, result_stack) = run(source);
+            assert_eq!(mishap.error_message(), message, "{source:?}");
+            assert_eq!(result_stack, stack, "{source:?}");
+        }
+    }
+
+    #[test]
+    fn in_game_mishaps_test() {
+        let config = Config {
+            in_game_mishaps: true,
+            ..Default::default()
+        };
+        let registry = PatternRegistry::from_config(&config);
+        let run = |source: &str| {
+            let (ast, macros, _) = parse(source, "", &registry, &mut HashMap::new()).unwrap();
+            interpret(ast, &config, macros)
+        };
+
+        //the rest of the evaluated list is skipped, but the rest of the hex isn't
+        let source = "{\n    Additive Distillation\n    Numerical Reflection: 1\n}\nHermes' Gambit\nNumerical Reflection: 2";
+        let Ok(state) = run(source) else {
+            panic!("expected the hex to keep casting")
+        };
+        assert_eq!(state.stack.display(), "[Garbage, Garbage, 2.000]");
+        assert_eq!(state.mishaps.len(), 1);
+        assert!(matches!(state.mishaps[0].0, Mishap::NotEnoughIotas { .. }));
+
+        //mishaps that only exist in hexagon still stop the hex
+        assert!(run("Push($x)\nNumerical Reflection: 1").is_err());
+    }
+
+    #[test]
+    fn not_enough_media_test() {
+        let config = Config {
+            media_budget: Some(2.0),
+            ..Default::default()
+        };
+        let registry = PatternRegistry::from_config(&config);
+        let source = "Vector Reflection Zero\nBreak Block\nVector Reflection Zero\nNumerical Reflection: 1\nFireball";
+        let (ast, macros, _) = parse(source, "", &registry, &mut HashMap::new()).unwrap();
+
+        let Err((mishap, _, caused_by, state)) = interpret(ast, &config, macros) else {
+            panic!("expected the fireball to be too expensive")
+        };
+        assert_eq!(
+            mishap.error_message(),
+            "Not enough media: the spell costs 4.000 dust but only 1.875 dust is left"
+        );
+        assert_eq!(caused_by, "Fireball");
+        assert_eq!(state.media_spent, 0.125);
+        //the spell is not cast, so its arguments are left on the stack
+        assert_eq!(state.stack.display(), "[(0.000, 0.000, 0.000), 1.000]");
+
+        //a wisp that can't be paid for is never summoned
+        let source = "Vacant Reflection\nVector Reflection Zero\nNumerical Reflection: 10\nSummon Cyclic Wisp";
+        let (ast, macros, _) = parse(source, "", &registry, &mut HashMap::new()).unwrap();
+        let Err((mishap, _, _, state)) = interpret(ast, &config, macros) else {
+            panic!("expected the wisp to be too expensive")
+        };
+        assert!(matches!(mishap, Mishap::NotEnoughMedia { .. }));
+        assert!(state.wisps.is_empty());
+    }
+
+    #[test]
+    fn limits_test() {
+        let config = Config {
+            limits: Limits {
+                max_ops: 1000,
+                max_depth: 64,
+            },
+            ..Default::default()
+        };
+        let registry = PatternRegistry::from_config(&config);
+        let run = |source: &str| {
+            let (ast, macros, _) = parse(source, "", &registry, &mut HashMap::new()).unwrap();
+            interpret(ast, &config, macros)
+        };
+
+        let Err((mishap, _, _, _)) =
+            run("while {True Reflection} do {\n    Numerical Reflection: 1\n}")
+        else {
+            panic!("expected the loop to be stopped")
+        };
+        assert!(matches!(mishap, Mishap::TooManyPatterns { limit: 1000 }));
+
+        //a list that evaluates a copy of itself as its last pattern is a tail call
+        let source = "{\n    Gemini Decomposition\n    Hermes' Gambit\n}\nGemini Decomposition\nHermes' Gambit";
+        let Err((mishap, _, _, _)) = run(source) else {
+            panic!("expected the recursion to be stopped")
+        };
+        assert!(matches!(mishap, Mishap::TooManyPatterns { limit: 1000 }));
+
+        //but has to remember the rest of the list otherwise
+        let source = "{\n    Gemini Decomposition\n    Hermes' Gambit\n    Bookkeeper's Gambit: v\n}\nGemini Decomposition\nHermes' Gambit";
+        let Err((mishap, _, _, _)) = run(source) else {
+            panic!("expected the recursion to be stopped")
+        };
+        assert!(matches!(mishap, Mishap::RecursedTooDeep { limit: 64 }));
+    }
+
+    #[test]
+    fn op_expected_iota_test() {
+        let registry = PatternRegistry::construct(&PatternRegistry::gen_default_great_sigs());
+        let mut state = State::default();
+        let arg = Some(OpValue::Var("$x".to_string()));
+        let mishap =
+            interpret_op(OpName::Embed, arg, &mut state, &registry, &HashMap::new()).unwrap_err();
+
+        assert!(matches!(mishap, Mishap::OpExpectedIota));
+        assert!(mishap.apply_to_stack(&state.stack).is_empty());
+    }
 }
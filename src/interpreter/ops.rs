@@ -162,7 +162,7 @@ pub fn embed<'a>(
                 Ok(pat) => {
                     interpret_action(
                         pattern_registry
-                            .find(&pat.signature.as_str(), &None)
+                            .find(&pat.signature.as_str(), &pat.value)
                             .ok_or(Mishap::InvalidPattern)?
                             .internal_name,
                         *pat.value.clone(),
@@ -1,10 +1,17 @@
-use std::{collections::HashMap, ops::Deref, rc::Rc};
+use std::{
+    collections::HashMap,
+    ops::{Deref, DerefMut},
+    rc::Rc,
+};
 
 use im::Vector;
+use rand::{rngs::StdRng, SeedableRng};
 
 use crate::{
     iota::{
-        hex_casting::{entity::EntityIota, pattern::Signature, vector::VectorIota},
+        hex_casting::{
+            entity::EntityIota, list::ListIota, pattern::Signature, vector::VectorIota,
+        },
         Iota,
     },
     parser::{AstNode, Location, Macros},
@@ -32,6 +39,139 @@ pub struct State {
     pub consider_next: bool,
     pub continuation: Vector<ContinuationFrame>,
     pub wisps: HashMap<String, Wisp>,
+    //mishaps that casting carried on after, with in-game mishaps turned on
+    pub mishaps: Vector<(Mishap, Location, String)>,
+    //in dust, None means the caster has unlimited media
+    pub media_budget: Option<f64>,
+    pub media_spent: f64,
+    pub limits: Limits,
+    pub op_count: usize,
+    pub rng: Rng,
+    //everything the hex did that the caster could see, in the order it happened
+    pub log: Vector<LogEntry>,
+}
+
+#[derive(Clone, Debug)]
+pub enum LogEntry {
+    //an iota shown with Reveal
+    Reveal(Rc<dyn Iota>),
+    //the note played by Make Note
+    Note(String),
+    Particles(Vector<VectorIota>),
+    //a spell that was cast and the media it cost, in dust
+    Spell { name: String, cost: f64 },
+}
+
+impl LogEntry {
+    //the line the caster would read in chat, only Reveal and Make Note print anything
+    pub fn output(&self) -> Option<String> {
+        match self {
+            LogEntry::Reveal(iota) => Some(iota.display()),
+            LogEntry::Note(note) => Some(note.clone()),
+            LogEntry::Particles(_) | LogEntry::Spell { .. } => None,
+        }
+    }
+
+    pub fn display(&self) -> String {
+        match self {
+            LogEntry::Reveal(_) | LogEntry::Note(_) => self.output().unwrap(),
+            LogEntry::Particles(positions) => format!(
+                "particles at {}",
+                positions
+                    .iter()
+                    .map(|position| position.display())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            LogEntry::Spell { name, cost } => format!("cast {name} for {} dust", cost.display()),
+        }
+    }
+}
+
+//every randomised pattern draws from this, so a run can be replayed with the same seed
+#[derive(Clone, Debug)]
+pub struct Rng(StdRng);
+
+impl Rng {
+    pub fn seeded(seed: u64) -> Rng {
+        Rng(StdRng::seed_from_u64(seed))
+    }
+}
+
+impl Default for Rng {
+    fn default() -> Self {
+        Rng(StdRng::from_entropy())
+    }
+}
+
+impl Deref for Rng {
+    type Target = StdRng;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for Rng {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+//the same limits the mod puts on a single cast
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Limits {
+    pub max_ops: usize,
+    pub max_depth: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_ops: 100_000,
+            max_depth: 512,
+        }
+    }
+}
+
+impl State {
+    //the value of every variable by name, sorted
+    pub fn variables(&self) -> Vec<(String, String)> {
+        let ravenmind = self
+            .ravenmind
+            .as_ref()
+            .and_then(|ravenmind| ravenmind.clone().downcast_rc::<ListIota>().ok());
+
+        let mut vars = self
+            .heap
+            .iter()
+            .map(|(name, index)| {
+                let value = ravenmind
+                    .as_ref()
+                    .and_then(|ravenmind| ravenmind.get(*index as usize))
+                    .map_or("Unassigned".to_string(), |iota| iota.display());
+                (name.clone(), value)
+            })
+            .collect::<Vec<_>>();
+        vars.sort();
+        vars
+    }
+
+    pub fn check_media(&self, cost: f64) -> Result<(), Mishap> {
+        if let Some(budget) = self.media_budget {
+            let remaining = budget - self.media_spent;
+            if cost > remaining {
+                return Err(Mishap::NotEnoughMedia { cost, remaining });
+            }
+        }
+        Ok(())
+    }
+
+    pub fn spend_media(&mut self, cost: f64) -> Result<(), Mishap> {
+        self.check_media(cost)?;
+        self.media_spent += cost;
+        Ok(())
+    }
 }
 
 pub type Library = HashMap<Signature, Rc<dyn Iota>>;
@@ -264,6 +404,7 @@ impl Wisp {
             buffer: Default::default(),
             consider_next: Default::default(),
             continuation: Default::default(),
+            op_count: 0,
             ..main_state.clone()
         };
 
@@ -288,6 +429,12 @@ impl Wisp {
         main_state.libraries = wisp_state.libraries.clone();
         main_state.sentinal_location = wisp_state.sentinal_location;
         main_state.wisps = wisp_state.wisps;
+        main_state.log = wisp_state.log;
+        main_state.rng = wisp_state.rng;
+        //wisps pay for their spells out of the same budget as the hex that summoned them
+        main_state.media_spent = wisp_state.media_spent;
+        //the patterns a wisp casts count towards the limit of the hex that summoned it
+        main_state.op_count += wisp_state.op_count;
 
         //set self_ref to None so that it isn't added to stack in future iterations
         let result = Wisp {
@@ -301,3 +448,94 @@ impl Wisp {
         Ok(result)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{
+        interpreter::{interpret, interpret_with_hook, state::LogEntry},
+        iota::Iota,
+        parse_config::Config,
+        parser::parse,
+        pattern_registry::{PatternRegistry, PatternRegistryExt},
+    };
+
+    #[test]
+    fn seed_test() {
+        let config = Config {
+            seed: Some(42),
+            ..Default::default()
+        };
+        let registry = PatternRegistry::from_config(&config);
+        let run = || {
+            let (ast, macros, _) = parse(
+                "Entropy Reflection\nEntropy Reflection",
+                "",
+                &registry,
+                &mut HashMap::new(),
+            )
+            .unwrap();
+            interpret(ast, &config, macros).unwrap().stack.display()
+        };
+
+        assert_eq!(run(), run());
+    }
+
+    #[test]
+    fn wisp_rng_test() {
+        let config = Config {
+            seed: Some(42),
+            ..Default::default()
+        };
+        let registry = PatternRegistry::from_config(&config);
+        let source = "{\n    Entropy Reflection\n    Reveal\n}\nVector Reflection Zero\nNumerical Reflection: 10\nSummon Cyclic Wisp";
+        let (ast, macros, _) = parse(source, "", &registry, &mut HashMap::new()).unwrap();
+
+        //stop the wisp once it has drawn twice
+        let reveals = |log: &im::Vector<LogEntry>| {
+            log.iter()
+                .filter_map(|entry| match entry {
+                    LogEntry::Reveal(iota) => Some(iota.display()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        let Err((_, _, _, state)) =
+            interpret_with_hook(ast, &config, macros, &mut |state| reveals(&state.log).len() < 2)
+        else {
+            panic!("expected the wisp to be interrupted")
+        };
+
+        let drawn = reveals(&state.log);
+        assert_eq!(drawn.len(), 2);
+        assert_ne!(drawn[0], drawn[1]);
+    }
+
+    #[test]
+    fn wisp_media_test() {
+        let config = Config::default();
+        let registry = PatternRegistry::from_config(&config);
+        let source = "{\n    Vector Reflection Zero\n    Break Block\n}\nVector Reflection Zero\nNumerical Reflection: 10\nSummon Cyclic Wisp";
+        let (ast, macros, _) = parse(source, "", &registry, &mut HashMap::new()).unwrap();
+
+        let spells = |log: &im::Vector<LogEntry>| {
+            log.iter()
+                .filter_map(|entry| match entry {
+                    LogEntry::Spell { cost, .. } => Some(*cost),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        //the wisp itself and two blocks broken by it
+        let Err((_, _, _, state)) =
+            interpret_with_hook(ast, &config, macros, &mut |state| spells(&state.log).len() < 3)
+        else {
+            panic!("expected the wisp to be interrupted")
+        };
+
+        let costs = spells(&state.log);
+        assert_eq!(costs.len(), 3);
+        assert_eq!(state.media_spent, costs.iter().sum::<f64>());
+    }
+}
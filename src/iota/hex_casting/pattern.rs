@@ -1,3 +1,7 @@
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::str::FromStr;
+
 use serde_json::Map;
 
 use crate::interpreter::mishap::Mishap;
@@ -10,6 +14,7 @@ use crate::iota::Iota;
 #[derive(Debug, Clone)]
 pub struct PatternIota {
     pub signature: Signature,
+    pub start_dir: Direction,
     pub value: Box<Option<ActionValue>>,
     pub location: Location,
 }
@@ -21,17 +26,32 @@ impl PatternIota {
         value: Option<ActionValue>,
         location: Location,
     ) -> Result<PatternIota, Mishap> {
+        let pattern = registry.find(name, &value).ok_or(Mishap::InvalidPattern)?;
         Ok(PatternIota {
-            signature: Signature::from_name(registry, name, &value)
-                .ok_or(Mishap::InvalidPattern)?,
+            signature: Signature::from_sig(&pattern.signature),
+            start_dir: pattern.start_dir.unwrap_or_default(),
             value: Box::new(value),
             location,
         })
     }
 
+    //a pattern drawn by hand, Numerical Reflection and Bookkeeper's Gambit get their value from it
+    pub fn from_angles(
+        registry: &PatternRegistry,
+        signature: &str,
+        start_dir: Direction,
+        location: Location,
+    ) -> PatternIota {
+        PatternIota {
+            start_dir,
+            ..PatternIota::from_sig(signature, registry.decode_value(signature), location)
+        }
+    }
+
     pub fn from_sig(name: &str, value: Option<ActionValue>, location: Location) -> PatternIota {
         PatternIota {
             signature: Signature::from_sig(name),
+            start_dir: Direction::default(),
             value: Box::new(value),
             location,
         }
@@ -46,7 +66,10 @@ impl Iota for PatternIota {
             &self.signature.as_str(),
             &self.value,
         )
-        .map_or(self.signature.as_str(), |pat| pat.display_name);
+        .map_or(
+            format!("{} {}", self.start_dir, self.signature.as_str()),
+            |pat| pat.display_name,
+        );
 
         if let Some(value) = *self.value.clone() {
             match value {
@@ -83,17 +106,99 @@ impl Iota for PatternIota {
             .collect::<Vec<_>>()
             .join("");
 
-        format!("{{type: \"hexcasting:pattern\", value: {{angles: \"{angles}\", start_dir: \"NORTH_EAST\"}}}}")
+        format!(
+            "{{type: \"hexcasting:pattern\", value: {{angles: \"{angles}\", start_dir: \"{}\"}}}}",
+            self.start_dir
+        )
     }
     
     fn serialize_to_json(&self) -> serde_json::Value {
         let mut map = Map::new();
         map.insert("iota_type".to_string(), serde_json::Value::String("pattern".to_string()));
         map.insert("value".to_string(), serde_json::Value::String(self.signature.as_str()));
+        map.insert("start_dir".to_string(), serde_json::Value::String(self.start_dir.to_string()));
 
         serde_json::Value::Object(map)    }
 }
 
+//the direction of the first stroke of a pattern
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Direction {
+    #[default]
+    NorthEast,
+    East,
+    SouthEast,
+    SouthWest,
+    West,
+    NorthWest,
+}
+
+impl FromStr for Direction {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        match string {
+            "NORTH_EAST" | "NE" => Ok(Direction::NorthEast),
+            "EAST" | "E" => Ok(Direction::East),
+            "SOUTH_EAST" | "SE" => Ok(Direction::SouthEast),
+            "SOUTH_WEST" | "SW" => Ok(Direction::SouthWest),
+            "WEST" | "W" => Ok(Direction::West),
+            "NORTH_WEST" | "NW" => Ok(Direction::NorthWest),
+            _ => Err(format!("invalid direction: {string}")),
+        }
+    }
+}
+
+impl Direction {
+    //the direction after turning by an angle of a signature, the directions go clockwise
+    pub fn turn(self, angle: &PatternSigDir) -> Direction {
+        let turns = match angle {
+            PatternSigDir::W => 0,
+            PatternSigDir::E => 1,
+            PatternSigDir::D => 2,
+            PatternSigDir::S => 3,
+            PatternSigDir::A => 4,
+            PatternSigDir::Q => 5,
+        };
+        DIRECTIONS[(self as usize + turns) % 6]
+    }
+
+    //a step in this direction on the hex grid, in axial coordinates
+    pub fn offset(self) -> (i32, i32) {
+        match self {
+            Direction::NorthEast => (1, -1),
+            Direction::East => (1, 0),
+            Direction::SouthEast => (0, 1),
+            Direction::SouthWest => (-1, 1),
+            Direction::West => (-1, 0),
+            Direction::NorthWest => (0, -1),
+        }
+    }
+}
+
+//clockwise from the first, which is also the order the mod numbers them in
+pub const DIRECTIONS: [Direction; 6] = [
+    Direction::NorthEast,
+    Direction::East,
+    Direction::SouthEast,
+    Direction::SouthWest,
+    Direction::West,
+    Direction::NorthWest,
+];
+
+impl Display for Direction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Direction::NorthEast => write!(f, "NORTH_EAST"),
+            Direction::East => write!(f, "EAST"),
+            Direction::SouthEast => write!(f, "SOUTH_EAST"),
+            Direction::SouthWest => write!(f, "SOUTH_WEST"),
+            Direction::West => write!(f, "WEST"),
+            Direction::NorthWest => write!(f, "NORTH_WEST"),
+        }
+    }
+}
+
 pub type Signature = Vec<PatternSigDir>;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -114,6 +219,8 @@ pub trait SignatureExt {
         value: &Option<ActionValue>,
     ) -> Option<Signature>;
     fn as_str(&self) -> String;
+    fn points(&self, start_dir: Direction) -> Vec<(i32, i32)>;
+    fn is_drawable(&self) -> bool;
 }
 
 impl SignatureExt for Signature {
@@ -154,4 +261,27 @@ impl SignatureExt for Signature {
             })
             .collect()
     }
+
+    //the points of the hex grid the pattern is drawn through, in axial coordinates
+    fn points(&self, start_dir: Direction) -> Vec<(i32, i32)> {
+        let mut dir = start_dir;
+        let mut point = dir.offset();
+        let mut points = vec![(0, 0), point];
+        for angle in self {
+            dir = dir.turn(angle);
+            point = (point.0 + dir.offset().0, point.1 + dir.offset().1);
+            points.push(point);
+        }
+        points
+    }
+
+    //the mod doesn't let a pattern go along the same line twice
+    fn is_drawable(&self) -> bool {
+        let points = self.points(Direction::default());
+        let mut lines = HashSet::new();
+        points.windows(2).all(|line| {
+            let (a, b) = (line[0], line[1]);
+            lines.insert(if a < b { (a, b) } else { (b, a) })
+        })
+    }
 }
@@ -1,152 +1,395 @@
-use compiler::{compile_to_iotas, nbt::gen_give_cmd};
+use compiler::{
+    compile_to_iotas,
+    hexpattern::compile_hexpattern,
+    json::compile_json,
+    nbt::{compile_nbt, gen_give_cmd},
+    svg::compile_svg,
+};
 
-use interpreter::error::print_interpreter_error;
+use interpreter::{
+    error::{mishap_json, parse_error_json, print_interpreter_error},
+    mishap::Mishap,
+    state::{Stack, State},
+};
 use iota::Iota;
 
+use checker::check;
+use debugger::debug;
+use decompiler::decompile;
+use test_runner::test;
+use cli::{Args, Command, ExitStatus, MessageFormat, OutputFormat, HELP};
 use owo_colors::OwoColorize;
-use std::{collections::HashMap, env, fs};
+use std::{env, fs, io, process::ExitCode, thread};
 
-use crate::{hex_server::send_hex, interpreter::interpret};
+use crate::{
+    hex_server::send_hex,
+    interpreter::{interpret_with_hook, WISP_TICK},
+    parser::{Location, SourceFile},
+};
+pub mod api;
+pub mod checker;
+pub mod cli;
 pub mod compiler;
+pub mod dap;
+pub mod debugger;
+pub mod decompiler;
 pub mod interpreter;
 pub mod iota;
+pub mod lsp;
 pub mod parse_config;
 pub mod parser;
 pub mod pattern_registry;
 pub mod patterns;
+pub mod repl;
 pub mod hex_server;
+pub mod test_runner;
 
-use parse_config::{parse_config, Config};
-use pattern_registry::{PatternRegistry, PatternRegistryExt};
+use parse_config::parse_config;
 
-struct Args {
-    command: Command,
-    url: Option<String>,
-    source_path: String,
-    config_path: String,
-}
+//the api for running hexes from other programs
+pub use api::{run_hex, Diagnostic, DiagnosticKind, Hex, Hooks, RunResult};
+pub use interpreter::state::{Limits, LogEntry};
+pub use parse_config::Config;
+use pattern_registry::{numbers, PatternRegistry, PatternRegistryExt};
+
+pub fn run() -> ExitCode {
+    let args = match Args::parse(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{} {err}\n\n{HELP}", "Error:".red().bold());
+            return ExitStatus::Usage.into();
+        }
+    };
 
-impl Args {
-    fn get() -> Args {
-        let args: Vec<String> = env::args().collect();
+    match args.command {
+        Command::Help => {
+            println!("{HELP}");
+            ExitStatus::Success.into()
+        }
+        Command::Version => {
+            println!("hexagon {}", env!("CARGO_PKG_VERSION"));
+            ExitStatus::Success.into()
+        }
+        Command::Dap => serve(&args, dap::serve).into(),
+        Command::Lsp => serve(&args, lsp::serve).into(),
+        Command::Repl => serve(&args, repl::serve).into(),
+        Command::Decompile => decompile_hex(&args).into(),
+        Command::Test => match load_args_config(&args) {
+            Ok(config) => test(&args.source_path, &config, args.quiet).into(),
+            Err((status, err)) => {
+                eprintln!("{} {err}", "Error:".red().bold());
+                status.into()
+            }
+        },
+        _ => execute(&args).into(),
+    }
+}
 
-        let command = Args::get_cmd(args.get(1).expect("Expected command"));
+fn execute(args: &Args) -> ExitStatus {
+    let mut config = match load_args_config(args) {
+        Ok(config) => config,
+        Err((status, err)) => {
+            eprintln!("{} {err}", "Error:".red().bold());
+            return status;
+        }
+    };
 
-        if let Command::Send = command {
-            let url = args.get(2).expect("Expected Url").to_owned();
+    //finding the patterns for numbers is slow, so they're kept between runs
+    if let Some(path) = &config.number_cache {
+        if let Err(err) = numbers::load_cache(path) {
+            eprintln!("{} {}: {err}", "Warning:".yellow().bold(), path.display());
+        }
+    }
 
+    let pattern_registry = PatternRegistry::from_config(&config);
 
-            let source_path = args.get(3).expect("Expected File Path").to_owned();
+    let source = match fs::read_to_string(&args.source_path) {
+        Ok(source) => source,
+        Err(err) => {
+            eprintln!("{} {}: {err}", "Error:".red().bold(), args.source_path);
+            return ExitStatus::Io;
+        }
+    };
 
-            let default_config_path = "config.toml".to_string();
-            let config_path = args.get(4).unwrap_or(&default_config_path).to_owned();
-    
-            Args {
-                command,
-                url: Some(url),
-                source_path,
-                config_path,
+    let parse_result = parser::parse(
+        &source,
+        &args.source_path,
+        &pattern_registry,
+        &mut config.entities,
+    );
+    let (ast, macros, sources) = match parse_result {
+        Ok(result) => result,
+        Err(errors) => {
+            for err in &errors {
+                match args.message_format {
+                    MessageFormat::Human => {
+                        eprintln!("{}\n{}", "Parsing Error:".red().bold(), err)
+                    }
+                    MessageFormat::Json => eprintln!("{}", parse_error_json(err)),
+                }
             }
+            return ExitStatus::Parse;
+        }
+    };
+
+    let status = match &args.command {
+        Command::Run | Command::Debug => {
+            //(log entries, mishaps) already printed
+            let mut printed = (0, 0);
+            let interpreter_result = if args.command == Command::Debug {
+                //the debugger prints what the hex printed while stepping through it
+                debug(ast, &config, macros, &sources)
+            } else {
+                let result = interpret_with_hook(ast, &config, macros, &mut |state| {
+                    print_progress(state, &mut printed, &sources, args);
+                    //the main hex has finished while its wisps are ticking
+                    if state.continuation.is_empty() && !state.wisps.is_empty() {
+                        thread::sleep(WISP_TICK);
+                    }
+                    true
+                });
+                //the step that stopped the hex isn't seen by the hook
+                let (Ok(state) | Err((.., state))) = &result;
+                print_progress(state, &mut printed, &sources, args);
+                result
+            };
+
+            match interpreter_result {
+                Ok(result) => {
+                    for err in result.mishaps.iter().skip(printed.1) {
+                        report_mishap("mishap", err.clone(), None, &sources, args);
+                    }
 
-        } else {
-            let source_path = args.get(2).expect("Expected File Path").to_owned();
-
-            let default_config_path = "config.toml".to_string();
-            let config_path = args.get(3).unwrap_or(&default_config_path).to_owned();
-    
-            Args {
-                command,
-                url: None,
-                source_path,
-                config_path,
+                    let status = if !args.quiet && args.output_path.is_none() {
+                        println!("\nresult: {} \n {:?}", result.stack.display(), result.buffer);
+                        println!("media used: {} dust", result.media_spent.display());
+                        ExitStatus::Success
+                    } else {
+                        write_output(&result.stack.display(), args)
+                    };
+
+                    //a hex that carried on after a mishap still mishapped
+                    match status {
+                        ExitStatus::Success if !result.mishaps.is_empty() => ExitStatus::Mishap,
+                        status => status,
+                    }
+                }
+                Err((mishap, location, caused_by, state)) => {
+                    let err = (mishap, location, caused_by);
+                    report_mishap("mishap", err, Some(&state.stack), &sources, args);
+                    ExitStatus::Mishap
+                }
+            }
+        }
+        Command::Build => {
+            let compile_result = compile_to_iotas(&ast, None, &pattern_registry, &macros);
+            match compile_result {
+                Ok(result) => {
+                    let output = match args.format {
+                        OutputFormat::Give(item) => gen_give_cmd(result, item),
+                        OutputFormat::Snbt => compile_nbt(result),
+                        OutputFormat::Json => compile_json(result),
+                        OutputFormat::HexPattern => compile_hexpattern(result),
+                    };
+                    write_output(&output, args)
+                }
+                Err(err) => {
+                    report_mishap("compile", err, None, &sources, args);
+                    ExitStatus::Mishap
+                }
             }
         }
-        
+        Command::Render => {
+            let compile_result = compile_to_iotas(&ast, None, &pattern_registry, &macros);
+            match compile_result {
+                //an image is only useful on its own, so it's printed without the result header
+                Ok(result) if args.output_path.is_some() => {
+                    write_output(&compile_svg(result, &pattern_registry), args)
+                }
+                Ok(result) => {
+                    println!("{}", compile_svg(result, &pattern_registry));
+                    ExitStatus::Success
+                }
+                Err(err) => {
+                    report_mishap("compile", err, None, &sources, args);
+                    ExitStatus::Mishap
+                }
+            }
+        }
+        Command::Check => {
+            let errors = check(&ast, &pattern_registry, &macros);
+            for err in &errors {
+                report_mishap("check", err.clone(), None, &sources, args);
+            }
 
+            if errors.is_empty() {
+                if !args.quiet {
+                    println!("No problems found");
+                }
+                ExitStatus::Success
+            } else {
+                ExitStatus::Mishap
+            }
+        }
+        Command::Send { url } => {
+            let compile_result = compile_to_iotas(&ast, None, &pattern_registry, &macros);
+            match compile_result {
+                Ok(result) => match send_hex(result, url) {
+                    Ok(msg) => write_output(&msg, args),
+                    Err(error) => {
+                        eprintln!("{} {error}", "Error:".red().bold());
+                        ExitStatus::Network
+                    }
+                },
+                Err(err) => {
+                    report_mishap("compile", err, None, &sources, args);
+                    ExitStatus::Mishap
+                }
+            }
+        }
+        Command::Help
+        | Command::Version
+        | Command::Dap
+        | Command::Lsp
+        | Command::Repl
+        | Command::Decompile
+        | Command::Test => {
+            unreachable!()
+        }
+    };
 
+    if let Some(path) = &config.number_cache {
+        if let Err(err) = numbers::save_cache(path) {
+            eprintln!("{} {}: {err}", "Warning:".yellow().bold(), path.display());
+        }
     }
+    status
+}
+
+//decompile reads a compiled hex instead of source, so it doesn't go through `execute`
+fn decompile_hex(args: &Args) -> ExitStatus {
+    let config = match load_args_config(args) {
+        Ok(config) => config,
+        Err((status, err)) => {
+            eprintln!("{} {err}", "Error:".red().bold());
+            return status;
+        }
+    };
 
-    fn get_cmd(cmd: &str) -> Command {
-        match cmd {
-            "run" => Command::Run,
-            "build" => Command::Build,
-            "send" => Command::Send,
-            _ => panic!("invalid command"),
+    let input = if args.source_path == "-" {
+        io::read_to_string(io::stdin())
+    } else {
+        fs::read_to_string(&args.source_path)
+    };
+    let input = match input {
+        Ok(input) => input,
+        Err(err) => {
+            eprintln!("{} {}: {err}", "Error:".red().bold(), args.source_path);
+            return ExitStatus::Io;
+        }
+    };
+
+    match decompile(&input, &config) {
+        Ok(source) if args.output_path.is_some() => write_output(&source, args),
+        Ok(source) => {
+            print!("{source}");
+            ExitStatus::Success
+        }
+        Err(err) => {
+            eprintln!("{} {}: {err}", "Error:".red().bold(), args.source_path);
+            ExitStatus::Parse
         }
     }
 }
 
-enum Command {
-    Run,
-    Build,
-    Send,
+//prints the output and the mishaps the hex carried on after as they happen
+fn print_progress(
+    state: &State,
+    printed: &mut (usize, usize),
+    sources: &[SourceFile],
+    args: &Args,
+) {
+    for line in state.log.iter().skip(printed.0).filter_map(LogEntry::output) {
+        println!("{line}");
+    }
+    for err in state.mishaps.iter().skip(printed.1) {
+        report_mishap("mishap", err.clone(), None, sources, args);
+    }
+    *printed = (state.log.len(), state.mishaps.len());
 }
 
-pub fn run() {
-    let args = Args::get();
-
-    let mut config = fs::read_to_string(args.config_path)
-        .map(parse_config)
-        .unwrap_or_else(|_| Config {
-            libraries: HashMap::new(),
-            entities: HashMap::new(),
-            great_spell_sigs: PatternRegistry::gen_default_great_sigs(),
-        });
-
-    let source =
-        fs::read_to_string(&args.source_path).expect("Should have been able to read the file");
+fn report_mishap(
+    kind: &str,
+    err: (Mishap, Location, String),
+    stack: Option<&Stack>,
+    sources: &[SourceFile],
+    args: &Args,
+) {
+    match args.message_format {
+        MessageFormat::Human => print_interpreter_error(err, sources),
+        MessageFormat::Json => eprintln!("{}", mishap_json(kind, &err, stack, sources)),
+    }
+}
 
-    let parse_result = parser::parse(&source, &config.great_spell_sigs, &mut config.entities);
-    let (ast, macros) = match parse_result {
-        Ok(result) => result,
-        Err(err) => {
-            eprintln!("{}\n{}\n{}", "Parsing Error:".red().bold(), args.source_path, err);
-            return;
+//runs a protocol server for editors or the repl on stdin/stdout
+fn serve(args: &Args, server: fn(Config) -> std::io::Result<()>) -> ExitStatus {
+    let config = match load_args_config(args) {
+        Ok(config) => config,
+        Err((status, err)) => {
+            eprintln!("{} {err}", "Error:".red().bold());
+            return status;
         }
     };
 
-    if let Command::Run = args.command {
-        let interpreter_result = interpret(ast, &config, macros, &source, &args.source_path);
-
-        match interpreter_result {
-            Ok(result) => println!(
-                "\nresult: {} \n {:?}",
-                result.stack.display(),
-                result.buffer
-            ),
-            Err((mishap, location, caused_by, _)) => {
-                print_interpreter_error((mishap, location, caused_by), &source, &args.source_path);
-            }
-        };
+    match server(config) {
+        Ok(()) => ExitStatus::Success,
+        Err(err) => {
+            eprintln!("{} {err}", "Error:".red().bold());
+            ExitStatus::Io
+        }
+    }
+}
 
-    } else if let Command::Build = args.command {
-        let pattern_registry = PatternRegistry::construct(&config.great_spell_sigs);
-        let compile_result = compile_to_iotas(&ast, None, &pattern_registry, &macros);
-        match compile_result {
-            // Ok(result) => println!("\nresult: {}", Vector::from(result).display()),
-            Ok(result) => println!("\nresult: {}", gen_give_cmd(result)),
+//a config that can't be read is an io error, one that can't be parsed is a parse error
+fn load_config(config_path: &Option<String>) -> Result<Config, (ExitStatus, String)> {
+    let (path, source) = match config_path {
+        //a config that was asked for explicitly has to exist
+        Some(path) => match fs::read_to_string(path) {
+            Ok(source) => (path.as_str(), source),
+            Err(err) => return Err((ExitStatus::Io, format!("{path}: {err}"))),
+        },
+        None => match fs::read_to_string("config.toml") {
+            Ok(source) => ("config.toml", source),
+            Err(_) => return Ok(Config::default()),
+        },
+    };
+    parse_config(source).map_err(|err| (ExitStatus::Parse, format!("{path}: {err}")))
+}
 
-            Err(err) => {
-                print_interpreter_error(err, &source, &args.source_path);
-            }
-        };
-    } else if let Command::Send = args.command { 
-        let pattern_registry = PatternRegistry::construct(&config.great_spell_sigs);
-        let compile_result = compile_to_iotas(&ast, None, &pattern_registry, &macros);
-        match compile_result {
-            // Ok(result) => println!("\nresult: {}", Vector::from(result).display()),
-            Ok(result) => {
-                let result = send_hex(result, &args.url.unwrap());
-                match result {
-                    Ok(msg) => println!("{msg}"),
-                    Err(error) => println!("{error}")
-                }
-            },
+//the config file with the options given on the command line applied on top
+fn load_args_config(args: &Args) -> Result<Config, (ExitStatus, String)> {
+    let mut config = load_config(&args.config_path)?;
+    config.in_game_mishaps |= args.in_game_mishaps;
+    config.seed = args.seed.or(config.seed);
+    Ok(config)
+}
 
+fn write_output(output: &str, args: &Args) -> ExitStatus {
+    match &args.output_path {
+        Some(path) => match fs::write(path, output) {
+            Ok(_) => ExitStatus::Success,
             Err(err) => {
-                print_interpreter_error(err, &source, &args.source_path);
+                eprintln!("{} {path}: {err}", "Error:".red().bold());
+                ExitStatus::Io
             }
-        };
+        },
+        None if args.quiet => {
+            println!("{output}");
+            ExitStatus::Success
+        }
+        None => {
+            println!("\nresult: {output}");
+            ExitStatus::Success
+        }
     }
-
 }
@@ -0,0 +1,580 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, BufRead, Write},
+};
+
+use pest::error::{Error, LineColLocation};
+use serde_json::{json, Value};
+
+use crate::{
+    compiler::compile_to_iotas,
+    dap::{read_message, write_message},
+    parse_config::Config,
+    parser::{self, AstNode, Location, Macros, OpName, OpValue, Rule, SourceFile},
+    pattern_registry::{PatternRegistry, PatternRegistryExt},
+};
+
+//serves the Language Server Protocol until the client exits
+pub fn serve(config: Config) -> io::Result<()> {
+    let stdin = io::stdin();
+    let mut server = LspServer::new(config, io::stdout());
+    server.serve(&mut stdin.lock())
+}
+
+//the last successful parse of a document, used for hover and go to definition
+struct Document {
+    text: String,
+    ast: Option<AstNode>,
+    macros: Macros,
+    sources: Vec<SourceFile>,
+}
+
+pub struct LspServer<W: Write> {
+    writer: W,
+    config: Config,
+    pattern_registry: PatternRegistry,
+    documents: HashMap<String, Document>,
+}
+
+impl<W: Write> LspServer<W> {
+    pub fn new(config: Config, writer: W) -> LspServer<W> {
+        LspServer {
+            writer,
+            pattern_registry: PatternRegistry::from_config(&config),
+            config,
+            documents: HashMap::new(),
+        }
+    }
+
+    pub fn serve(&mut self, reader: &mut impl BufRead) -> io::Result<()> {
+        while let Some(message) = read_message(reader)? {
+            let method = message["method"].as_str().unwrap_or_default();
+            let params = &message["params"];
+
+            match method {
+                "exit" => break,
+                "textDocument/didOpen" => {
+                    let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+                    let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                    self.update_document(uri, text)?;
+                }
+                "textDocument/didChange" => {
+                    //only full document sync is supported, so the last change is the whole text
+                    let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+                    if let Some(text) = params["contentChanges"]
+                        .as_array()
+                        .and_then(|changes| changes.last())
+                        .and_then(|change| change["text"].as_str())
+                    {
+                        self.update_document(uri, text)?;
+                    }
+                }
+                "textDocument/didClose" => {
+                    let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+                    self.documents.remove(uri);
+                    self.publish_diagnostics(uri, vec![])?;
+                }
+                _ => (),
+            }
+
+            //notifications don't have an id and don't get a response
+            if message.get("id").is_none() {
+                continue;
+            }
+            let result = self.handle_request(method, params);
+            let response = match result {
+                Ok(result) => json!({ "jsonrpc": "2.0", "id": message["id"], "result": result }),
+                Err(err) => json!({
+                    "jsonrpc": "2.0",
+                    "id": message["id"],
+                    "error": { "code": -32601, "message": err },
+                }),
+            };
+            write_message(&mut self.writer, &response)?;
+        }
+        Ok(())
+    }
+
+    fn handle_request(&self, method: &str, params: &Value) -> Result<Value, String> {
+        match method {
+            "initialize" => Ok(json!({
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "completionProvider": {},
+                    "hoverProvider": true,
+                    "definitionProvider": true,
+                },
+                "serverInfo": { "name": "hexagon", "version": env!("CARGO_PKG_VERSION") },
+            })),
+            "shutdown" => Ok(Value::Null),
+            "textDocument/completion" => Ok(self.completion(params)),
+            "textDocument/hover" => Ok(self.hover(params)),
+            "textDocument/definition" => Ok(self.definition(params)),
+            _ => Err(format!("unsupported method '{method}'")),
+        }
+    }
+
+    fn update_document(&mut self, uri: &str, text: &str) -> io::Result<()> {
+        let path = uri_to_path(uri);
+        let mut entities = self.config.entities.clone();
+
+        let mut document = Document {
+            text: text.to_string(),
+            ast: None,
+            macros: HashMap::new(),
+            sources: vec![],
+        };
+        let mut diagnostics = vec![];
+
+        match parser::parse(text, &path, &self.pattern_registry, &mut entities) {
+            Ok((ast, macros, sources)) => {
+                if let Err((mishap, location, caused_by)) =
+                    compile_to_iotas(&ast, None, &self.pattern_registry, &macros)
+                {
+                    let message = match mishap.error_hint() {
+                        Some(hint) => format!("{}\n{hint}", mishap.error_message()),
+                        None => mishap.error_message(),
+                    };
+                    diagnostics.push(mishap_diagnostic(&location, &caused_by, message, &sources));
+                }
+                document.ast = Some(ast);
+                document.macros = macros;
+                document.sources = sources;
+            }
+            Err(errors) => {
+                diagnostics.extend(errors.iter().map(|err| parse_error_diagnostic(err, &path)))
+            }
+        }
+
+        //keep the last good parse around so hover still works while the document is broken
+        if let Some(previous) = self.documents.remove(uri) {
+            if document.ast.is_none() {
+                document.ast = previous.ast;
+                document.macros = previous.macros;
+                document.sources = previous.sources;
+            }
+        }
+        self.documents.insert(uri.to_string(), document);
+
+        self.publish_diagnostics(uri, diagnostics)
+    }
+
+    fn publish_diagnostics(&mut self, uri: &str, diagnostics: Vec<Value>) -> io::Result<()> {
+        write_message(
+            &mut self.writer,
+            &json!({
+                "jsonrpc": "2.0",
+                "method": "textDocument/publishDiagnostics",
+                "params": { "uri": uri, "diagnostics": diagnostics },
+            }),
+        )
+    }
+
+    fn completion(&self, params: &Value) -> Value {
+        let macros = self
+            .documents
+            .get(params["textDocument"]["uri"].as_str().unwrap_or_default())
+            .map(|document| &document.macros);
+
+        let patterns = self.pattern_registry.iter().map(|pattern| {
+            json!({
+                "label": pattern.display_name,
+                "kind": 3,
+                "detail": pattern.args.as_ref().map(|args| args.raw.clone()),
+                "documentation": pattern.mod_name,
+            })
+        });
+        let macros = macros.into_iter().flatten().map(|(name, (_, params, _))| {
+            json!({
+                "label": name,
+                "kind": 15,
+                "detail": format!("#define {name}({})", params.join(", ")),
+            })
+        });
+
+        Value::Array(patterns.chain(macros).collect())
+    }
+
+    fn hover(&self, params: &Value) -> Value {
+        let Some((document, line, column)) = self.position(params) else {
+            return Value::Null;
+        };
+        let Some(node) = node_at(document, line, column) else {
+            return Value::Null;
+        };
+
+        let contents = match node {
+            AstNode::Action { name, .. } | AstNode::MacroCall { name, .. }
+                if document.macros.contains_key(name) =>
+            {
+                let (_, params, _) = &document.macros[name];
+                format!("```\n#define {name}({})\n```", params.join(", "))
+            }
+            AstNode::Action { name, value, .. } => {
+                let Some(pattern) = self.pattern_registry.find(name, value) else {
+                    return Value::Null;
+                };
+                let mut contents =
+                    format!("**{}** `{}`", pattern.display_name, pattern.internal_name);
+                if let Some(args) = &pattern.args {
+                    contents += &format!("\n\n`{}`", args.raw);
+                    let arity = args
+                        .arity()
+                        .map_or("variable".to_string(), |arity| arity.to_string());
+                    contents += &format!("\n\narity: {arity}");
+                }
+                let start_dir = pattern
+                    .start_dir
+                    .map_or(String::new(), |start_dir| format!("{start_dir:?} "));
+                contents += &format!("\n\nsignature: `{start_dir}{}`", pattern.signature);
+                if let Some(mod_name) = &pattern.mod_name {
+                    contents += &format!("\n\nmod: {mod_name}");
+                }
+                contents
+            }
+            _ => return Value::Null,
+        };
+
+        json!({ "contents": { "kind": "markdown", "value": contents } })
+    }
+
+    fn definition(&self, params: &Value) -> Value {
+        let Some((document, line, column)) = self.position(params) else {
+            return Value::Null;
+        };
+
+        if let Some(var) = var_at(&document.text, line, column) {
+            return document
+                .ast
+                .as_ref()
+                .and_then(|ast| var_definition(ast, &var))
+                .and_then(|location| location_json(&location, var.len(), &document.sources))
+                .unwrap_or(Value::Null);
+        }
+
+        match node_at(document, line, column) {
+            Some(AstNode::Action { name, .. } | AstNode::MacroCall { name, .. })
+                if document.macros.contains_key(name) =>
+            {
+                macro_definition(name, document).unwrap_or(Value::Null)
+            }
+            _ => Value::Null,
+        }
+    }
+
+    //the document and one based line and column a request points at
+    fn position<'a>(&'a self, params: &Value) -> Option<(&'a Document, usize, usize)> {
+        let document = self
+            .documents
+            .get(params["textDocument"]["uri"].as_str()?)?;
+        let line = params["position"]["line"].as_u64()? as usize + 1;
+        let column = params["position"]["character"].as_u64()? as usize + 1;
+        Some((document, line, column))
+    }
+}
+
+//finds the action or macro call in the root file whose name covers the position
+fn node_at(document: &Document, line: usize, column: usize) -> Option<&AstNode> {
+    let mut nodes = vec![];
+    flatten(document.ast.as_ref()?, &mut nodes);
+
+    nodes.into_iter().find(|node| match node {
+        AstNode::Action {
+            location: Location::Line(node_line, node_column),
+            name,
+            ..
+        }
+        | AstNode::MacroCall {
+            location: Location::Line(node_line, node_column),
+            name,
+            ..
+        } if *node_line == line => {
+            //actions with a value are located at the value, so look back for the name
+            let Some(content) = document.text.lines().nth(line - 1) else {
+                return false;
+            };
+            let end = (node_column - 1 + name.len()).min(content.len());
+            content
+                .get(..end)
+                .and_then(|prefix| prefix.rfind(name.as_str()))
+                .is_some_and(|start| (start + 1..=start + name.len()).contains(&column))
+        }
+        _ => false,
+    })
+}
+
+fn flatten<'a>(node: &'a AstNode, nodes: &mut Vec<&'a AstNode>) {
+    nodes.push(node);
+    match node {
+        AstNode::Program(children)
+        | AstNode::Block {
+            nodes: children, ..
+        } => children.iter().for_each(|child| flatten(child, nodes)),
+        AstNode::MacroCall { args, .. } => args.iter().for_each(|arg| flatten(arg, nodes)),
+        AstNode::IfBlock {
+            condition,
+            succeed,
+            fail,
+            ..
+        } => {
+            flatten(condition, nodes);
+            flatten(succeed, nodes);
+            if let Some(fail) = fail {
+                flatten(fail, nodes);
+            }
+        }
+        AstNode::WhileBlock {
+            condition, block, ..
+        } => {
+            flatten(condition, nodes);
+            flatten(block, nodes);
+        }
+        AstNode::Action { .. } | AstNode::Op { .. } => (),
+    }
+}
+
+//the $variable under the cursor, if there is one
+fn var_at(text: &str, line: usize, column: usize) -> Option<String> {
+    let chars = text.lines().nth(line - 1)?.chars().collect::<Vec<_>>();
+    let index = column - 1;
+
+    let start = (0..=index.min(chars.len().checked_sub(1)?))
+        .rev()
+        .take_while(|i| chars[*i] == '$' || chars[*i].is_ascii_alphanumeric())
+        .last()?;
+    if chars[start] != '$' {
+        return None;
+    }
+    let end = (start + 1..chars.len())
+        .take_while(|i| chars[*i].is_ascii_alphanumeric())
+        .last()?;
+
+    (index <= end).then(|| chars[start..=end].iter().collect())
+}
+
+//variables are defined by the first Store or Copy that uses them
+fn var_definition(ast: &AstNode, var: &str) -> Option<Location> {
+    let mut nodes = vec![];
+    flatten(ast, &mut nodes);
+
+    nodes.into_iter().find_map(|node| match node {
+        AstNode::Op {
+            location,
+            name: OpName::Store | OpName::Copy,
+            arg: Some(OpValue::Var(name)),
+        } if name == var => Some(*location),
+        _ => None,
+    })
+}
+
+//macros don't keep their location, so the definition is found in the source text
+fn macro_definition(name: &str, document: &Document) -> Option<Value> {
+    document
+        .sources
+        .iter()
+        .enumerate()
+        .find_map(|(file, source)| {
+            //the root document might have unsaved changes
+            let text = if file == 0 {
+                &document.text
+            } else {
+                &source.source
+            };
+            text.lines().enumerate().find_map(|(line, content)| {
+                let rest = content.trim_start().strip_prefix("#define")?;
+                let rest = rest.trim_start().strip_prefix(name)?;
+                if rest.starts_with(|c: char| c.is_ascii_alphanumeric() || c == '\'') {
+                    return None;
+                }
+                let column = content.find(name)?;
+                Some(json!({
+                    "uri": path_to_uri(&source.path),
+                    "range": range(line, column, column + name.len()),
+                }))
+            })
+        })
+}
+
+fn location_json(location: &Location, len: usize, sources: &[SourceFile]) -> Option<Value> {
+    let (file, line) = location.file_line()?;
+    let column = match location {
+        Location::Line(_, column) | Location::File(_, _, column) => column - 1,
+        _ => 0,
+    };
+    Some(json!({
+        "uri": path_to_uri(&sources.get(file)?.path),
+        "range": range(line - 1, column, column + len),
+    }))
+}
+
+fn mishap_diagnostic(
+    location: &Location,
+    caused_by: &str,
+    message: String,
+    sources: &[SourceFile],
+) -> Value {
+    let range = match location {
+        Location::Line(line, column) => range(line - 1, column - 1, column - 1 + caused_by.len()),
+        _ => range(0, 0, 0),
+    };
+    //mishaps in included files are reported on the first line
+    let message = match location {
+        Location::File(file, line, _) => format!("{}:{line}: {message}", sources[*file].path),
+        _ => message,
+    };
+    json!({ "range": range, "severity": 1, "source": "hexagon", "message": message })
+}
+
+fn parse_error_diagnostic(err: &Error<Rule>, path: &str) -> Value {
+    let (start, end) = match err.line_col {
+        LineColLocation::Pos(pos) => (pos, pos),
+        LineColLocation::Span(start, end) => (start, end),
+    };
+    let message = err.variant.message().to_string();
+
+    match err.path() {
+        Some(err_path) if err_path != path => json!({
+            "range": range(0, 0, 0),
+            "severity": 1,
+            "source": "hexagon",
+            "message": format!("{err_path}:{}: {message}", start.0),
+        }),
+        _ => json!({
+            "range": {
+                "start": { "line": start.0 - 1, "character": start.1 - 1 },
+                "end": { "line": end.0 - 1, "character": end.1 - 1 },
+            },
+            "severity": 1,
+            "source": "hexagon",
+            "message": message,
+        }),
+    }
+}
+
+fn range(line: usize, start: usize, end: usize) -> Value {
+    json!({
+        "start": { "line": line, "character": start },
+        "end": { "line": line, "character": end },
+    })
+}
+
+fn uri_to_path(uri: &str) -> String {
+    let path = uri.strip_prefix("file://").unwrap_or(uri);
+
+    //percent decoding, eg. %20 for spaces
+    let mut bytes = vec![];
+    let mut chars = path.bytes();
+    while let Some(byte) = chars.next() {
+        if byte == b'%' {
+            let hex = [chars.next(), chars.next()];
+            if let [Some(high), Some(low)] = hex {
+                if let Ok(byte) = u8::from_str_radix(&String::from_utf8_lossy(&[high, low]), 16) {
+                    bytes.push(byte);
+                    continue;
+                }
+            }
+        }
+        bytes.push(byte);
+    }
+    String::from_utf8_lossy(&bytes).to_string()
+}
+
+fn path_to_uri(path: &str) -> String {
+    let path = fs::canonicalize(path).map_or(path.to_string(), |path| path.display().to_string());
+    format!("file://{}", path.replace('%', "%25").replace(' ', "%20"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn message(id: Option<i64>, method: &str, params: Value) -> Vec<u8> {
+        let mut message = json!({ "jsonrpc": "2.0", "method": method, "params": params });
+        if let Some(id) = id {
+            message["id"] = json!(id);
+        }
+        let mut bytes = vec![];
+        write_message(&mut bytes, &message).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn lsp_test() {
+        let uri = "file:///tmp/hexagon_lsp_test.hexagon";
+        let text = "#define Double (SOUTH_EAST qaq) {\n    Gemini Decomposition\n}\nNumerical Reflection: 1\nStore($x)\nDouble\nPush($x)";
+        let position = |line: i64, character: i64| json!({ "textDocument": { "uri": uri }, "position": { "line": line, "character": character } });
+
+        let input = [
+            message(Some(1), "initialize", json!({})),
+            message(None, "initialized", json!({})),
+            message(
+                None,
+                "textDocument/didOpen",
+                json!({ "textDocument": { "uri": uri, "text": text } }),
+            ),
+            //hover and go to definition keep using the last document that parsed
+            message(
+                None,
+                "textDocument/didChange",
+                json!({
+                    "textDocument": { "uri": uri },
+                    "contentChanges": [{ "text": format!("{text}\nNot A Pattern") }],
+                }),
+            ),
+            message(Some(2), "textDocument/hover", position(3, 3)),
+            message(Some(3), "textDocument/definition", position(5, 1)),
+            message(Some(4), "textDocument/definition", position(6, 6)),
+            message(
+                Some(5),
+                "textDocument/completion",
+                json!({ "textDocument": { "uri": uri } }),
+            ),
+            message(Some(6), "shutdown", Value::Null),
+            message(None, "exit", Value::Null),
+        ]
+        .concat();
+
+        let config = Config::default();
+        let mut output = vec![];
+        LspServer::new(config, &mut output)
+            .serve(&mut Cursor::new(input))
+            .unwrap();
+
+        let mut reader = Cursor::new(output);
+        let mut messages = vec![];
+        while let Some(message) = read_message(&mut reader).unwrap() {
+            messages.push(message);
+        }
+        let response = |id: i64| {
+            messages
+                .iter()
+                .find(|message| message["id"] == id)
+                .map(|message| message["result"].clone())
+                .unwrap()
+        };
+
+        let diagnostics = messages
+            .iter()
+            .filter(|message| message["method"] == "textDocument/publishDiagnostics")
+            .map(|message| message["params"]["diagnostics"].clone())
+            .collect::<Vec<_>>();
+        assert_eq!(diagnostics[0], json!([]));
+        assert_eq!(diagnostics[1].as_array().unwrap().len(), 1);
+        assert_eq!(diagnostics[1][0]["range"]["start"]["line"], 7);
+
+        assert!(response(2)["contents"]["value"]
+            .as_str()
+            .unwrap()
+            .contains("Numerical Reflection"));
+        assert_eq!(response(3)["range"]["start"]["line"], 0);
+        assert_eq!(response(4)["range"]["start"]["line"], 4);
+        assert!(response(5)
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|item| item["label"] == "Double"));
+    }
+}
@@ -1,3 +1,5 @@
-fn main() {
-    hexagon::run();
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    hexagon::run()
 }
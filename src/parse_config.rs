@@ -1,31 +1,91 @@
 use pest::Parser;
-use std::{collections::HashMap, rc::Rc};
+use std::{collections::HashMap, fs, path::PathBuf, rc::Rc};
 use toml::{map::Map, Table, Value};
 
 use crate::{
-    interpreter::state::{Entity, EntityType, Holding, Library},
+    interpreter::state::{Entity, EntityType, Holding, Library, Limits},
     iota::{
         hex_casting::pattern::{Signature, SignatureExt},
         Iota,
     },
     parser::{parse_iota, HexParser, Rule},
-    pattern_registry::{PatternRegistry, PatternRegistryExt},
+    pattern_registry::{
+        data::{parse_pattern_data, PatternData},
+        numbers, PatternRegistry, PatternRegistryExt,
+    },
 };
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Config {
     pub libraries: HashMap<[i32; 3], Library>,
     pub entities: HashMap<String, Entity>,
     pub great_spell_sigs: HashMap<String, String>,
+    pub pattern_data: Option<Vec<PatternData>>,
+    //keep casting after a mishap, the way the mod does when drawing with a staff
+    pub in_game_mishaps: bool,
+    //the media the caster can spend in dust, None means unlimited
+    pub media_budget: Option<f64>,
+    pub limits: Limits,
+    //seeds the randomised patterns so that runs can be replayed, None picks a random seed
+    pub seed: Option<u64>,
+    //where the patterns found for numbers are kept between runs, None turns the cache off
+    pub number_cache: Option<PathBuf>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            libraries: HashMap::new(),
+            entities: HashMap::new(),
+            great_spell_sigs: PatternRegistry::gen_default_great_sigs(),
+            pattern_data: None,
+            in_game_mishaps: false,
+            media_budget: None,
+            limits: Limits::default(),
+            seed: None,
+            number_cache: numbers::default_cache_path(),
+        }
+    }
 }
 
-pub fn parse_config(source: String) -> Config {
-    let parsed = source.parse::<Table>().unwrap();
+pub fn parse_config(source: String) -> Result<Config, String> {
+    let parsed = source.parse::<Table>().map_err(|err| err.to_string())?;
+
+    let mut config = Config::default();
+
+    if let Some(Value::String(path)) = &parsed.get("pattern_data") {
+        let source = fs::read_to_string(path).map_err(|err| format!("{path}: {err}"))?;
+        config.pattern_data =
+            Some(parse_pattern_data(&source).map_err(|err| format!("{path}: {err}"))?);
+    };
+
+    if let Some(Value::Boolean(in_game_mishaps)) = &parsed.get("in_game_mishaps") {
+        config.in_game_mishaps = *in_game_mishaps;
+    };
+
+    if let Some(Value::Table(media)) = &parsed.get("media") {
+        config.media_budget = Some(parse_media(media));
+    };
+
+    if let Some(Value::Table(limits)) = &parsed.get("limits") {
+        if let Some(Value::Integer(max_ops)) = limits.get("max_ops") {
+            config.limits.max_ops = parse_limit("max_ops", *max_ops)?;
+        }
+        if let Some(Value::Integer(max_depth)) = limits.get("max_depth") {
+            config.limits.max_depth = parse_limit("max_depth", *max_depth)?;
+        }
+    };
+
+    if let Some(Value::Integer(seed)) = &parsed.get("seed") {
+        config.seed = Some(
+            u64::try_from(*seed).map_err(|_| format!("invalid seed '{seed}' (expected 0 or more)"))?,
+        );
+    };
 
-    let mut config = Config {
-        libraries: HashMap::new(),
-        entities: HashMap::new(),
-        great_spell_sigs: PatternRegistry::gen_default_great_sigs(),
+    match &parsed.get("number_cache") {
+        Some(Value::String(path)) => config.number_cache = Some(PathBuf::from(path)),
+        Some(Value::Boolean(false)) => config.number_cache = None,
+        _ => (),
     };
 
     if let Some(Value::Table(sigs)) = &parsed.get("Great_Spells") {
@@ -38,16 +98,34 @@ pub fn parse_config(source: String) -> Config {
 
     for (key, val) in &parsed {
         match &key[..] {
-            "libraries" => parse_libraries(val, &mut config),
-            "entities" => parse_entities(val, &mut config),
+            "libraries" => parse_libraries(val, &mut config)?,
+            "entities" => parse_entities(val, &mut config)?,
             _ => (),
         }
     }
 
-    config
+    Ok(config)
+}
+
+//a negative limit would wrap around to no limit at all
+fn parse_limit(name: &str, limit: i64) -> Result<usize, String> {
+    usize::try_from(limit)
+        .map_err(|_| format!("invalid {name} '{limit}' (expected 0 or more)"))
+}
+
+//media can come from the caster's inventory, the battery of the item being cast from and phials
+fn parse_media(media: &Table) -> f64 {
+    ["inventory", "battery", "phial"]
+        .iter()
+        .filter_map(|source| match media.get(*source) {
+            Some(Value::Integer(dust)) => Some(*dust as f64),
+            Some(Value::Float(dust)) => Some(*dust),
+            _ => None,
+        })
+        .sum()
 }
 
-fn parse_libraries(libraries: &Value, config: &mut Config) {
+fn parse_libraries(libraries: &Value, config: &mut Config) -> Result<(), String> {
     let libraries = match libraries {
         Value::Array(arr) => arr,
         _ => unreachable!(),
@@ -55,28 +133,21 @@ fn parse_libraries(libraries: &Value, config: &mut Config) {
 
     for val in libraries {
         match val {
-            Value::Table(library) => parse_library(&mut library.clone(), config),
+            Value::Table(library) => parse_library(&mut library.clone(), config)?,
             _ => unreachable!(),
         }
     }
+    Ok(())
 }
 
-fn parse_library(library: &mut Map<String, Value>, config: &mut Config) {
+fn parse_library(library: &mut Map<String, Value>, config: &mut Config) -> Result<(), String> {
     let mut contents: HashMap<_, Rc<dyn Iota>> = HashMap::new();
 
     let location_value = library.get("location").unwrap().clone();
     library.remove("location");
 
     for (key, val) in library {
-        let iota = parse_iota(
-            HexParser::parse(Rule::Iota, parse_str(val))
-                .unwrap()
-                .next()
-                .unwrap(),
-            &PatternRegistry::construct(&config.great_spell_sigs),
-            &mut config.entities,
-            &HashMap::new(),
-        );
+        let iota = parse_config_iota(&format!("libraries.{key}"), parse_str(val), config)?;
         contents.insert(Signature::from_sig(key), iota);
     }
 
@@ -92,9 +163,10 @@ fn parse_library(library: &mut Map<String, Value>, config: &mut Config) {
         }
     };
     config.libraries.insert(location, contents);
+    Ok(())
 }
 
-fn parse_entities(entities: &Value, config: &mut Config) {
+fn parse_entities(entities: &Value, config: &mut Config) -> Result<(), String> {
     let entities = match entities {
         Value::Array(arr) => arr,
         _ => unreachable!(),
@@ -102,13 +174,14 @@ fn parse_entities(entities: &Value, config: &mut Config) {
 
     for val in entities {
         match val {
-            Value::Table(entity) => parse_entity(entity, config),
+            Value::Table(entity) => parse_entity(entity, config)?,
             _ => unreachable!(),
         }
     }
+    Ok(())
 }
 
-fn parse_entity(entity: &Map<String, Value>, config: &mut Config) {
+fn parse_entity(entity: &Map<String, Value>, config: &mut Config) -> Result<(), String> {
     let name_value = entity.get("name").unwrap().clone();
     let name = parse_str(&name_value).to_string();
 
@@ -120,7 +193,7 @@ fn parse_entity(entity: &Map<String, Value>, config: &mut Config) {
 
     let entity_type_value = entity.get("type").unwrap().clone();
     let entity_type_pair = HexParser::parse(Rule::EntityType, parse_str(&entity_type_value))
-        .unwrap()
+        .map_err(|err| format!("entities.{name}.type: {}", err.variant.message()))?
         .next()
         .unwrap();
     let entity_type = parse_entity_type(entity_type_pair.as_str().to_string());
@@ -128,21 +201,10 @@ fn parse_entity(entity: &Map<String, Value>, config: &mut Config) {
     let held_item = entity.get("item");
     let held_item = held_item.map(|i| &parse_str(i)[..]);
 
-    let held_item_contents_value = entity.get("iota");
-    let held_item_contents_pair = held_item_contents_value.map(|value| {
-        HexParser::parse(Rule::Iota, parse_str(value))
-            .unwrap()
-            .next()
-            .unwrap()
-    });
-    let held_item_contents = held_item_contents_pair.map(|pair| {
-        parse_iota(
-            pair,
-            &PatternRegistry::construct(&config.great_spell_sigs),
-            &mut config.entities,
-            &HashMap::new(),
-        )
-    });
+    let held_item_contents = entity
+        .get("iota")
+        .map(|value| parse_config_iota(&format!("entities.{name}.iota"), parse_str(value), config))
+        .transpose()?;
 
     let holding = match held_item {
         Some("Focus") => Holding::Focus(held_item_contents),
@@ -162,6 +224,22 @@ fn parse_entity(entity: &Map<String, Value>, config: &mut Config) {
             holding: Box::new(holding),
         },
     );
+    Ok(())
+}
+
+//errors are named after the key the iota was written under, like `libraries.qaq`
+fn parse_config_iota(key: &str, source: &str, config: &mut Config) -> Result<Rc<dyn Iota>, String> {
+    let pair = HexParser::parse(Rule::Iota, source)
+        .map_err(|err| format!("{key}: {}", err.variant.message()))?
+        .next()
+        .unwrap();
+    parse_iota(
+        pair,
+        &PatternRegistry::construct(&config.great_spell_sigs),
+        &mut config.entities,
+        &HashMap::new(),
+    )
+    .map_err(|errors| format!("{key}: {}", errors[0].variant.message()))
 }
 
 pub fn parse_entity_type(string: String) -> EntityType {
@@ -189,3 +267,33 @@ fn parse_str(value: &Value) -> &String {
         _ => unreachable!(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_config_error_test() {
+        assert!(parse_config("seed = 1".to_string()).is_ok());
+        assert!(parse_config("seed = ".to_string()).is_err());
+        assert!(parse_config("pattern_data = \"missing.json\"".to_string())
+            .unwrap_err()
+            .starts_with("missing.json: "));
+
+        let config = parse_config("[limits]\nmax_ops = 100".to_string()).unwrap();
+        assert_eq!(config.limits.max_ops, 100);
+        assert_eq!(
+            parse_config("[limits]\nmax_depth = -1".to_string()).unwrap_err(),
+            "invalid max_depth '-1' (expected 0 or more)"
+        );
+        assert!(parse_config("seed = -1".to_string()).is_err());
+
+        //a typo in an iota is reported with the key it was written under
+        let library = "[[libraries]]\nlocation = [0, 0, 0]\nqaq = \"[1, 2\"";
+        let err = parse_config(library.to_string()).unwrap_err();
+        assert!(err.starts_with("libraries.qaq: "), "{err}");
+        let entity = "[[entities]]\nname = \"Robin\"\ntype = \"Player\"\nitem = \"Focus\"\niota = \"Not A Pattern\"";
+        let err = parse_config(entity.to_string()).unwrap_err();
+        assert!(err.starts_with("entities.Robin.iota: "), "{err}");
+    }
+}
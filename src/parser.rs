@@ -1,7 +1,14 @@
-use std::{collections::HashMap, fmt::Display, ops::Deref, rc::Rc};
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    fs,
+    ops::Deref,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
 
 use crate::{
-    interpreter::state::Entity,
+    interpreter::{mishap::Mishap, state::Entity},
     iota::{
         hex_casting::{
             entity::EntityIota, garbage::GarbageIota, list::ListIota, null::NullIota,
@@ -14,64 +21,353 @@ use crate::{
 };
 use nalgebra::matrix;
 use pest::{
-    error::Error,
+    error::{Error, ErrorVariant, InputLocation},
     iterators::{Pair, Pairs},
-    Parser,
+    Parser, Span,
 };
 use pest_derive::Parser;
 
-pub type Macros = HashMap<String, (PatternIota, AstNode)>;
+//macros map their name to their pattern, parameter names and body
+pub type Macros = HashMap<String, (PatternIota, Vec<String>, AstNode)>;
 
 #[derive(Parser)]
 #[grammar = "grammar.pest"]
 pub struct HexParser;
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceFile {
+    pub path: String,
+    pub source: String,
+}
+
+pub type ParseErrors = Vec<Error<Rule>>;
+
+type ParseResult<T> = Result<T, ParseErrors>;
+
+//the first source file is always the one that was parsed, the rest are the files it included
+//every error in every file is returned, not only the first one
 pub fn parse(
     source: &str,
-    great_spell_sigs: &HashMap<String, String>,
+    source_path: &str,
+    pattern_registry: &PatternRegistry,
+    conf_entities: &mut HashMap<String, Entity>,
+) -> ParseResult<(AstNode, Macros, Vec<SourceFile>)> {
+    parse_with_macros(source, source_path, pattern_registry, conf_entities, HashMap::new())
+}
+
+//like parse, but macros defined before, like in an earlier line of the repl, can be used too.
+//they are returned along with the new ones
+pub fn parse_with_macros(
+    source: &str,
+    source_path: &str,
+    pattern_registry: &PatternRegistry,
+    conf_entities: &mut HashMap<String, Entity>,
+    macros: Macros,
+) -> ParseResult<(AstNode, Macros, Vec<SourceFile>)> {
+    let mut includes = Includes {
+        files: vec![SourceFile {
+            path: source_path.to_string(),
+            source: source.to_string(),
+        }],
+        included: vec![include_key(Path::new(source_path))],
+        stack: vec![],
+        macros: HashMap::new(),
+    };
+
+    let (ast, macros) = parse_source(0, &mut includes, pattern_registry, conf_entities, macros)?;
+
+    Ok((AstNode::Program(ast), macros, includes.files))
+}
+
+struct Includes {
+    files: Vec<SourceFile>,
+    //canonical paths of every file in `files`, in the same order
+    included: Vec<PathBuf>,
+    //files that are currently being parsed, used to detect include cycles
+    stack: Vec<usize>,
+    //the macros each file ended up with, so a file included again still gets them
+    macros: HashMap<PathBuf, Macros>,
+}
+
+fn include_key(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or(path.to_path_buf())
+}
+
+fn parse_source(
+    file: usize,
+    includes: &mut Includes,
+    pattern_registry: &PatternRegistry,
     conf_entities: &mut HashMap<String, Entity>,
-) -> Result<(AstNode, Macros), Box<Error<Rule>>> {
+    mut macros: Macros,
+) -> ParseResult<(Vec<AstNode>, Macros)> {
+    let SourceFile { path, source } = includes.files[file].clone();
     let mut ast = vec![];
-    let pattern_registry = PatternRegistry::construct(great_spell_sigs);
-    let mut macros: Macros = HashMap::new();
+    let mut included_nodes = vec![];
+    let mut macro_bodies = vec![];
+
+    includes.stack.push(file);
+
+    let (recovered_source, mut errors) = recover_syntax_errors(&source);
+    let pairs = match HexParser::parse(Rule::File, &recovered_source) {
+        Ok(pairs) => pairs,
+        Err(_) => {
+            includes.stack.pop();
+            return Err(with_path(errors, &path));
+        }
+    };
 
-    let pairs = HexParser::parse(Rule::File, source)?;
     for pair in pairs.clone() {
-        if Rule::Macro == pair.as_rule() {
-            let hex_macro = parse_macro(pair, &pattern_registry, conf_entities);
-            macros.insert(hex_macro.0, hex_macro.1);
+        match pair.as_rule() {
+            Rule::Include => {
+                match parse_include(pair, &path, includes, pattern_registry, conf_entities) {
+                    Ok((nodes, included_macros)) => {
+                        macros.extend(included_macros);
+                        included_nodes.push(nodes);
+                    }
+                    Err(mut include_errors) => {
+                        errors.append(&mut include_errors);
+                        included_nodes.push(vec![]);
+                    }
+                }
+            }
+            Rule::Macro => match parse_macro(pair, pattern_registry, conf_entities) {
+                Ok((name, pattern, params, body)) => {
+                    //the body is parsed once every macro name is known
+                    let placeholder = AstNode::Block {
+                        external: false,
+                        nodes: vec![],
+                    };
+                    macros.insert(name.clone(), (pattern, params, placeholder));
+                    macro_bodies.push((name, body));
+                }
+                Err(mut macro_errors) => errors.append(&mut macro_errors),
+            },
+            _ => (),
+        }
+    }
+
+    let macro_bodies = macro_bodies
+        .into_iter()
+        .map(|(name, body)| {
+            (
+                name,
+                construct_ast_node(body, pattern_registry, conf_entities, &macros),
+            )
+        })
+        .collect::<Vec<_>>();
+    for (name, body) in macro_bodies {
+        match body {
+            Ok(body) => {
+                let mut body = body.unwrap();
+                body.set_file(file);
+                macros.get_mut(&name).unwrap().2 = body;
+            }
+            Err(mut body_errors) => errors.append(&mut body_errors),
         }
     }
+
+    let mut included_nodes = included_nodes.into_iter();
     for pair in pairs {
-        if let Some(node) = construct_ast_node(pair, &pattern_registry, conf_entities, &macros) {
-            ast.push(node);
+        if Rule::Include == pair.as_rule() {
+            ast.append(&mut included_nodes.next().unwrap());
+            continue;
+        }
+        //a statement with an error is left out, the rest of the file is still parsed
+        match construct_ast_node(pair, pattern_registry, conf_entities, &macros) {
+            Ok(Some(mut node)) => {
+                node.set_file(file);
+                ast.push(node);
+            }
+            Ok(None) => (),
+            Err(mut node_errors) => errors.append(&mut node_errors),
+        }
+    }
+
+    includes.stack.pop();
+
+    if errors.is_empty() {
+        Ok((ast, macros))
+    } else {
+        Err(with_path(errors, &path))
+    }
+}
+
+//blanks out lines with syntax errors until the rest of the file parses,
+//so every broken line is reported instead of only the first one
+fn recover_syntax_errors(source: &str) -> (String, ParseErrors) {
+    let mut source = source.to_string();
+    let mut errors = vec![];
+
+    while let Err(err) = HexParser::parse(Rule::File, &source) {
+        let position = match err.location {
+            InputLocation::Pos(position) | InputLocation::Span((position, _)) => position,
+        };
+        let line_start = source[..position].rfind('\n').map_or(0, |index| index + 1);
+        let line_end = source[position..]
+            .find('\n')
+            .map_or(source.len(), |index| position + index);
+        let line = &source[line_start..line_end];
+        errors.push(err);
+
+        //an error on an empty line (eg. a missing closing bracket) can't be recovered from
+        if line.trim().is_empty() {
+            break;
+        }
+        //line and column numbers of the rest of the file stay the same
+        let blank = line.chars().map(|_| ' ').collect::<String>();
+        source.replace_range(line_start..line_end, &blank);
+    }
+
+    (source, errors)
+}
+
+fn with_path(errors: ParseErrors, path: &str) -> ParseErrors {
+    errors
+        .into_iter()
+        //errors from included files already have their path
+        .map(|err| match err.path() {
+            Some(_) => err,
+            None => err.with_path(path),
+        })
+        .collect()
+}
+
+fn custom_error(message: String, span: Span<'_>) -> ParseErrors {
+    vec![Error::new_from_span(
+        ErrorVariant::CustomError { message },
+        span,
+    )]
+}
+
+//runs every parser before failing, so the errors of all of them are returned
+fn collect_results<T>(results: impl IntoIterator<Item = ParseResult<T>>) -> ParseResult<Vec<T>> {
+    let mut values = vec![];
+    let mut errors = vec![];
+    for result in results {
+        match result {
+            Ok(value) => values.push(value),
+            Err(mut result_errors) => errors.append(&mut result_errors),
         }
     }
 
-    Ok((AstNode::Program(ast), macros))
+    if errors.is_empty() {
+        Ok(values)
+    } else {
+        Err(errors)
+    }
 }
 
-fn parse_macro(
+fn parse_include(
     pair: Pair<'_, Rule>,
+    including_path: &str,
+    includes: &mut Includes,
     pattern_registry: &PatternRegistry,
     conf_entities: &mut HashMap<String, Entity>,
-) -> (String, (PatternIota, AstNode)) {
-    let mut inner = pair.into_inner();
+) -> ParseResult<(Vec<AstNode>, Macros)> {
+    let span = pair.as_span();
+    let include_error = |message: String| {
+        vec![
+            Error::new_from_span(ErrorVariant::CustomError { message }, span)
+                .with_path(including_path),
+        ]
+    };
+
+    let string = pair.into_inner().next().unwrap().as_str();
+    let relative_path = snailquote::unescape(string)
+        .map_err(|err| include_error(format!("invalid path {string}: {err}")))?;
+
+    //paths are relative to the file that includes them
+    let path = Path::new(including_path)
+        .parent()
+        .unwrap_or(Path::new(""))
+        .join(&relative_path);
+    let key = include_key(&path);
+
+    match includes
+        .included
+        .iter()
+        .position(|included| *included == key)
+    {
+        Some(index) if includes.stack.contains(&index) => {
+            let cycle = includes.stack[includes.stack.iter().position(|i| *i == index).unwrap()..]
+                .iter()
+                .map(|i| includes.files[*i].path.clone())
+                .chain([path.display().to_string()])
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            Err(include_error(format!("include cycle: {cycle}")))
+        }
+        //the nodes of a file are only included once
+        Some(_) => Ok((
+            vec![],
+            includes.macros.get(&key).cloned().unwrap_or_default(),
+        )),
+        None => {
+            let source = fs::read_to_string(&path).map_err(|err| {
+                include_error(format!("could not include '{}': {err}", path.display()))
+            })?;
+
+            includes.files.push(SourceFile {
+                path: path.display().to_string(),
+                source,
+            });
+            includes.included.push(key.clone());
+
+            let (nodes, macros) = parse_source(
+                includes.files.len() - 1,
+                includes,
+                pattern_registry,
+                conf_entities,
+                HashMap::new(),
+            )?;
+            includes.macros.insert(key, macros.clone());
+            Ok((nodes, macros))
+        }
+    }
+}
+
+//the body of the macro is returned unparsed, because it can use macros that come after it
+fn parse_macro<'a>(
+    pair: Pair<'a, Rule>,
+    pattern_registry: &PatternRegistry,
+    conf_entities: &mut HashMap<String, Entity>,
+) -> ParseResult<(String, PatternIota, Vec<String>, Pair<'a, Rule>)> {
+    let mut inner = pair.into_inner().peekable();
     let name = inner.next().unwrap().as_str().to_string();
+    let params = inner
+        .next_if(|pair| pair.as_rule() == Rule::MacroParams)
+        .map(|params| {
+            params
+                .into_inner()
+                .map(|param| param.as_str().to_string())
+                .collect()
+        })
+        .unwrap_or_default();
     let pattern = parse_pattern(
         inner.next().unwrap(),
         pattern_registry,
         conf_entities,
         &HashMap::new(),
-    );
-    let hex = construct_ast_node(
-        inner.next().unwrap(),
-        pattern_registry,
-        conf_entities,
-        &HashMap::new(),
-    )
-    .unwrap();
+    )?;
+
+    Ok((name, pattern, params, inner.next().unwrap()))
+}
+
+//combines two results so the errors of both are kept
+trait Combine<A, B> {
+    fn combine(self) -> ParseResult<(A, B)>;
+}
 
-    (name, (pattern, hex))
+impl<A, B> Combine<A, B> for (ParseResult<A>, ParseResult<B>) {
+    fn combine(self) -> ParseResult<(A, B)> {
+        match self {
+            (Ok(a), Ok(b)) => Ok((a, b)),
+            (Err(mut a), Err(mut b)) => {
+                a.append(&mut b);
+                Err(a)
+            }
+            (Err(errors), _) | (_, Err(errors)) => Err(errors),
+        }
+    }
 }
 
 fn construct_ast_node(
@@ -79,8 +375,8 @@ fn construct_ast_node(
     pattern_registry: &PatternRegistry,
     conf_entities: &mut HashMap<String, Entity>,
     macros: &Macros,
-) -> Option<AstNode> {
-    match pair.as_rule() {
+) -> ParseResult<Option<AstNode>> {
+    Ok(match pair.as_rule() {
         Rule::Action => {
             let mut pair = pair.into_inner();
             let left = pair.next().unwrap();
@@ -94,58 +390,72 @@ fn construct_ast_node(
                 pattern_registry,
                 conf_entities,
                 macros,
-            ))
+            )?)
         }
         Rule::Op => {
             let mut pair = pair.into_inner();
             let name = pair.next().unwrap();
             let arg = pair.next();
 
-            Some(parse_op(name, arg, pattern_registry, conf_entities, macros))
+            Some(parse_op(
+                name,
+                arg,
+                pattern_registry,
+                conf_entities,
+                macros,
+            )?)
         }
         Rule::Var => Some(parse_var(pair)),
-        Rule::Embed => Some(parse_embed(pair, pattern_registry, conf_entities, macros)),
+        Rule::MacroCall => Some(parse_macro_call(
+            pair,
+            pattern_registry,
+            conf_entities,
+            macros,
+        )?),
+        Rule::Embed => Some(parse_embed(pair, pattern_registry, conf_entities, macros)?),
         Rule::IfBlock => Some(parse_if_block(
             pair,
             pattern_registry,
             conf_entities,
             macros,
-        )),
+        )?),
         Rule::DoWhileBlock => Some(parse_while_block(
             pair,
             pattern_registry,
             conf_entities,
             macros,
             true,
-        )),
+        )?),
         Rule::WhileBlock => Some(parse_while_block(
             pair,
             pattern_registry,
             conf_entities,
             macros,
             false,
-        )),
+        )?),
         Rule::Term => Some(AstNode::Block {
-            nodes: pair
-                .into_inner()
-                .filter_map(|node| {
-                    construct_ast_node(node, pattern_registry, conf_entities, macros)
-                })
-                .collect(),
+            nodes: collect_results(
+                pair.into_inner()
+                    .map(|node| construct_ast_node(node, pattern_registry, conf_entities, macros)),
+            )?
+            .into_iter()
+            .flatten()
+            .collect(),
             external: false,
         }),
 
         Rule::ExternTerm => Some(AstNode::Block {
-            nodes: pair
-                .into_inner()
-                .filter_map(|node| {
-                    construct_ast_node(node, pattern_registry, conf_entities, macros)
-                })
-                .collect(),
+            nodes: collect_results(
+                pair.into_inner()
+                    .map(|node| construct_ast_node(node, pattern_registry, conf_entities, macros)),
+            )?
+            .into_iter()
+            .flatten()
+            .collect(),
             external: true,
         }),
         _ => None,
-    }
+    })
 }
 
 fn parse_op(
@@ -154,8 +464,8 @@ fn parse_op(
     pattern_registry: &PatternRegistry,
     conf_entities: &mut HashMap<String, Entity>,
     macros: &Macros,
-) -> AstNode {
-    AstNode::Op {
+) -> ParseResult<AstNode> {
+    Ok(AstNode::Op {
         name: {
             match name.as_str() {
                 "Store" => OpName::Store,
@@ -167,14 +477,15 @@ fn parse_op(
         arg: {
             arg.map(|pair| match pair.as_rule() {
                 Rule::Iota => {
-                    OpValue::Iota(parse_iota(pair, pattern_registry, conf_entities, macros))
+                    parse_iota(pair, pattern_registry, conf_entities, macros).map(OpValue::Iota)
                 }
-                Rule::Var => OpValue::Var(pair.as_str().to_string()),
+                Rule::Var => Ok(OpValue::Var(pair.as_str().to_string())),
                 _ => unreachable!(),
             })
+            .transpose()?
         },
         location: Location::Line(name.line_col().0, name.line_col().1),
-    }
+    })
 }
 
 fn parse_action(
@@ -184,10 +495,9 @@ fn parse_action(
     pattern_registry: &PatternRegistry,
     conf_entities: &mut HashMap<String, Entity>,
     macros: &Macros,
-) -> AstNode {
-    right
-        .clone()
-        .map(|pair| match pair.as_rule() {
+) -> ParseResult<AstNode> {
+    let action = match right.clone() {
+        Some(pair) => match pair.as_rule() {
             Rule::Iota => AstNode::Action {
                 name: left.as_str().to_string(),
                 value: Some(ActionValue::Iota(parse_iota(
@@ -195,7 +505,7 @@ fn parse_action(
                     pattern_registry,
                     conf_entities,
                     macros,
-                ))),
+                )?)),
                 location: Location::Line(pair.line_col().0, pair.line_col().1),
             },
 
@@ -207,17 +517,31 @@ fn parse_action(
 
             _ => AstNode::Action {
                 name: format!("{}: {}", left.as_str(), right.unwrap().as_str()),
-                value: righter.map(|p| {
-                    ActionValue::Iota(parse_iota(p, pattern_registry, conf_entities, macros))
-                }),
+                value: righter
+                    .map(|p| parse_iota(p, pattern_registry, conf_entities, macros))
+                    .transpose()?
+                    .map(ActionValue::Iota),
                 location: Location::Line(pair.line_col().0, pair.line_col().1),
             },
-        })
-        .unwrap_or(AstNode::Action {
+        },
+        None => AstNode::Action {
             name: left.as_str().to_string(),
             value: None,
             location: Location::Line(left.line_col().0, left.line_col().1),
-        })
+        },
+    };
+
+    match &action {
+        AstNode::Action { name, value, .. }
+            if !macros.contains_key(name) && pattern_registry.find(name, value).is_none() =>
+        {
+            Err(custom_error(
+                format!("unknown pattern '{name}'"),
+                left.as_span(),
+            ))
+        }
+        _ => Ok(action),
+    }
 }
 
 fn parse_action_iota(
@@ -227,42 +551,48 @@ fn parse_action_iota(
     pattern_registry: &PatternRegistry,
     macros: &Macros,
     conf_entities: &mut HashMap<String, Entity>,
-) -> PatternIota {
-    right
-        .clone()
-        .map(|pair| match pair.as_rule() {
-            Rule::Iota => PatternIota::from_name(
-                pattern_registry,
-                left.as_str(),
-                Some(ActionValue::Iota(parse_iota(
-                    pair.clone(),
+) -> ParseResult<PatternIota> {
+    let unknown_pattern =
+        |name: &str| custom_error(format!("unknown pattern '{name}'"), left.as_span());
+
+    match right.clone() {
+        Some(pair) => {
+            let pattern = match pair.as_rule() {
+                Rule::Iota => PatternIota::from_name(
                     pattern_registry,
-                    conf_entities,
-                    macros,
-                ))),
-                Location::Line(pair.line_col().0, pair.line_col().1),
-            ),
-            Rule::EntityType => PatternIota::from_name(
-                pattern_registry,
-                &format!("{}: {}", left.as_str(), right.unwrap().as_str()),
-                righter.map(|p| {
-                    ActionValue::Iota(parse_iota(p, pattern_registry, conf_entities, macros))
-                }),
-                Location::Line(pair.line_col().0, pair.line_col().1),
-            ),
-            Rule::BookkeeperValue => PatternIota::from_name(
-                pattern_registry,
-                left.as_str(),
-                Some(ActionValue::Bookkeeper(parse_bookkeeper(pair.clone()))),
-                Location::Line(pair.line_col().0, pair.line_col().1),
-            ),
-            _ => unreachable!(),
-        })
-        .unwrap_or(Ok(
+                    left.as_str(),
+                    Some(ActionValue::Iota(parse_iota(
+                        pair.clone(),
+                        pattern_registry,
+                        conf_entities,
+                        macros,
+                    )?)),
+                    Location::Line(pair.line_col().0, pair.line_col().1),
+                ),
+                Rule::EntityType => PatternIota::from_name(
+                    pattern_registry,
+                    &format!("{}: {}", left.as_str(), right.unwrap().as_str()),
+                    righter
+                        .map(|p| parse_iota(p, pattern_registry, conf_entities, macros))
+                        .transpose()?
+                        .map(ActionValue::Iota),
+                    Location::Line(pair.line_col().0, pair.line_col().1),
+                ),
+                Rule::BookkeeperValue => PatternIota::from_name(
+                    pattern_registry,
+                    left.as_str(),
+                    Some(ActionValue::Bookkeeper(parse_bookkeeper(pair.clone()))),
+                    Location::Line(pair.line_col().0, pair.line_col().1),
+                ),
+                _ => unreachable!(),
+            };
+            pattern.map_err(|_| unknown_pattern(left.as_str()))
+        }
+        None => {
             //check if macro
             macros
                 .get(left.as_str())
-                .map(|(pattern, _)| pattern.clone())
+                .map(|(pattern, _, _)| Ok(pattern.clone()))
                 .unwrap_or_else(
                     //check if pattern name
                     || {
@@ -272,11 +602,11 @@ fn parse_action_iota(
                             None,
                             Location::Line(left.line_col().0, left.line_col().1),
                         )
-                        .unwrap()
+                        .map_err(|_| unknown_pattern(left.as_str()))
                     },
-                ),
-        ))
-        .unwrap()
+                )
+        }
+    }
 }
 
 fn parse_var(pair: Pair<'_, Rule>) -> AstNode {
@@ -287,14 +617,170 @@ fn parse_var(pair: Pair<'_, Rule>) -> AstNode {
     }
 }
 
+fn parse_macro_call(
+    pair: Pair<'_, Rule>,
+    pattern_registry: &PatternRegistry,
+    conf_entities: &mut HashMap<String, Entity>,
+    macros: &Macros,
+) -> ParseResult<AstNode> {
+    let location = Location::Line(pair.line_col().0, pair.line_col().1);
+    let mut inner = pair.into_inner();
+    let name_pair = inner.next().unwrap();
+    let name = name_pair.as_str().to_string();
+
+    let args = collect_results(inner.map(|arg| {
+        let arg = arg.into_inner().next().unwrap();
+        match arg.as_rule() {
+            Rule::Term => {
+                construct_ast_node(arg, pattern_registry, conf_entities, macros).map(Option::unwrap)
+            }
+            Rule::Var => Ok(parse_var(arg)),
+            //iotas are pushed to the stack wherever the parameter is used
+            Rule::Iota => Ok(AstNode::Op {
+                location: Location::Line(arg.line_col().0, arg.line_col().1),
+                name: OpName::SmartEmbed,
+                arg: Some(OpValue::Iota(parse_iota(
+                    arg,
+                    pattern_registry,
+                    conf_entities,
+                    macros,
+                )?)),
+            }),
+            _ => unreachable!(),
+        }
+    }));
+    let name_error = if macros.contains_key(&name) {
+        Ok(())
+    } else {
+        Err(custom_error(
+            format!("unknown macro '{name}'"),
+            name_pair.as_span(),
+        ))
+    };
+    let (args, _) = (args, name_error).combine()?;
+
+    Ok(AstNode::MacroCall {
+        location,
+        name,
+        args,
+    })
+}
+
+//substitutes the arguments of a macro call into the body of the macro
+pub fn expand_macro(
+    params: &[String],
+    body: &AstNode,
+    args: &[AstNode],
+) -> Result<Vec<AstNode>, Mishap> {
+    if params.len() != args.len() {
+        return Err(Mishap::MacroWrongArgCount {
+            expected: params.len(),
+            received: args.len(),
+        });
+    }
+
+    let AstNode::Block { nodes, .. } = body else {
+        unreachable!()
+    };
+    let params: HashMap<&String, &AstNode> = params.iter().zip(args).collect();
+
+    nodes
+        .iter()
+        .map(|node| substitute_params(node, &params))
+        .collect()
+}
+
+fn substitute_params(
+    node: &AstNode,
+    params: &HashMap<&String, &AstNode>,
+) -> Result<AstNode, Mishap> {
+    let substitute_box = |node: &AstNode| substitute_params(node, params).map(Box::new);
+
+    Ok(match node {
+        AstNode::Op {
+            name: OpName::Push,
+            arg: Some(OpValue::Var(var)),
+            ..
+        } => params.get(var).map_or(node.clone(), |arg| (*arg).clone()),
+
+        //Store and Copy need somewhere to put the iota, so their argument has to be a variable
+        AstNode::Op {
+            location,
+            name,
+            arg: Some(OpValue::Var(var)),
+        } => match params.get(var) {
+            Some(AstNode::Op {
+                name: OpName::Push,
+                arg: Some(OpValue::Var(arg_var)),
+                ..
+            }) => AstNode::Op {
+                location: *location,
+                name: name.clone(),
+                arg: Some(OpValue::Var(arg_var.clone())),
+            },
+            Some(_) => Err(Mishap::MacroExpectedVar { param: var.clone() })?,
+            None => node.clone(),
+        },
+
+        AstNode::Program(nodes) => AstNode::Program(
+            nodes
+                .iter()
+                .map(|node| substitute_params(node, params))
+                .collect::<Result<_, _>>()?,
+        ),
+        AstNode::Block { external, nodes } => AstNode::Block {
+            external: *external,
+            nodes: nodes
+                .iter()
+                .map(|node| substitute_params(node, params))
+                .collect::<Result<_, _>>()?,
+        },
+        AstNode::MacroCall {
+            location,
+            name,
+            args,
+        } => AstNode::MacroCall {
+            location: *location,
+            name: name.clone(),
+            args: args
+                .iter()
+                .map(|node| substitute_params(node, params))
+                .collect::<Result<_, _>>()?,
+        },
+        AstNode::IfBlock {
+            location,
+            condition,
+            succeed,
+            fail,
+        } => AstNode::IfBlock {
+            location: *location,
+            condition: substitute_box(condition)?,
+            succeed: substitute_box(succeed)?,
+            fail: fail.as_deref().map(substitute_box).transpose()?,
+        },
+        AstNode::WhileBlock {
+            do_while,
+            location,
+            condition,
+            block,
+        } => AstNode::WhileBlock {
+            do_while: *do_while,
+            location: *location,
+            condition: substitute_box(condition)?,
+            block: substitute_box(block)?,
+        },
+        AstNode::Action { .. } | AstNode::Op { .. } => node.clone(),
+    })
+}
+
 fn parse_embed(
     pair: Pair<'_, Rule>,
     pattern_registry: &PatternRegistry,
     conf_entities: &mut HashMap<String, Entity>,
     macros: &Macros,
-) -> AstNode {
+) -> ParseResult<AstNode> {
     let inner_pair = pair.clone().into_inner().next().unwrap();
-    AstNode::Op {
+    Ok(AstNode::Op {
         name: {
             match inner_pair.as_rule() {
                 Rule::DirectEmbed => OpName::Embed,
@@ -304,12 +790,14 @@ fn parse_embed(
                 _ => unreachable!(),
             }
         },
-        arg: (inner_pair
+        arg: inner_pair
             .into_inner()
             .next()
-            .map(|iota| OpValue::Iota(parse_iota(iota, pattern_registry, conf_entities, macros)))),
+            .map(|iota| parse_iota(iota, pattern_registry, conf_entities, macros))
+            .transpose()?
+            .map(OpValue::Iota),
         location: Location::Line(pair.line_col().0, pair.line_col().1),
-    }
+    })
 }
 
 fn parse_if_block(
@@ -317,62 +805,45 @@ fn parse_if_block(
     pattern_registry: &PatternRegistry,
     conf_entities: &mut HashMap<String, Entity>,
     macros: &Macros,
-) -> AstNode {
+) -> ParseResult<AstNode> {
     fn parse_inner(
         line: (usize, usize),
         mut inner: Pairs<'_, Rule>,
         pattern_registry: &PatternRegistry,
         conf_entities: &mut HashMap<String, Entity>,
         macros: &Macros,
-    ) -> AstNode {
-        AstNode::IfBlock {
-            condition: {
-                let mut condition = inner.next().unwrap().into_inner();
-                Box::new(
-                    construct_ast_node(
-                        condition.next().unwrap(),
-                        pattern_registry,
-                        conf_entities,
-                        macros,
-                    )
-                    .unwrap(),
-                )
-            },
-            succeed: {
-                let mut succeed = inner.next().unwrap().into_inner();
-                Box::new(
-                    construct_ast_node(
-                        succeed.next().unwrap(),
-                        pattern_registry,
-                        conf_entities,
-                        macros,
-                    )
-                    .unwrap(),
-                )
-            },
-            fail: {
-                inner.clone().next().map(|branch| match branch.as_rule() {
-                    Rule::Else => Box::new(
-                        construct_ast_node(
-                            branch.into_inner().next().unwrap(),
-                            pattern_registry,
-                            conf_entities,
-                            macros,
-                        )
-                        .unwrap(),
-                    ),
-                    Rule::ElseIf => Box::new(parse_inner(
-                        line,
-                        inner,
-                        pattern_registry,
-                        conf_entities,
-                        macros,
-                    )),
-                    _ => unreachable!(),
-                })
-            },
+    ) -> ParseResult<AstNode> {
+        let condition = parse_branch(
+            inner.next().unwrap(),
+            pattern_registry,
+            conf_entities,
+            macros,
+        );
+        let succeed = parse_branch(
+            inner.next().unwrap(),
+            pattern_registry,
+            conf_entities,
+            macros,
+        );
+        let fail = inner
+            .clone()
+            .next()
+            .map(|branch| match branch.as_rule() {
+                Rule::Else => parse_branch(branch, pattern_registry, conf_entities, macros),
+                Rule::ElseIf => {
+                    parse_inner(line, inner, pattern_registry, conf_entities, macros).map(Box::new)
+                }
+                _ => unreachable!(),
+            })
+            .transpose();
+        let ((condition, succeed), fail) = ((condition, succeed).combine(), fail).combine()?;
+
+        Ok(AstNode::IfBlock {
+            condition,
+            succeed,
+            fail,
             location: Location::Line(line.0, line.1),
-        }
+        })
     }
     parse_inner(
         pair.line_col(),
@@ -389,52 +860,46 @@ fn parse_while_block(
     conf_entities: &mut HashMap<String, Entity>,
     macros: &Macros,
     do_while: bool,
-) -> AstNode {
-    fn parse_inner(
-        line: (usize, usize),
-        mut inner: Pairs<'_, Rule>,
-        pattern_registry: &PatternRegistry,
-        conf_entities: &mut HashMap<String, Entity>,
-        macros: &Macros,
-        do_while: bool,
-    ) -> AstNode {
-        AstNode::WhileBlock {
-            do_while,
-            condition: {
-                let mut condition = inner.next().unwrap().into_inner();
-                Box::new(
-                    construct_ast_node(
-                        condition.next().unwrap(),
-                        pattern_registry,
-                        conf_entities,
-                        macros,
-                    )
-                    .unwrap(),
-                )
-            },
-            block: {
-                let mut block = inner.next().unwrap().into_inner();
-                Box::new(
-                    construct_ast_node(
-                        block.next().unwrap(),
-                        pattern_registry,
-                        conf_entities,
-                        macros,
-                    )
-                    .unwrap(),
-                )
-            },
-            location: Location::Line(line.0, line.1),
-        }
-    }
-    parse_inner(
-        pair.line_col(),
-        pair.into_inner(),
+) -> ParseResult<AstNode> {
+    let line = pair.line_col();
+    let mut inner = pair.into_inner();
+
+    let condition = parse_branch(
+        inner.next().unwrap(),
+        pattern_registry,
+        conf_entities,
+        macros,
+    );
+    let block = parse_branch(
+        inner.next().unwrap(),
         pattern_registry,
         conf_entities,
         macros,
+    );
+    let (condition, block) = (condition, block).combine()?;
+
+    Ok(AstNode::WhileBlock {
         do_while,
+        condition,
+        block,
+        location: Location::Line(line.0, line.1),
+    })
+}
+
+//the block inside an if, then, else, while or do
+fn parse_branch(
+    pair: Pair<'_, Rule>,
+    pattern_registry: &PatternRegistry,
+    conf_entities: &mut HashMap<String, Entity>,
+    macros: &Macros,
+) -> ParseResult<Box<AstNode>> {
+    construct_ast_node(
+        pair.into_inner().next().unwrap(),
+        pattern_registry,
+        conf_entities,
+        macros,
     )
+    .map(|node| Box::new(node.unwrap()))
 }
 
 pub fn parse_iota(
@@ -442,16 +907,16 @@ pub fn parse_iota(
     pattern_registry: &PatternRegistry,
     conf_entities: &mut HashMap<String, Entity>,
     macros: &Macros,
-) -> Rc<dyn Iota> {
+) -> ParseResult<Rc<dyn Iota>> {
     let inner_pair = pair.into_inner().next().unwrap();
-    match inner_pair.as_rule() {
+    Ok(match inner_pair.as_rule() {
         Rule::Number => Rc::new(inner_pair.as_str().parse::<NumberIota>().unwrap()),
         Rule::Pattern => Rc::new(parse_pattern(
             inner_pair.into_inner().next().unwrap(),
             pattern_registry,
             conf_entities,
             macros,
-        )),
+        )?),
         Rule::Vector => {
             let mut inner = inner_pair.into_inner();
             Rc::new(matrix![
@@ -483,30 +948,50 @@ pub fn parse_iota(
         Rule::List => {
             let inner = inner_pair.into_inner();
             Rc::new(
-                inner
-                    .map(|x| parse_iota(x, pattern_registry, conf_entities, macros))
-                    .collect::<ListIota>(),
+                collect_results(
+                    inner.map(|x| parse_iota(x, pattern_registry, conf_entities, macros)),
+                )?
+                .into_iter()
+                .collect::<ListIota>(),
             )
         }
         Rule::String => {
-            let string = snailquote::unescape(&inner_pair.as_str())
-                .unwrap()
-                .to_string();
+            let string = snailquote::unescape(inner_pair.as_str()).map_err(|err| {
+                custom_error(format!("invalid string: {err}"), inner_pair.as_span())
+            })?;
             Rc::new(string)
         }
         Rule::Matrix => {
+            let span = inner_pair.as_span();
             let mut inner = inner_pair.into_inner();
             let nrows = inner.next().unwrap().as_str().parse::<usize>().unwrap();
             let ncols = inner.next().unwrap().as_str().parse::<usize>().unwrap();
-            let data = inner
-                .map(|x| x.as_str().parse::<NumberIota>().unwrap())
-                .collect::<Vec<_>>();
+            //the last value is parsed as any iota, so it might not be a number
+            let data = collect_results(inner.map(|x| {
+                x.as_str().parse::<NumberIota>().map_err(|_| {
+                    custom_error(
+                        format!("expected a number in matrix but got {}", x.as_str()),
+                        x.as_span(),
+                    )
+                })
+            }))?;
+
+            if data.len() != nrows * ncols {
+                Err(custom_error(
+                    format!(
+                        "a {nrows} by {ncols} matrix needs {} values but got {}",
+                        nrows * ncols,
+                        data.len()
+                    ),
+                    span,
+                ))?
+            }
 
             Rc::new(MatrixIota::from_vec(nrows, ncols, data))
         }
 
         _ => unreachable!(),
-    }
+    })
 }
 
 fn parse_pattern(
@@ -514,8 +999,8 @@ fn parse_pattern(
     pattern_registry: &PatternRegistry,
     conf_entities: &mut HashMap<String, Entity>,
     macros: &Macros,
-) -> PatternIota {
-    match pair.as_str() {
+) -> ParseResult<PatternIota> {
+    Ok(match pair.as_str() {
         "{" => PatternIota::from_name(
             pattern_registry,
             "open_paren",
@@ -542,16 +1027,21 @@ fn parse_pattern(
                     pattern_registry,
                     macros,
                     conf_entities,
+                )?
+            }
+            Rule::PatternRaw => {
+                let mut inner = pair.clone().into_inner();
+                let start_dir = inner.next().unwrap().as_str().parse().unwrap();
+                PatternIota::from_angles(
+                    pattern_registry,
+                    inner.next().unwrap().as_str(),
+                    start_dir,
+                    Location::Line(pair.line_col().0, pair.line_col().1),
                 )
             }
-            Rule::PatternRaw => PatternIota::from_sig(
-                pair.clone().into_inner().last().unwrap().as_str(),
-                None,
-                Location::Line(pair.line_col().0, pair.line_col().1),
-            ),
             _ => unreachable!("{:?}", pair.as_rule()),
         },
-    }
+    })
 }
 
 fn parse_bookkeeper(pair: Pair<'_, Rule>) -> String {
@@ -582,6 +1072,11 @@ pub enum AstNode {
         name: OpName,
         arg: Option<OpValue>,
     },
+    MacroCall {
+        location: Location,
+        name: String,
+        args: Vec<AstNode>,
+    },
     IfBlock {
         location: Location,
         condition: Box<AstNode>,
@@ -596,13 +1091,87 @@ pub enum AstNode {
     },
 }
 
+impl AstNode {
+    pub fn location(&self) -> Option<Location> {
+        match self {
+            AstNode::Program(_) | AstNode::Block { .. } => None,
+            AstNode::Action { location, .. }
+            | AstNode::Op { location, .. }
+            | AstNode::MacroCall { location, .. }
+            | AstNode::IfBlock { location, .. }
+            | AstNode::WhileBlock { location, .. } => Some(*location),
+        }
+    }
+
+    //locations in included files keep track of which file they came from
+    fn set_file(&mut self, file: usize) {
+        if file == 0 {
+            return;
+        }
+        match self {
+            AstNode::Program(nodes) | AstNode::Block { nodes, .. } => {
+                nodes.iter_mut().for_each(|node| node.set_file(file))
+            }
+            AstNode::Action { location, .. } | AstNode::Op { location, .. } => {
+                location.set_file(file)
+            }
+            AstNode::MacroCall { location, args, .. } => {
+                location.set_file(file);
+                args.iter_mut().for_each(|arg| arg.set_file(file))
+            }
+            AstNode::IfBlock {
+                location,
+                condition,
+                succeed,
+                fail,
+            } => {
+                location.set_file(file);
+                condition.set_file(file);
+                succeed.set_file(file);
+                if let Some(fail) = fail {
+                    fail.set_file(file)
+                }
+            }
+            AstNode::WhileBlock {
+                location,
+                condition,
+                block,
+                ..
+            } => {
+                location.set_file(file);
+                condition.set_file(file);
+                block.set_file(file);
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Location {
     Unknown,
     Line(usize, usize),
+    //line and column in an included source file
+    File(usize, usize, usize),
     List(usize),
 }
 
+impl Location {
+    //the source file index and line of a location in the source
+    pub fn file_line(&self) -> Option<(usize, usize)> {
+        match self {
+            Location::Line(line, _) => Some((0, *line)),
+            Location::File(file, line, _) => Some((*file, *line)),
+            Location::Unknown | Location::List(_) => None,
+        }
+    }
+
+    fn set_file(&mut self, file: usize) {
+        if let Location::Line(line, col) = *self {
+            *self = Location::File(file, line, col)
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum OpName {
     Init,
@@ -665,6 +1234,120 @@ impl PartialEq for ActionValue {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use pest::error::LineColLocation;
+
+    #[test]
+    fn macro_args_test() {
+        let registry = PatternRegistry::construct(&PatternRegistry::gen_default_great_sigs());
+        let (ast, macros, _) = parse(
+            "#define Save($v) (SOUTH_EAST ded) {\n    Store($v)\n}\nSave($x)",
+            "",
+            &registry,
+            &mut HashMap::new(),
+        )
+        .unwrap();
+
+        let AstNode::Program(nodes) = ast else {
+            unreachable!()
+        };
+        let AstNode::MacroCall { name, args, .. } = &nodes[0] else {
+            panic!("expected a macro call, got {:?}", nodes[0])
+        };
+        let (_, params, body) = &macros[name];
+        assert_eq!(params, &vec!["$v".to_string()]);
+
+        let expanded = expand_macro(params, body, args).unwrap();
+        assert!(matches!(
+            &expanded[0],
+            AstNode::Op { name: OpName::Store, arg: Some(OpValue::Var(var)), .. } if var == "$x"
+        ));
+        assert!(expand_macro(params, body, &[]).is_err());
+    }
+
+    #[test]
+    fn include_test() {
+        let dir = std::env::temp_dir().join("hexagon_include_test");
+        fs::create_dir_all(dir.join("lib")).unwrap();
+        fs::write(
+            dir.join("lib/macros.hexagon"),
+            "#define Twice (SOUTH_EAST ded) {\n    Gemini Decomposition\n}",
+        )
+        .unwrap();
+        fs::write(dir.join("a.hexagon"), "#include \"b.hexagon\"").unwrap();
+        fs::write(dir.join("b.hexagon"), "#include \"a.hexagon\"").unwrap();
+
+        let registry = PatternRegistry::construct(&PatternRegistry::gen_default_great_sigs());
+        let main_path = dir.join("main.hexagon").display().to_string();
+
+        let (_, macros, sources) = parse(
+            "#include \"lib/macros.hexagon\"\nTwice",
+            &main_path,
+            &registry,
+            &mut HashMap::new(),
+        )
+        .unwrap();
+        assert!(macros.contains_key("Twice"));
+        assert_eq!(sources.len(), 2);
+
+        let err = parse(
+            "#include \"a.hexagon\"",
+            &main_path,
+            &registry,
+            &mut HashMap::new(),
+        )
+        .unwrap_err();
+        assert!(err[0].to_string().contains("include cycle"));
+    }
+
+    #[test]
+    fn diamond_include_test() {
+        let dir = std::env::temp_dir().join("hexagon_diamond_include_test");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("d.hexagon"),
+            "#define Twice (SOUTH_EAST ded) {\n    Gemini Decomposition\n}",
+        )
+        .unwrap();
+        fs::write(dir.join("b.hexagon"), "#include \"d.hexagon\"\nTwice").unwrap();
+        fs::write(dir.join("c.hexagon"), "#include \"d.hexagon\"\nTwice").unwrap();
+
+        let registry = PatternRegistry::construct(&PatternRegistry::gen_default_great_sigs());
+        let main_path = dir.join("main.hexagon").display().to_string();
+
+        //c includes d after b already has, and still needs its macros
+        let (ast, macros, sources) = parse(
+            "#include \"b.hexagon\"\n#include \"c.hexagon\"",
+            &main_path,
+            &registry,
+            &mut HashMap::new(),
+        )
+        .unwrap();
+        assert!(macros.contains_key("Twice"));
+        assert_eq!(sources.len(), 4);
+
+        let AstNode::Program(nodes) = ast else {
+            unreachable!()
+        };
+        assert_eq!(nodes.len(), 2);
+    }
+
+    #[test]
+    fn error_recovery_test() {
+        let registry = PatternRegistry::construct(&PatternRegistry::gen_default_great_sigs());
+        let source = "Numerical Reflection: 1\nNumerical Reflection: ]\nNot A Pattern\n{\n    Undefined Macro()\n    Embed: [Bad Pattern, 1]\n}\nNumerical Reflection: [(2, 2) | 1, 2, 3]\nMind's Reflection";
+
+        let errors = parse(source, "errors.hexagon", &registry, &mut HashMap::new()).unwrap_err();
+        let lines = errors
+            .iter()
+            .map(|err| match err.line_col {
+                LineColLocation::Pos((line, _)) | LineColLocation::Span((line, _), _) => line,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(lines, vec![2, 3, 5, 6, 8]);
+        assert!(errors
+            .iter()
+            .all(|err| err.path() == Some("errors.hexagon")));
+    }
 
     // #[test]
     // fn hex() {
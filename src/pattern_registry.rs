@@ -2,10 +2,10 @@ use std::collections::HashMap;
 use std::f64::consts::{E, PI, TAU};
 use std::rc::Rc;
 
-use hexnumgen::{generate_number_pattern_beam, Bounds};
 use im::{vector, Vector};
 
 use crate::interpreter::state::EntityType;
+use crate::parse_config::Config;
 use crate::iota::hex_casting::entity::EntityIota;
 use crate::iota::hex_casting::null::NullIota;
 use crate::iota::hex_casting::number::NumberIota;
@@ -18,7 +18,12 @@ use crate::patterns::hex_casting::{
     eval, lists, math, read_write, sentinel, special, stack, swizzle,
 };
 use crate::patterns::more_iotas::{matrix, string};
-use crate::patterns::{constructors, hexal, Pattern};
+use crate::patterns::{constructors, costs, hexal, MediaCost, Pattern};
+
+use self::data::{default_pattern_data, PatternData};
+
+pub mod data;
+pub mod numbers;
 
 pub type PatternRegistry = Vec<Pattern>;
 
@@ -26,8 +31,14 @@ pub trait PatternRegistryExt {
     fn gen_default_great_sigs() -> HashMap<String, String>;
 
     fn construct(great_sigs: &HashMap<String, String>) -> PatternRegistry;
+    fn construct_from_data(
+        great_sigs: &HashMap<String, String>,
+        data: &[PatternData],
+    ) -> PatternRegistry;
+    fn from_config(config: &Config) -> PatternRegistry;
     fn find(&self, query: &str, value: &Option<ActionValue>) -> Option<Pattern>;
     fn find_all(&self, query: &str, value: &Option<ActionValue>) -> Vector<Pattern>;
+    fn decode_value(&self, signature: &str) -> Option<ActionValue>;
 }
 
 impl PatternRegistryExt for PatternRegistry {
@@ -53,393 +64,26 @@ impl PatternRegistryExt for PatternRegistry {
         hashmap
     }
 
-    #[rustfmt::skip]
     fn construct(great_sigs: &HashMap<String, String>) -> PatternRegistry {
-        let registry: PatternRegistry = vec![
-            //special patterns
-            Pattern::new_with_val("Consideration", "escape", "qqqaw", Box::new(special::escape)),
-            Pattern::new("Introspection", "open_paren", "qqq", Box::new(special::introspect)),
-            Pattern::new("Retrospection", "close_paren", "eee", Box::new(special::retrospect)),
-            Pattern::new("Hermes' Gambit", "eval", "deaqq", Box::new(eval::eval)),
-            Pattern::new("Thoth's Gambit", "for_each", "dadad", Box::new(eval::for_each)),
-            Pattern::new("Charon's Gambit", "halt", "aqdee", Box::new(eval::halt)),
-            Pattern::new("Reveal", "print", "de", Box::new(special::print)),
-            Pattern::new("Iris' Gambit", "eval/cc", "qwaqde", Box::new(eval::eval_cc)),
-
-
-
-            //math
-            Pattern::new("Additive Distillation", "add", "waaw", Box::new(math::add)),
-            Pattern::new("Subtractive Distillation", "sub", "wddw", Box::new(math::subtract)),
-            Pattern::new("Multiplicative Distillation", "mul_dot", "waqaw", Box::new(math::mul_dot)),
-            Pattern::new("Division Distillation", "div_cross", "wdedw", Box::new(math::div_cross)),
-            Pattern::new("Length Purification", "abs_len", "wqaqw", Box::new(math::abs_len)),
-            Pattern::new("Power Distillation", "pow_proj", "wedew", Box::new(math::pow_proj)),
-            Pattern::new("Floor Purification", "floor", "ewq", Box::new(math::floor)),
-            Pattern::new("Ceiling Purification", "ceil", "qwe", Box::new(math::ceil)),
-            Pattern::new("Vector Exaltation", "construct_vec", "eqqqqq", Box::new(math::construct_vec)),
-            Pattern::new("Vector Disintegration", "deconstruct_vec", "qeeeee", Box::new(math::deconstruct_vec)),
-            Pattern::new("Axial Purification", "coerce_axial", "qqqqqaww", Box::new(math::coerce_axial)),
-            Pattern::new("Conjunction Distillation", "and", "wdw", Box::new(math::and)),
-            Pattern::new("Disjunction Distillation", "or", "waw", Box::new(math::or)),
-            Pattern::new("Exclusion Distillation", "xor", "dwa", Box::new(math::xor)),
-            Pattern::new("Maximus Distillation", "greater", "e", Box::new(math::greater)),
-            Pattern::new("Minimus Distillation", "less", "q", Box::new(math::less)),
-            Pattern::new("Maximus Distillation II", "greater_eq", "ee", Box::new(math::greater_eq)),
-            Pattern::new("Minimus Distillation II", "less_eq", "qq", Box::new(math::less_eq)),
-            Pattern::new("Equality Distillation", "equals", "ad", Box::new(math::equals)),
-            Pattern::new("Inequality Distillation", "not_equals", "da", Box::new(math::not_equals)),
-            Pattern::new("Negation Purification", "not", "dw", Box::new(math::not)),
-            Pattern::new("Augur's Purification", "bool_coerce", "aw", Box::new(math::bool_coerce)),
-            Pattern::new("Sine Purification", "sin", "qqqqqaa", Box::new(math::sin)),
-            Pattern::new("Cosine Purification", "cos", "qqqqqad", Box::new(math::cos)),
-            Pattern::new("Tangent Purification", "tan", "wqqqqqadq", Box::new(math::tan)),
-            Pattern::new("Inverse Sine Purification", "arcsin", "ddeeeee", Box::new(math::arcsin)),
-            Pattern::new("Inverse Cosine Purification", "arccos", "adeeeee", Box::new(math::arccos)),
-            Pattern::new("Inverse Tangent Purification", "arctan", "eadeeeeew", Box::new(math::arctan)),
-            Pattern::new("Logarithmic Distillation", "logarithm", "eqaqe", Box::new(math::logarithm)),
-            Pattern::new("Modulus Distillation", "modulo", "addwaad", Box::new(math::modulo)),
-            Pattern::new("Intersection Distillation", "and_bit", "wdweaqa", Box::new(math::and_bit)),
-            Pattern::new("Unifying Distillation", "or_bit", "waweaqa", Box::new(math::or_bit)),
-            Pattern::new("Exclusionary Distillation", "xor_bit", "dwaeaqa", Box::new(math::xor_bit)),
-            Pattern::new("Inversion Purification", "not_bit", "dweaqa", Box::new(math::not_bit)),
-            Pattern::new("Uniqueness Purification", "to_set", "aweaqa", Box::new(math::to_set)),
-            Pattern::new("Augur's Exaltation", "if", "awdd",  Box::new(math::bool_if)),
-            Pattern::new("Entropy Reflection", "random", "eqqq",  Box::new(math::random)),
-
-            //Hexal - math
-            Pattern::new("Factorial Purification", "factorial", "wawdedwaw",  Box::new(hexal::math::factorial)),
-            Pattern::new("Running Sum Purification", "running/sum", "aea",  Box::new(hexal::math::running_sum)),
-            Pattern::new("Running Product Purification", "running/mul", "qaawaaq",  Box::new(hexal::math::running_product)),
-
-
-            //lists
-            Pattern::new("Integration Distillation", "append", "edqde", Box::new(lists::append)),
-            Pattern::new("Combination Distillation", "concat", "qaeaq", Box::new(lists::concat)),
-            Pattern::new("Selection Distillation", "index", "deeed", Box::new(lists::index)),
-            Pattern::new("Abacus Purification", "list_size", "aqaeaq", Box::new(lists::list_size)),
-            Pattern::new("Single's Purification", "singleton", "adeeed", Box::new(lists::singleton)),
-            Pattern::new("Retrograde Purification", "reverse_list", "qqqaede", Box::new(lists::reverse_list)),
-            Pattern::new("Flock's Gambit", "last_n_list", "ewdqdwe", Box::new(lists::last_n_list)),
-            Pattern::new("Flock's Disintegration", "splat", "qwaeawq", Box::new(lists::splat)),
-            Pattern::new("Locator's Distillation", "index_of", "dedqde", Box::new(lists::index_of)),
-            Pattern::new("Excisor's Distillation", "list_remove", "edqdewaqa", Box::new(lists::list_remove)),
-            Pattern::new("Selection Exaltation", "slice", "qaeaqwded", Box::new(lists::slice)),
-            Pattern::new("Surgeon's Exaltation", "modify_in_place", "wqaeaqw", Box::new(lists::modify_in_place)),
-            Pattern::new("Speaker's Distillation", "construct", "ddewedd", Box::new(lists::construct)),
-            Pattern::new("Speaker's Decomposition", "deconstruct", "aaqwqaa", Box::new(lists::deconstruct)),
-
-            //stack manipulation
-            Pattern::new("Gemini Decomposition", "duplicate", "aadaa", Box::new(stack::duplicate)),
-            Pattern::new("Dioscuri Gambit", "two_dup", "aadadaaw", Box::new(stack::two_dup)),
-            Pattern::new("Gemini Gambit", "duplicate_n", "aadaadaa", Box::new(stack::duplicate_n)),
-            Pattern::new("Jester's Gambit", "swap", "aawdd", Box::new(stack::swap)),
-            Pattern::new("Rotation Gambit", "rotate", "aaeaa", Box::new(stack::rotate)),
-            Pattern::new("Rotation Gambit II", "rotate_reverse", "ddqdd", Box::new(stack::rotate_reverse)),
-            Pattern::new("Prospector's Gambit", "over", "aaedd", Box::new(stack::over)),
-            Pattern::new("Undertaker's Gambit", "tuck", "ddqaa", Box::new(stack::tuck)),
-            Pattern::new("Flock's Reflection", "stack_len", "qwaeawqaeaqa", Box::new(stack::stack_len)),
-            Pattern::new("Fisherman's Gambit", "fisherman", "ddad", Box::new(stack::fisherman)),
-            Pattern::new("Fisherman's Gambit II", "fisherman/copy", "aada", Box::new(stack::fisherman_copy)),
-            Pattern::new("Swindler's Gambit", "swizzle", "qaawdde", Box::new(swizzle::swizzle)),
-
-
-            //read/write
-            Pattern::new("Muninn's Reflection", "read/local",  "qeewdweddw", Box::new(read_write::read_local)),
-            Pattern::new("Huginn's Gambit", "write/local",  "eqqwawqaaw", Box::new(read_write::write_local)),
-            Pattern::new("Erase Item", "erase", "qdqawwaww", Box::new(read_write::erase)),
-            Pattern::new("Craft Trinket", "craft/trinket", "wwaqqqqqeaqeaeqqqeaeq", Box::new(read_write::craft_trinket)),
-            Pattern::new("Craft Cypher", "craft/cypher", "waqqqqq", Box::new(read_write::craft_cypher)),
-            Pattern::new("Craft Artifact", "craft/artifact", "wwaqqqqqeawqwqwqwqwqwwqqeadaeqqeqqeadaeqq", Box::new(read_write::craft_artifact)),
-            Pattern::new("Scribe's Reflection", "read",  "aqqqqq", Box::new(read_write::read)),
-            Pattern::new("Scribe's Gambit", "write",  "deeeee", Box::new(read_write::write)),
-            Pattern::new("Auditor's Reflection", "readable",  "aqqqqqe", Box::new(read_write::readable)),
-            Pattern::new("Assessor's Reflection", "writable",  "deeeeeq", Box::new(read_write::writable)),
-            Pattern::new("Akasha's Distillation", "akashic/read",  "qqqwqqqqqaq", Box::new(read_write::akashic_read)),
-            Pattern::new("Akasha's Gambit", "akashic/write",  "eeeweeeeede", Box::new(read_write::akashic_write)),
-            Pattern::new("Chronicler's Purification", "read/entity",  "wawqwqwqwqwqw", Box::new(read_write::read_entity)),
-            Pattern::new("Chronicler's Gambit", "write/entity",  "wdwewewewewew", Box::new(read_write::write_entity)),
-            Pattern::new("Auditor's Purification", "readable/entity",  "wawqwqwqwqwqwew", Box::new(read_write::readable_entity)),
-            Pattern::new("Assessor's Purification", "writable/entity",  "wdwewewewewewqw", Box::new(read_write::writeable_entity)),
-
-
-            //sentinel
-            Pattern::new( "sentinel/create", "Summon Sentinel", "waeawae", Box::new(sentinel::create)),
-            Pattern::new( "sentinel/destroy", "Banish Sentinel","qdwdqdw", Box::new(sentinel::destroy)),
-            Pattern::new( "sentinel/get_pos", "Locate Sentinel", "waeawaede", Box::new(sentinel::get_pos)),
-            Pattern::new( "sentinel/wayfind", "Wayfind Sentinel","waeawaedwa", Box::new(sentinel::wayfind)),
-
-
-            //consts
-            Pattern::new("Mind's Reflection", "get_caster", "qaq", 
-                constructors::push_const(Rc::new(EntityIota {name: Rc::from("Caster"), uuid: "[I;0,0,0,0]".to_string()}))),
-            Pattern::new("Vacant Reflection", "empty_list", "qqaeaae", constructors::push_const(Rc::new(vector![]))),
-            Pattern::new("Vector Reflection +X", "const/vec/px", "qqqqqea", constructors::push_const(Rc::new(VectorIota::new(1.0, 0.0, 0.0)))),
-            Pattern::new("Vector Reflection +Y", "const/vec/py", "qqqqqew", constructors::push_const(Rc::new(VectorIota::new(0.0, 1.0, 0.0)))),
-            Pattern::new("Vector Reflection +Z", "const/vec/pz", "qqqqqed", constructors::push_const(Rc::new(VectorIota::new(0.0, 0.0, 1.0)))),
-            Pattern::new("Vector Reflection -X", "const/vec/nx", "eeeeeqa", constructors::push_const(Rc::new(VectorIota::new(-1.0, 0.0, 0.0)))),
-            Pattern::new("Vector Reflection -Y", "const/vec/ny", "eeeeeqw", constructors::push_const(Rc::new(VectorIota::new(0.0, -1.0, 0.0)))),
-            Pattern::new("Vector Reflection -Z", "const/vec/nz", "eeeeeqd", constructors::push_const(Rc::new(VectorIota::new(0.0, 0.0, -1.0)))),
-            Pattern::new("Vector Reflection Zero", "const/vec/0", "qqqqq", constructors::push_const(Rc::new(VectorIota::new(0.0, 0.0, 0.0)))),
-            Pattern::new("Arc's Reflection", "const/double/pi", "qdwdq", constructors::push_const(Rc::new(PI))),
-            Pattern::new("Circle's Reflection", "const/double/tau", "eawae", constructors::push_const(Rc::new(TAU))),
-            Pattern::new("Euler's Reflection", "const/double/e", "aaq", constructors::push_const(Rc::new(E))),
-            Pattern::new("Nullary Reflection", "const/null", "d", constructors::push_const(Rc::new(NullIota))),
-            Pattern::new("True Reflection", "const/true", "aqae",constructors::push_const(Rc::new(true))),
-            Pattern::new("False Reflection", "const/false", "dedq",constructors::push_const(Rc::new(false))),
-            Pattern::new("Blank Reflection", "string/empty", "awdwa",constructors::push_const(Rc::new(String::new()))),
-            Pattern::new("Spacing Reflection", "string/space", "awdwaaww",constructors::push_const(Rc::new(" ".to_string()))),
-            Pattern::new("Comma Reflection", "string/comma", "qa",constructors::push_const(Rc::new(",".to_string()))),
-            Pattern::new("Breaking Reflection", "string/newline", "waawaw",constructors::push_const(Rc::new("\n".to_string()))),
-            // Pattern::new("", "", "",constructors::push_const(Rc::new())),
-
-
-
-            //spells
-            Pattern::new("Alter Gravity", "interop/gravity/set", "wdwdwaaqw", constructors::spell_2::<EntityIota, VectorIota>()),
-            Pattern::new("Alter Scale", "interop/pehkui/set", "ddwdwwdwwd", constructors::spell_2::<EntityIota, VectorIota>()),
-            Pattern::new("Explosion", "explode", "aawaawaa", constructors::spell_2::<VectorIota, NumberIota>()),
-            Pattern::new("Fireball", "explode/fire", "ddwddwdd", constructors::spell_2::<VectorIota, NumberIota>()),
-            Pattern::new("Impulse", "add_motion", "awqqqwaqw", constructors::spell_2::<EntityIota, VectorIota>()),
-            Pattern::new("Blink", "blink", "awqqqwaq", constructors::spell_2::<EntityIota, NumberIota>()),
-            Pattern::new("Break Block", "break_block", "qaqqqqq", constructors::spell_1::<VectorIota>()),
-            Pattern::new("Place Block", "place_block", "eeeeede", constructors::spell_1::<VectorIota>()),
-            Pattern::new("Internalize Pigment", "colorize", "awddwqawqwawq", Box::new(special::no_action)),
-            Pattern::new("Create Water", "create_water", "aqawqadaq", constructors::spell_1::<VectorIota>()),
-            Pattern::new("Destroy Liquid", "destroy_water", "dedwedade", constructors::spell_1::<VectorIota>()),
-            Pattern::new("Ignite Block", "ignite", "aaqawawa", constructors::spell_1::<VectorIota>()),
-            Pattern::new("Extinguish Area", "extinguish", "ddedwdwd", constructors::spell_1::<VectorIota>()),
-            Pattern::new("Conjure Block", "conjure_block", "qqa", constructors::spell_1::<VectorIota>()),
-            Pattern::new("Conjure Light", "conjure_light", "qqd", constructors::spell_1::<VectorIota>()),
-            Pattern::new("Overgrow", "bonemeal", "wqaqwawqaqw", constructors::spell_1::<VectorIota>()),
-            Pattern::new("Recharge Item", "recharge", "qqqqqwaeaeaeaeaea", constructors::spell_1::<EntityIota>()),
-            Pattern::new("Edify Sapling", "edify", "wqaqwd", constructors::spell_1::<VectorIota>()),
-            Pattern::new("Make Note", "beep", "adaa", Box::new(special::beep)),
-            Pattern::new("White Sun's Nadir", "potion/weakness", "qqqqqaqwawaw", constructors::spell_3::<EntityIota, NumberIota, NumberIota>()),
-            Pattern::new("Blue Sun's Nadir", "potion/levitation", "qqqqqawwawawd", constructors::spell_2::<EntityIota, VectorIota>()),
-            Pattern::new("Black Sun's Nadir", "potion/wither", "qqqqqaewawawe", constructors::spell_3::<EntityIota, NumberIota, NumberIota>()),
-            Pattern::new("Red Sun's Nadir", "potion/poison", "qqqqqadwawaww", constructors::spell_3::<EntityIota, NumberIota, NumberIota>()),
-            Pattern::new("Green Sun's Nadir", "potion/slowness", "qqqqqadwawaw", constructors::spell_3::<EntityIota, NumberIota, NumberIota>()),
-            Pattern::new("Write", "string/block/set", "dwewdweq", Box::new(string::write)),
-            Pattern::new("Sifter's Gambit", "string/chat/prefix/set", "qwaqa", Box::new(string::set_prefix)),
-            Pattern::new("Particles", "particles", "eqqqqa", Box::new(hexal::spells::particles)),
-            Pattern::new("Falling Block", "falling_block", "wqwawqwqwqwqwqw", constructors::spell_1::<VectorIota>()),
-            Pattern::new("Summon Cyclic Wisp", "wisp/summon/ticking", "aqaweewaqawee", Box::new(hexal::spells::summon_wisp_ticking)),
-            Pattern::new("Pathfinder's Gambit", "wisp/move/target/set", "awqwawqaw", constructors::spell_1::<VectorIota>()),
-            Pattern::new("Haste", "wisp/move/speed/set", "aeawqqqae", constructors::spell_1::<NumberIota>()),
-            //TODO: make Delay wisp Delay wisps
-            Pattern::new("Delay Wisp", "wisp/trigger/tick", "aqawded", constructors::spell_1::<NumberIota>()),
-
-
-            //great spells
-            Pattern::new("Craft Phial", "craft/battery", great_sigs.get("craft/battery").unwrap(), 
-                constructors::spell_1::<EntityIota>()),
-
-            Pattern::new("White Sun's Zenith", "potion/regeneration", great_sigs.get("potion/regeneration").unwrap(), 
-                constructors::spell_3::<EntityIota, NumberIota, NumberIota>()),
-
-            Pattern::new("Blue Sun's Zenith", "potion/night_vision", great_sigs.get("potion/night_vision").unwrap(), 
-                constructors::spell_2::<EntityIota, VectorIota>()),
-
-            Pattern::new("Black Sun's Zenith", "potion/absorption", great_sigs.get("potion/absorption").unwrap(), 
-                constructors::spell_3::<EntityIota, NumberIota, NumberIota>()),
-
-            Pattern::new("Red Sun's Zenith", "potion/haste", great_sigs.get("potion/haste").unwrap(), 
-                constructors::spell_3::<EntityIota, NumberIota, NumberIota>()),
-
-            Pattern::new("Green Sun's Zenith", "potion/strength", great_sigs.get("potion/strength").unwrap(), 
-                constructors::spell_3::<EntityIota, NumberIota, NumberIota>()),
-
-            Pattern::new("Summon Lightning", "lightning", great_sigs.get("lightning").unwrap(), 
-                constructors::spell_1::<VectorIota>()),
-
-            Pattern::new("Flight", "flight", great_sigs.get("flight").unwrap(), 
-                constructors::spell_3::<EntityIota, NumberIota, NumberIota>()),
-
-            Pattern::new("Create Lava", "create_lava", great_sigs.get("create_lava").unwrap(), 
-                constructors::spell_1::<VectorIota>()),
-
-            Pattern::new("Greater Teleport", "teleport", great_sigs.get("teleport").unwrap(), 
-                constructors::spell_2::<EntityIota, VectorIota>()),
-
-            Pattern::new("Summon Greater Sentinel", "sentinel/create/great", great_sigs.get("sentinel/create/great").unwrap(), 
-                constructors::spell_1::<VectorIota>()),
-
-            Pattern::new("Dispel Rain", "dispel_rain", great_sigs.get("dispel_rain").unwrap(), Box::new(special::no_action)),
-
-            Pattern::new("Summon Rain", "summon_rain", great_sigs.get("summon_rain").unwrap(), Box::new(special::no_action)),
-
-            Pattern::new("Flay Mind", "brainsweep", great_sigs.get("brainsweep").unwrap(), Box::new(special::no_action)),
-
-            Pattern::new("Greater Translocation", "move_block/spell", great_sigs.get("move_block/spell").unwrap(), 
-                constructors::spell_2::<VectorIota, VectorIota>()),
-
-
-            //requires value to be set
-            Pattern::new_with_val("Numerical Reflection", "number", "", 
-                constructors::value_0::<NumberIota>("Number", false, "Numerical Reflection")),
-
-            Pattern::new_with_val("Entity Purification", "get_entity", "qqqqqdaqa",
-                constructors::get_entity(None, "Entity Purification")),
-
-            Pattern::new_with_val("Entity Purification: Animal", "get_entity/animal", "qqqqqdaqaawa",
-                constructors::get_entity(Some(&EntityType::Animal), "Entity Purification: Animal")),
-
-
-            Pattern::new_with_val("Entity Purification: Monster", "get_entity/monster", "qqqqqdaqaawq",
-                constructors::get_entity(Some(&EntityType::Monster), "Entity Purification: Monster")),
-
-            Pattern::new_with_val("Entity Purification: Item", "get_entity/item", "qqqqqdaqaaww",
-                constructors::get_entity(Some(&EntityType::Item), "Entity Purification: Item")),
-
-            Pattern::new_with_val("Entity Purification: Player", "get_entity/player", "qqqqqdaqaawe",
-                constructors::get_entity(Some(&EntityType::Player), "Entity Purification: Player")),
-
-            Pattern::new_with_val("Entity Purification: Living", "get_entity/living", "qqqqqdaqaawd",
-                constructors::get_entity(Some(&EntityType::Living), "Entity Purification: Living")),
-
-            Pattern::new_with_val("Zone Distillation: Any", "zone_entity", "qqqqqwded",
-                constructors::zone_entity(None, &false, "Zone Distillation: Any")),
-
-            Pattern::new_with_val("Zone Distillation: Animal", "zone_entity/animal", "qqqqqwdeddwa",
-                constructors::zone_entity(Some(&EntityType::Animal), &false, "Zone Distillation: Animal")),
-
-            Pattern::new_with_val("Zone Distillation: Non-Animal", "zone_entity/not_animal", "eeeeewaqaawa",
-                constructors::zone_entity(Some(&EntityType::Animal), &true, "Zone Distillation: Non-Animal")),
-
-            Pattern::new_with_val("Zone Distillation: Monster", "zone_entity/monster", "qqqqqwdeddwq",
-                constructors::zone_entity(Some(&EntityType::Monster), &false, "Zone Distillation: Monster")),
-
-            Pattern::new_with_val("Zone Distillation: Non-Monster", "zone_entity/not_monster", "eeeeewaqaawq",
-                constructors::zone_entity(Some(&EntityType::Monster), &true, "Zone Distillation: Non-Monster")),
-
-            Pattern::new_with_val("Zone Distillation: Item", "zone_entity/item", "qqqqqwdeddww",
-                constructors::zone_entity(Some(&EntityType::Item), &false, "Zone Distillation: Item")),
-
-            Pattern::new_with_val("Zone Distillation: Non-Item", "zone_entity/not_item", "eeeeewaqaaww",
-                constructors::zone_entity(Some(&EntityType::Item), &true, "Zone Distillation: Non-Item")),
-
-            Pattern::new_with_val("Zone Distillation: Player", "zone_entity/player", "qqqqqwdeddwe",
-                constructors::zone_entity(Some(&EntityType::Player), &false, "Zone Distillation: Player")),
-
-            Pattern::new_with_val("Zone Distillation: Non-Player", "zone_entity/not_player", "eeeeewaqaawe",
-                constructors::zone_entity(Some(&EntityType::Player), &true, "Zone Distillation: Non-Player")),
-
-            Pattern::new_with_val("Zone Distillation: Living", "zone_entity/living", "qqqqqwdeddwd",
-                constructors::zone_entity(Some(&EntityType::Living), &false, "Zone Distillation: Living")),
-
-            Pattern::new_with_val("Zone Distillation: Non-Living", "zone_entity/not_living", "eeeeewaqaawd",
-                constructors::zone_entity(Some(&EntityType::Living), &true, "Zone Distillation: Non-Living")),
-
-            Pattern::new_with_val("Compass' Purification", "entity_pos/eye",  "aa",
-                constructors::value_1::<EntityIota, VectorIota>("Vector", false, "Compass' Purification")),
-
-            Pattern::new_with_val("Compass' Purification II", "entity_pos/foot", "dd",
-                constructors::value_1::<EntityIota, VectorIota>("Vector", false, "Compass' Purification II")),
-
-            Pattern::new_with_val("Alidade's Purification", "get_entity_look", "wa",
-                constructors::value_1::<EntityIota, VectorIota>("Vector", false, "Alidade's Purification")),
-
-            Pattern::new_with_val("Stadiometer's Purification", "get_entity_height", "awq",
-                constructors::value_1::<EntityIota, NumberIota>("Number", false, "Stadiometer's Purification")),
-
-            Pattern::new_with_val("Pace Purification", "get_entity_velocity", "wq",
-                constructors::value_1::<EntityIota, VectorIota>("Vector", false, "Pace Purification")),
-
-            Pattern::new_with_val("Gravitational Purification", "interop/gravity/get", "wawawddew",
-                constructors::value_1::<EntityIota, VectorIota>("Vector", false, "Gravitational Purification")),
-
-            Pattern::new_with_val("Gulliver's Purification", "interop/pehkui/get", "aawawwawwa",
-                constructors::value_1::<EntityIota, NumberIota>("Number", false, "Gulliver's Purification")),
-
-            Pattern::new_with_val("Archer's Distillation", "raycast", "wqaawdd",
-                constructors::value_2::<VectorIota, VectorIota, VectorIota>("Vector", true, "Archer's Distillation")),
-
-            Pattern::new_with_val("Architect's Distillation", "raycast/axis", "weddwaa",
-                constructors::value_2::<VectorIota, VectorIota, VectorIota>("Vector", true, "Architect's Distillation")),
-
-            Pattern::new_with_val("Scout's Distillation", "raycast/entity", "weaqa",
-                constructors::value_2::<VectorIota, VectorIota, EntityIota>("Entity", true, "Scout's Distillation")),
-
-            Pattern::new_with_val("Waystone Reflection", "circle/impetus_pos", "eaqwqae",
-                constructors::value_1::<EntityIota, NumberIota>("Number", false, "Waystone Reflection")),
-
-            Pattern::new_with_val("Lodestone Reflection", "circle/impetus_dir", "eaqwqaewede",
-                constructors::value_1::<EntityIota, NumberIota>("Number", false, "Lodestone Reflection")),
-
-            Pattern::new_with_val("Lesser Fold Reflection", "circle/bounds/min", "eaqwqaewdd",
-                constructors::value_1::<EntityIota, NumberIota>("Number", false, "Lesser Fold Reflection")),
-
-            Pattern::new_with_val("Greater Fold Reflection", "circle/bounds/max", "aqwqawaaqa",
-                constructors::value_1::<EntityIota, NumberIota>("Number", false, "Greater Fold Reflection")),
-
-            Pattern::new_with_val("Reader's Purification", "string/block/get", "awqwawqe",
-                constructors::value_1::<VectorIota, StringIota>("String", true, "Reader's Purification")),
-
-            Pattern::new_with_val("Whisper Reflection", "string/chat/caster", "waqa",
-                    constructors::value_0::<StringIota>("String", false, "Whisper Reflection")),
-
-            Pattern::new_with_val("Listener's Reflection", "string/chat/all", "wded",
-                constructors::value_0::<StringIota>("String", false, "Listener's Reflection")),
-
-            Pattern::new_with_val("Sifter's Reflection", "string/chat/prefix/get", "ewded",
-                constructors::value_0::<StringIota>("String", true, "Sifter's Reflection")),
-
-            Pattern::new_with_val("Identity Reflection", "wisp/self", "dedwqqwdedwqqaw",
-                constructors::value_0::<EntityIota>("Entity", true, "Identity Reflection")),
-
-            //MoreIotas - Matrices
-            Pattern::new("Transformation Purification", "matrix/make", "awwaeawwaadwa", Box::new(matrix::make)),
-            Pattern::new("Restoration Purification", "matrix/unmake", "dwwdqdwwddawd", Box::new(matrix::unmake)),
-            Pattern::new("Identity Purification", "matrix/identity", "awwaeawwaqw", Box::new(matrix::identity)),
-            Pattern::new("Zero Distillation", "matrix/zero", "awwaeawwa", Box::new(matrix::zero)),
-            Pattern::new("Rotation Distillation", "matrix/rotation", "awwaeawwawawddw", Box::new(matrix::rotate)),
-            Pattern::new("Addition Distillation: Matrix", "matrix/add", "waawawaeawwaea", Box::new(matrix::add)),
-            Pattern::new("Multiplication Distillation: Matrix", "matrix/mul", "waqawawwaeaww", Box::new(matrix::multiply)),
-            Pattern::new("Transpose Purification", "matrix/transpose", "wwaeawwaede", Box::new(matrix::transpose)),
-            Pattern::new("Inverse Purification", "matrix/inverse", "wwdqdwwdqaq", Box::new(matrix::inverse)),
-            Pattern::new("Determinant Purification", "matrix/determinant", "aeawwaeawaw", Box::new(matrix::determinant)),
-            Pattern::new("Tower Distillation", "matrix/concat/vert", "awwaeawwawawdedwa", Box::new(matrix::concat_vertical)),
-            Pattern::new("Sprawling Distillation", "matrix/concat/hori", "dwwdqdwwdwdwaqawd", Box::new(matrix::concat_horizontal)),
-            Pattern::new("Toppling Gambit", "matrix/split/vert", "awdedwawawwaeawwa", Box::new(matrix::split_vertical)),
-            Pattern::new("Mitosis Gambit", "matrix/split/hori", "dwaqawdwdwwdqdwwd", Box::new(matrix::split_horizontal)),
-
-            //MoreIotas - Strings
-            Pattern::new("Concatenation Distillation", "string/add", "waawaqwawqq", Box::new(string::concat)),
-            Pattern::new("Separation Distillation", "string/split", "aqwaqa", Box::new(string::split)),
-            Pattern::new("Input Purification", "string/parse", "aqwaq", Box::new(string::parse)),
-            Pattern::new("Discoverer's Distillation", "string/find", "waqwwaqa", Box::new(string::find)),
-            Pattern::new("Winnowing Distillation", "string/sub", "aqwwaqwaad", Box::new(string::sub)),
-            Pattern::new("Length Distillation: Str", "string/len", "waqaeaq", Box::new(string::len)),
-            Pattern::new("Scrivener's Purification", "string/iota", "wawqwawaw", Box::new(string::display_iota)),
-            Pattern::new("Patternmaster's Purification", "string/action", "wdwewdwdw", Box::new(string::display_action)),
-            Pattern::new("Case Distillation", "string/case", "dwwdwwdwdd", Box::new(string::set_case)),
-
-            // Pattern::new("", "", "", Box::new(string::)),
-
-            //5D Casting - Continuum
-            Pattern::new("Selection Distillation", "continuum/get", "deeed", Box::new(continuum::get)),
-            Pattern::new("Selection Exaltation", "continuum/slice", "qaeaqwded", Box::new(continuum::slice)),
-            Pattern::new("Transmutation Distillation", "continuum/map", "dadadad", Box::new(continuum::map)),
-            Pattern::new("Natural Reflection", "continuum/stream/num", "edwaq", Box::new(continuum::number_stream)),
-            Pattern::new("Eternal Distillation", "continuum/stream/make", "aqqqaqwdaqqqaq", Box::new(continuum::make_stream)),
-            Pattern::new("Speaker's Decomposition", "continuum/deconstruct", "aaqwqaa", Box::new(continuum::deconstruct)),
-
-            //5D Casting - Cell
-            Pattern::new("Cell Purification", "cell/create", "aaaaaaaaaa", Box::new(cell::create)),
-            Pattern::new("Mutation Distillation", "cell/replace", "aaaaaaaaaaa", Box::new(cell::replace)),
-            Pattern::new("Retrieval Purification", "cell/unwrap", "aaaaaaaaaaaa", Box::new(cell::unwrap)),
-
-            //Macula
-            Pattern::new("Macula Gambit", "macula/dimensions", "aawawaa", Box::new(no_action)),
-            Pattern::new("Calligrapher's Purification", "visage/text/unbounded", "aaqdwdwd", Box::new(no_action)),
-            Pattern::new("Etch Visage", "macula/add", "wddaaddw", Box::new(no_action)),
-
-        ];
+        PatternRegistry::construct_from_data(great_sigs, default_pattern_data())
+    }
 
+    fn construct_from_data(
+        great_sigs: &HashMap<String, String>,
+        data: &[PatternData],
+    ) -> PatternRegistry {
+        let mut registry = builtin_patterns(great_sigs);
+        attach_pattern_data(&mut registry, data, great_sigs);
         registry
     }
 
+    fn from_config(config: &Config) -> PatternRegistry {
+        match &config.pattern_data {
+            Some(data) => PatternRegistry::construct_from_data(&config.great_spell_sigs, data),
+            None => PatternRegistry::construct(&config.great_spell_sigs),
+        }
+    }
+
     fn find(&self, query: &str, value: &Option<ActionValue>) -> Option<Pattern> {
         if let Some(ActionValue::Bookkeeper(code)) = value {
             let mut bookkeeper =
@@ -462,16 +106,20 @@ impl PatternRegistryExt for PatternRegistry {
         {
             if let Some(ActionValue::Iota(iota)) = value {
                 if let Some(number) = iota.downcast_ref::<NumberIota>() {
-                    let number = Pattern::new_with_val(
+                    let mut number = Pattern::new_with_val(
                         "Numerical Reflection",
                         "number",
-                        &gen_number(*number as f32),
+                        &numbers::signature(*number).unwrap_or_default(),
                         constructors::value_0::<NumberIota>(
                             "Number",
                             false,
                             "Numerical Reflection",
                         ),
                     );
+                    number.args = self
+                        .iter()
+                        .find(|entry| entry.internal_name == "number")
+                        .and_then(|entry| entry.args.clone());
                     return Some(number);
                 }
             }
@@ -514,7 +162,7 @@ impl PatternRegistryExt for PatternRegistry {
                     let number = Pattern::new_with_val(
                         "Numerical Reflection",
                         "number",
-                        &gen_number(*number as f32),
+                        &numbers::signature(*number).unwrap_or_default(),
                         constructors::value_0::<NumberIota>(
                             "Number",
                             false,
@@ -535,6 +183,448 @@ impl PatternRegistryExt for PatternRegistry {
             })
             .collect::<Vector<Pattern>>()
     }
+
+    //the value of a Numerical Reflection or Bookkeeper's Gambit, worked out from its angles
+    fn decode_value(&self, signature: &str) -> Option<ActionValue> {
+        if let Some(number) = decode_number(signature) {
+            return Some(ActionValue::Iota(Rc::new(number)));
+        }
+
+        //like the mod, patterns with their own signature aren't masks
+        let registered = self
+            .iter()
+            .any(|entry| !entry.signature.is_empty() && entry.signature == signature);
+        if registered {
+            None
+        } else {
+            decode_bookkeeper(signature).map(ActionValue::Bookkeeper)
+        }
+    }
+}
+
+//patterns that the interpreter has an action for
+#[rustfmt::skip]
+fn builtin_patterns(great_sigs: &HashMap<String, String>) -> PatternRegistry {
+    vec![
+        //special patterns
+        Pattern::new_with_val("Consideration", "escape", "qqqaw", Box::new(special::escape)),
+        Pattern::new("Introspection", "open_paren", "qqq", Box::new(special::introspect)),
+        Pattern::new("Retrospection", "close_paren", "eee", Box::new(special::retrospect)),
+        Pattern::new("Hermes' Gambit", "eval", "deaqq", Box::new(eval::eval)),
+        Pattern::new("Thoth's Gambit", "for_each", "dadad", Box::new(eval::for_each)),
+        Pattern::new("Charon's Gambit", "halt", "aqdee", Box::new(eval::halt)),
+        Pattern::new("Reveal", "print", "de", Box::new(special::print)),
+        Pattern::new("Iris' Gambit", "eval/cc", "qwaqde", Box::new(eval::eval_cc)),
+
+
+
+        //math
+        Pattern::new("Additive Distillation", "add", "waaw", Box::new(math::add)),
+        Pattern::new("Subtractive Distillation", "sub", "wddw", Box::new(math::subtract)),
+        Pattern::new("Multiplicative Distillation", "mul_dot", "waqaw", Box::new(math::mul_dot)),
+        Pattern::new("Division Distillation", "div_cross", "wdedw", Box::new(math::div_cross)),
+        Pattern::new("Length Purification", "abs_len", "wqaqw", Box::new(math::abs_len)),
+        Pattern::new("Power Distillation", "pow_proj", "wedew", Box::new(math::pow_proj)),
+        Pattern::new("Floor Purification", "floor", "ewq", Box::new(math::floor)),
+        Pattern::new("Ceiling Purification", "ceil", "qwe", Box::new(math::ceil)),
+        Pattern::new("Vector Exaltation", "construct_vec", "eqqqqq", Box::new(math::construct_vec)),
+        Pattern::new("Vector Disintegration", "deconstruct_vec", "qeeeee", Box::new(math::deconstruct_vec)),
+        Pattern::new("Axial Purification", "coerce_axial", "qqqqqaww", Box::new(math::coerce_axial)),
+        Pattern::new("Conjunction Distillation", "and", "wdw", Box::new(math::and)),
+        Pattern::new("Disjunction Distillation", "or", "waw", Box::new(math::or)),
+        Pattern::new("Exclusion Distillation", "xor", "dwa", Box::new(math::xor)),
+        Pattern::new("Maximus Distillation", "greater", "e", Box::new(math::greater)),
+        Pattern::new("Minimus Distillation", "less", "q", Box::new(math::less)),
+        Pattern::new("Maximus Distillation II", "greater_eq", "ee", Box::new(math::greater_eq)),
+        Pattern::new("Minimus Distillation II", "less_eq", "qq", Box::new(math::less_eq)),
+        Pattern::new("Equality Distillation", "equals", "ad", Box::new(math::equals)),
+        Pattern::new("Inequality Distillation", "not_equals", "da", Box::new(math::not_equals)),
+        Pattern::new("Negation Purification", "not", "dw", Box::new(math::not)),
+        Pattern::new("Augur's Purification", "bool_coerce", "aw", Box::new(math::bool_coerce)),
+        Pattern::new("Sine Purification", "sin", "qqqqqaa", Box::new(math::sin)),
+        Pattern::new("Cosine Purification", "cos", "qqqqqad", Box::new(math::cos)),
+        Pattern::new("Tangent Purification", "tan", "wqqqqqadq", Box::new(math::tan)),
+        Pattern::new("Inverse Sine Purification", "arcsin", "ddeeeee", Box::new(math::arcsin)),
+        Pattern::new("Inverse Cosine Purification", "arccos", "adeeeee", Box::new(math::arccos)),
+        Pattern::new("Inverse Tangent Purification", "arctan", "eadeeeeew", Box::new(math::arctan)),
+        Pattern::new("Logarithmic Distillation", "logarithm", "eqaqe", Box::new(math::logarithm)),
+        Pattern::new("Modulus Distillation", "modulo", "addwaad", Box::new(math::modulo)),
+        Pattern::new("Intersection Distillation", "and_bit", "wdweaqa", Box::new(math::and_bit)),
+        Pattern::new("Unifying Distillation", "or_bit", "waweaqa", Box::new(math::or_bit)),
+        Pattern::new("Exclusionary Distillation", "xor_bit", "dwaeaqa", Box::new(math::xor_bit)),
+        Pattern::new("Inversion Purification", "not_bit", "dweaqa", Box::new(math::not_bit)),
+        Pattern::new("Uniqueness Purification", "to_set", "aweaqa", Box::new(math::to_set)),
+        Pattern::new("Augur's Exaltation", "if", "awdd",  Box::new(math::bool_if)),
+        Pattern::new("Entropy Reflection", "random", "eqqq",  Box::new(math::random)),
+
+        //Hexal - math
+        Pattern::new("Factorial Purification", "factorial", "wawdedwaw",  Box::new(hexal::math::factorial)),
+        Pattern::new("Running Sum Purification", "running/sum", "aea",  Box::new(hexal::math::running_sum)),
+        Pattern::new("Running Product Purification", "running/mul", "qaawaaq",  Box::new(hexal::math::running_product)),
+
+
+        //lists
+        Pattern::new("Integration Distillation", "append", "edqde", Box::new(lists::append)),
+        Pattern::new("Combination Distillation", "concat", "qaeaq", Box::new(lists::concat)),
+        Pattern::new("Selection Distillation", "index", "deeed", Box::new(lists::index)),
+        Pattern::new("Abacus Purification", "list_size", "aqaeaq", Box::new(lists::list_size)),
+        Pattern::new("Single's Purification", "singleton", "adeeed", Box::new(lists::singleton)),
+        Pattern::new("Retrograde Purification", "reverse_list", "qqqaede", Box::new(lists::reverse_list)),
+        Pattern::new("Flock's Gambit", "last_n_list", "ewdqdwe", Box::new(lists::last_n_list)),
+        Pattern::new("Flock's Disintegration", "splat", "qwaeawq", Box::new(lists::splat)),
+        Pattern::new("Locator's Distillation", "index_of", "dedqde", Box::new(lists::index_of)),
+        Pattern::new("Excisor's Distillation", "list_remove", "edqdewaqa", Box::new(lists::list_remove)),
+        Pattern::new("Selection Exaltation", "slice", "qaeaqwded", Box::new(lists::slice)),
+        Pattern::new("Surgeon's Exaltation", "modify_in_place", "wqaeaqw", Box::new(lists::modify_in_place)),
+        Pattern::new("Speaker's Distillation", "construct", "ddewedd", Box::new(lists::construct)),
+        Pattern::new("Speaker's Decomposition", "deconstruct", "aaqwqaa", Box::new(lists::deconstruct)),
+
+        //stack manipulation
+        Pattern::new("Gemini Decomposition", "duplicate", "aadaa", Box::new(stack::duplicate)),
+        Pattern::new("Dioscuri Gambit", "2dup", "aadadaaw", Box::new(stack::two_dup)),
+        Pattern::new("Gemini Gambit", "duplicate_n", "aadaadaa", Box::new(stack::duplicate_n)),
+        Pattern::new("Jester's Gambit", "swap", "aawdd", Box::new(stack::swap)),
+        Pattern::new("Rotation Gambit", "rotate", "aaeaa", Box::new(stack::rotate)),
+        Pattern::new("Rotation Gambit II", "rotate_reverse", "ddqdd", Box::new(stack::rotate_reverse)),
+        Pattern::new("Prospector's Gambit", "over", "aaedd", Box::new(stack::over)),
+        Pattern::new("Undertaker's Gambit", "tuck", "ddqaa", Box::new(stack::tuck)),
+        Pattern::new("Flock's Reflection", "stack_len", "qwaeawqaeaqa", Box::new(stack::stack_len)),
+        Pattern::new("Fisherman's Gambit", "fisherman", "ddad", Box::new(stack::fisherman)),
+        Pattern::new("Fisherman's Gambit II", "fisherman/copy", "aada", Box::new(stack::fisherman_copy)),
+        Pattern::new("Swindler's Gambit", "swizzle", "qaawdde", Box::new(swizzle::swizzle)),
+
+
+        //read/write
+        Pattern::new("Muninn's Reflection", "read/local",  "qeewdweddw", Box::new(read_write::read_local)),
+        Pattern::new("Huginn's Gambit", "write/local",  "eqqwawqaaw", Box::new(read_write::write_local)),
+        Pattern::new("Erase Item", "erase", "qdqawwaww", Box::new(read_write::erase)),
+        Pattern::new("Craft Trinket", "craft/trinket", "wwaqqqqqeaqeaeqqqeaeq", Box::new(read_write::craft_trinket)),
+        Pattern::new("Craft Cypher", "craft/cypher", "waqqqqq", Box::new(read_write::craft_cypher)),
+        Pattern::new("Craft Artifact", "craft/artifact", "wwaqqqqqeawqwqwqwqwqwwqqeadaeqqeqqeadaeqq", Box::new(read_write::craft_artifact)),
+        Pattern::new("Scribe's Reflection", "read",  "aqqqqq", Box::new(read_write::read)),
+        Pattern::new("Scribe's Gambit", "write",  "deeeee", Box::new(read_write::write)),
+        Pattern::new("Auditor's Reflection", "readable",  "aqqqqqe", Box::new(read_write::readable)),
+        Pattern::new("Assessor's Reflection", "writable",  "deeeeeq", Box::new(read_write::writable)),
+        Pattern::new("Akasha's Distillation", "akashic/read",  "qqqwqqqqqaq", Box::new(read_write::akashic_read)),
+        Pattern::new("Akasha's Gambit", "akashic/write",  "eeeweeeeede", Box::new(read_write::akashic_write)),
+        Pattern::new("Chronicler's Purification", "read/entity",  "wawqwqwqwqwqw", Box::new(read_write::read_entity)),
+        Pattern::new("Chronicler's Gambit", "write/entity",  "wdwewewewewew", Box::new(read_write::write_entity)),
+        Pattern::new("Auditor's Purification", "readable/entity",  "wawqwqwqwqwqwew", Box::new(read_write::readable_entity)),
+        Pattern::new("Assessor's Purification", "writable/entity",  "wdwewewewewewqw", Box::new(read_write::writeable_entity)),
+
+
+        //sentinel
+        Pattern::new("Summon Sentinel", "sentinel/create", "waeawae", Box::new(sentinel::create)),
+        Pattern::new("Banish Sentinel", "sentinel/destroy", "qdwdqdw", Box::new(sentinel::destroy)),
+        Pattern::new("Locate Sentinel", "sentinel/get_pos", "waeawaede", Box::new(sentinel::get_pos)),
+        Pattern::new("Wayfind Sentinel", "sentinel/wayfind", "waeawaedwa", Box::new(sentinel::wayfind)),
+
+
+        //consts
+        Pattern::new("Mind's Reflection", "get_caster", "qaq", 
+            constructors::push_const(Rc::new(EntityIota {name: Rc::from("Caster"), uuid: "[I;0,0,0,0]".to_string()}))),
+        Pattern::new("Vacant Reflection", "empty_list", "qqaeaae", constructors::push_const(Rc::new(vector![]))),
+        Pattern::new("Vector Reflection +X", "const/vec/px", "qqqqqea", constructors::push_const(Rc::new(VectorIota::new(1.0, 0.0, 0.0)))),
+        Pattern::new("Vector Reflection +Y", "const/vec/py", "qqqqqew", constructors::push_const(Rc::new(VectorIota::new(0.0, 1.0, 0.0)))),
+        Pattern::new("Vector Reflection +Z", "const/vec/pz", "qqqqqed", constructors::push_const(Rc::new(VectorIota::new(0.0, 0.0, 1.0)))),
+        Pattern::new("Vector Reflection -X", "const/vec/nx", "eeeeeqa", constructors::push_const(Rc::new(VectorIota::new(-1.0, 0.0, 0.0)))),
+        Pattern::new("Vector Reflection -Y", "const/vec/ny", "eeeeeqw", constructors::push_const(Rc::new(VectorIota::new(0.0, -1.0, 0.0)))),
+        Pattern::new("Vector Reflection -Z", "const/vec/nz", "eeeeeqd", constructors::push_const(Rc::new(VectorIota::new(0.0, 0.0, -1.0)))),
+        Pattern::new("Vector Reflection Zero", "const/vec/0", "qqqqq", constructors::push_const(Rc::new(VectorIota::new(0.0, 0.0, 0.0)))),
+        Pattern::new("Arc's Reflection", "const/double/pi", "qdwdq", constructors::push_const(Rc::new(PI))),
+        Pattern::new("Circle's Reflection", "const/double/tau", "eawae", constructors::push_const(Rc::new(TAU))),
+        Pattern::new("Euler's Reflection", "const/double/e", "aaq", constructors::push_const(Rc::new(E))),
+        Pattern::new("Nullary Reflection", "const/null", "d", constructors::push_const(Rc::new(NullIota))),
+        Pattern::new("True Reflection", "const/true", "aqae",constructors::push_const(Rc::new(true))),
+        Pattern::new("False Reflection", "const/false", "dedq",constructors::push_const(Rc::new(false))),
+        Pattern::new("Blank Reflection", "string/empty", "awdwa",constructors::push_const(Rc::new(String::new()))),
+        Pattern::new("Spacing Reflection", "string/space", "awdwaaww",constructors::push_const(Rc::new(" ".to_string()))),
+        Pattern::new("Comma Reflection", "string/comma", "qa",constructors::push_const(Rc::new(",".to_string()))),
+        Pattern::new("Breaking Reflection", "string/newline", "waawaw",constructors::push_const(Rc::new("\n".to_string()))),
+        // Pattern::new("", "", "",constructors::push_const(Rc::new())),
+
+
+
+        //spells
+        Pattern::new("Alter Gravity", "interop/gravity/set", "wdwdwaaqw", constructors::spell_2::<EntityIota, VectorIota>()).with_cost(MediaCost::Const(1.0)),
+        Pattern::new("Alter Scale", "interop/pehkui/set", "ddwdwwdwwd", constructors::spell_2::<EntityIota, VectorIota>()).with_cost(MediaCost::Const(1.0)),
+        Pattern::new("Explosion", "explode", "aawaawaa", constructors::spell_2::<VectorIota, NumberIota>()).with_cost(costs::explosion(false)),
+        Pattern::new("Fireball", "explode/fire", "ddwddwdd", constructors::spell_2::<VectorIota, NumberIota>()).with_cost(costs::explosion(true)),
+        Pattern::new("Impulse", "add_motion", "awqqqwaqw", constructors::spell_2::<EntityIota, VectorIota>()).with_cost(costs::impulse()),
+        Pattern::new("Blink", "blink", "awqqqwaq", constructors::spell_2::<EntityIota, NumberIota>()).with_cost(costs::blink()),
+        Pattern::new("Break Block", "break_block", "qaqqqqq", constructors::spell_1::<VectorIota>()).with_cost(MediaCost::Const(0.125)),
+        Pattern::new("Place Block", "place_block", "eeeeede", constructors::spell_1::<VectorIota>()).with_cost(MediaCost::Const(0.125)),
+        Pattern::new("Internalize Pigment", "colorize", "awddwqawqwawq", Box::new(special::no_action)).with_cost(MediaCost::Const(1.0)),
+        Pattern::new("Create Water", "create_water", "aqawqadaq", constructors::spell_1::<VectorIota>()).with_cost(MediaCost::Const(1.0)),
+        Pattern::new("Destroy Liquid", "destroy_water", "dedwedade", constructors::spell_1::<VectorIota>()).with_cost(MediaCost::Const(2.0)),
+        Pattern::new("Ignite Block", "ignite", "aaqawawa", constructors::spell_1::<VectorIota>()).with_cost(MediaCost::Const(1.0)),
+        Pattern::new("Extinguish Area", "extinguish", "ddedwdwd", constructors::spell_1::<VectorIota>()).with_cost(MediaCost::Const(6.0)),
+        Pattern::new("Conjure Block", "conjure_block", "qqa", constructors::spell_1::<VectorIota>()).with_cost(MediaCost::Const(1.0)),
+        Pattern::new("Conjure Light", "conjure_light", "qqd", constructors::spell_1::<VectorIota>()).with_cost(MediaCost::Const(1.0)),
+        Pattern::new("Overgrow", "bonemeal", "wqaqwawqaqw", constructors::spell_1::<VectorIota>()).with_cost(MediaCost::Const(1.0)),
+        Pattern::new("Recharge Item", "recharge", "qqqqqwaeaeaeaeaea", constructors::spell_1::<EntityIota>()).with_cost(MediaCost::Const(10.0)),
+        Pattern::new("Edify Sapling", "edify", "wqaqwd", constructors::spell_1::<VectorIota>()).with_cost(MediaCost::Const(10.0)),
+        Pattern::new("Make Note", "beep", "adaa", Box::new(special::beep)).with_cost(MediaCost::Const(0.1)),
+        Pattern::new("White Sun's Nadir", "potion/weakness", "qqqqqaqwawaw", constructors::spell_3::<EntityIota, NumberIota, NumberIota>()).with_cost(costs::potion(0.1, true, false)),
+        Pattern::new("Blue Sun's Nadir", "potion/levitation", "qqqqqawwawawd", constructors::spell_2::<EntityIota, NumberIota>()).with_cost(costs::potion(0.2, false, false)),
+        Pattern::new("Black Sun's Nadir", "potion/wither", "qqqqqaewawawe", constructors::spell_3::<EntityIota, NumberIota, NumberIota>()).with_cost(costs::potion(1.0, true, false)),
+        Pattern::new("Red Sun's Nadir", "potion/poison", "qqqqqadwawaww", constructors::spell_3::<EntityIota, NumberIota, NumberIota>()).with_cost(costs::potion(1.0 / 3.0, true, false)),
+        Pattern::new("Green Sun's Nadir", "potion/slowness", "qqqqqadwawaw", constructors::spell_3::<EntityIota, NumberIota, NumberIota>()).with_cost(costs::potion(1.0 / 3.0, true, false)),
+        Pattern::new("Write", "string/block/set", "dwewdweq", Box::new(string::write)),
+        Pattern::new("Sifter's Gambit", "string/chat/prefix/set", "qwaqa", Box::new(string::set_prefix)),
+        Pattern::new("Particles", "particles", "eqqqqa", Box::new(hexal::spells::particles)).with_cost(MediaCost::Const(0.01)),
+        Pattern::new("Falling Block", "falling_block", "wqwawqwqwqwqwqw", constructors::spell_1::<VectorIota>()).with_cost(MediaCost::Const(1.5)),
+        Pattern::new("Summon Cyclic Wisp", "wisp/summon/ticking", "aqaweewaqawee", Box::new(hexal::spells::summon_wisp_ticking)).with_cost(costs::summon_wisp()),
+        Pattern::new("Pathfinder's Gambit", "wisp/move/target/set", "awqwawqaw", constructors::spell_1::<VectorIota>()),
+        Pattern::new("Haste", "wisp/move/speed/set", "aeawqqqae", constructors::spell_1::<NumberIota>()),
+        //TODO: make Delay wisp Delay wisps
+        Pattern::new("Delay Wisp", "wisp/trigger/tick", "aqawded", constructors::spell_1::<NumberIota>()),
+
+
+        //great spells
+        Pattern::new("Craft Phial", "craft/battery", great_sigs.get("craft/battery").unwrap(), 
+            constructors::spell_1::<EntityIota>()).with_cost(MediaCost::Const(10.0)),
+
+        Pattern::new("White Sun's Zenith", "potion/regeneration", great_sigs.get("potion/regeneration").unwrap(), 
+            constructors::spell_3::<EntityIota, NumberIota, NumberIota>()).with_cost(costs::potion(1.0, true, true)),
+
+        Pattern::new("Blue Sun's Zenith", "potion/night_vision", great_sigs.get("potion/night_vision").unwrap(), 
+            constructors::spell_2::<EntityIota, NumberIota>()).with_cost(costs::potion(0.2, false, true)),
+
+        Pattern::new("Black Sun's Zenith", "potion/absorption", great_sigs.get("potion/absorption").unwrap(), 
+            constructors::spell_3::<EntityIota, NumberIota, NumberIota>()).with_cost(costs::potion(1.0, true, true)),
+
+        Pattern::new("Red Sun's Zenith", "potion/haste", great_sigs.get("potion/haste").unwrap(), 
+            constructors::spell_3::<EntityIota, NumberIota, NumberIota>()).with_cost(costs::potion(1.0 / 3.0, true, true)),
+
+        Pattern::new("Green Sun's Zenith", "potion/strength", great_sigs.get("potion/strength").unwrap(), 
+            constructors::spell_3::<EntityIota, NumberIota, NumberIota>()).with_cost(costs::potion(1.0 / 3.0, true, true)),
+
+        Pattern::new("Summon Lightning", "lightning", great_sigs.get("lightning").unwrap(), 
+            constructors::spell_1::<VectorIota>()).with_cost(MediaCost::Const(15.0)),
+
+        Pattern::new("Flight", "flight", great_sigs.get("flight").unwrap(), 
+            constructors::spell_3::<EntityIota, NumberIota, NumberIota>()).with_cost(costs::flight()),
+
+        Pattern::new("Create Lava", "create_lava", great_sigs.get("create_lava").unwrap(), 
+            constructors::spell_1::<VectorIota>()).with_cost(MediaCost::Const(10.0)),
+
+        Pattern::new("Greater Teleport", "teleport", great_sigs.get("teleport").unwrap(), 
+            constructors::spell_2::<EntityIota, VectorIota>()).with_cost(MediaCost::Const(100.0)),
+
+        Pattern::new("Summon Greater Sentinel", "sentinel/create/great", great_sigs.get("sentinel/create/great").unwrap(), 
+            constructors::spell_1::<VectorIota>()).with_cost(MediaCost::Const(2.0)),
+
+        Pattern::new("Dispel Rain", "dispel_rain", great_sigs.get("dispel_rain").unwrap(), Box::new(special::no_action)).with_cost(MediaCost::Const(10.0)),
+
+        Pattern::new("Summon Rain", "summon_rain", great_sigs.get("summon_rain").unwrap(), Box::new(special::no_action)).with_cost(MediaCost::Const(15.0)),
+
+        Pattern::new("Flay Mind", "brainsweep", great_sigs.get("brainsweep").unwrap(), Box::new(special::no_action)).with_cost(MediaCost::Const(10.0)),
+
+        Pattern::new("Greater Translocation", "move_block/spell", great_sigs.get("move_block/spell").unwrap(), 
+            constructors::spell_2::<VectorIota, VectorIota>()).with_cost(MediaCost::Const(10.0)),
+
+
+        //requires value to be set
+        Pattern::new_with_val("Numerical Reflection", "number", "", 
+            constructors::value_0::<NumberIota>("Number", false, "Numerical Reflection")),
+
+        Pattern::new_with_val("Entity Purification", "get_entity", "qqqqqdaqa",
+            constructors::get_entity(None, "Entity Purification")),
+
+        Pattern::new_with_val("Entity Purification: Animal", "get_entity/animal", "qqqqqdaqaawa",
+            constructors::get_entity(Some(&EntityType::Animal), "Entity Purification: Animal")),
+
+
+        Pattern::new_with_val("Entity Purification: Monster", "get_entity/monster", "qqqqqdaqaawq",
+            constructors::get_entity(Some(&EntityType::Monster), "Entity Purification: Monster")),
+
+        Pattern::new_with_val("Entity Purification: Item", "get_entity/item", "qqqqqdaqaaww",
+            constructors::get_entity(Some(&EntityType::Item), "Entity Purification: Item")),
+
+        Pattern::new_with_val("Entity Purification: Player", "get_entity/player", "qqqqqdaqaawe",
+            constructors::get_entity(Some(&EntityType::Player), "Entity Purification: Player")),
+
+        Pattern::new_with_val("Entity Purification: Living", "get_entity/living", "qqqqqdaqaawd",
+            constructors::get_entity(Some(&EntityType::Living), "Entity Purification: Living")),
+
+        Pattern::new_with_val("Zone Distillation: Any", "zone_entity", "qqqqqwded",
+            constructors::zone_entity(None, &false, "Zone Distillation: Any")),
+
+        Pattern::new_with_val("Zone Distillation: Animal", "zone_entity/animal", "qqqqqwdeddwa",
+            constructors::zone_entity(Some(&EntityType::Animal), &false, "Zone Distillation: Animal")),
+
+        Pattern::new_with_val("Zone Distillation: Non-Animal", "zone_entity/not_animal", "eeeeewaqaawa",
+            constructors::zone_entity(Some(&EntityType::Animal), &true, "Zone Distillation: Non-Animal")),
+
+        Pattern::new_with_val("Zone Distillation: Monster", "zone_entity/monster", "qqqqqwdeddwq",
+            constructors::zone_entity(Some(&EntityType::Monster), &false, "Zone Distillation: Monster")),
+
+        Pattern::new_with_val("Zone Distillation: Non-Monster", "zone_entity/not_monster", "eeeeewaqaawq",
+            constructors::zone_entity(Some(&EntityType::Monster), &true, "Zone Distillation: Non-Monster")),
+
+        Pattern::new_with_val("Zone Distillation: Item", "zone_entity/item", "qqqqqwdeddww",
+            constructors::zone_entity(Some(&EntityType::Item), &false, "Zone Distillation: Item")),
+
+        Pattern::new_with_val("Zone Distillation: Non-Item", "zone_entity/not_item", "eeeeewaqaaww",
+            constructors::zone_entity(Some(&EntityType::Item), &true, "Zone Distillation: Non-Item")),
+
+        Pattern::new_with_val("Zone Distillation: Player", "zone_entity/player", "qqqqqwdeddwe",
+            constructors::zone_entity(Some(&EntityType::Player), &false, "Zone Distillation: Player")),
+
+        Pattern::new_with_val("Zone Distillation: Non-Player", "zone_entity/not_player", "eeeeewaqaawe",
+            constructors::zone_entity(Some(&EntityType::Player), &true, "Zone Distillation: Non-Player")),
+
+        Pattern::new_with_val("Zone Distillation: Living", "zone_entity/living", "qqqqqwdeddwd",
+            constructors::zone_entity(Some(&EntityType::Living), &false, "Zone Distillation: Living")),
+
+        Pattern::new_with_val("Zone Distillation: Non-Living", "zone_entity/not_living", "eeeeewaqaawd",
+            constructors::zone_entity(Some(&EntityType::Living), &true, "Zone Distillation: Non-Living")),
+
+        Pattern::new_with_val("Compass' Purification", "entity_pos/eye",  "aa",
+            constructors::value_1::<EntityIota, VectorIota>("Vector", false, "Compass' Purification")),
+
+        Pattern::new_with_val("Compass' Purification II", "entity_pos/foot", "dd",
+            constructors::value_1::<EntityIota, VectorIota>("Vector", false, "Compass' Purification II")),
+
+        Pattern::new_with_val("Alidade's Purification", "get_entity_look", "wa",
+            constructors::value_1::<EntityIota, VectorIota>("Vector", false, "Alidade's Purification")),
+
+        Pattern::new_with_val("Stadiometer's Purification", "get_entity_height", "awq",
+            constructors::value_1::<EntityIota, NumberIota>("Number", false, "Stadiometer's Purification")),
+
+        Pattern::new_with_val("Pace Purification", "get_entity_velocity", "wq",
+            constructors::value_1::<EntityIota, VectorIota>("Vector", false, "Pace Purification")),
+
+        Pattern::new_with_val("Gravitational Purification", "interop/gravity/get", "wawawddew",
+            constructors::value_1::<EntityIota, VectorIota>("Vector", false, "Gravitational Purification")),
+
+        Pattern::new_with_val("Gulliver's Purification", "interop/pehkui/get", "aawawwawwa",
+            constructors::value_1::<EntityIota, NumberIota>("Number", false, "Gulliver's Purification")),
+
+        Pattern::new_with_val("Archer's Distillation", "raycast", "wqaawdd",
+            constructors::value_2::<VectorIota, VectorIota, VectorIota>("Vector", true, "Archer's Distillation")),
+
+        Pattern::new_with_val("Architect's Distillation", "raycast/axis", "weddwaa",
+            constructors::value_2::<VectorIota, VectorIota, VectorIota>("Vector", true, "Architect's Distillation")),
+
+        Pattern::new_with_val("Scout's Distillation", "raycast/entity", "weaqa",
+            constructors::value_2::<VectorIota, VectorIota, EntityIota>("Entity", true, "Scout's Distillation")),
+
+        Pattern::new_with_val("Waystone Reflection", "circle/impetus_pos", "eaqwqae",
+            constructors::value_1::<EntityIota, NumberIota>("Number", false, "Waystone Reflection")),
+
+        Pattern::new_with_val("Lodestone Reflection", "circle/impetus_dir", "eaqwqaewede",
+            constructors::value_1::<EntityIota, NumberIota>("Number", false, "Lodestone Reflection")),
+
+        Pattern::new_with_val("Lesser Fold Reflection", "circle/bounds/min", "eaqwqaewdd",
+            constructors::value_1::<EntityIota, NumberIota>("Number", false, "Lesser Fold Reflection")),
+
+        Pattern::new_with_val("Greater Fold Reflection", "circle/bounds/max", "aqwqawaaqa",
+            constructors::value_1::<EntityIota, NumberIota>("Number", false, "Greater Fold Reflection")),
+
+        Pattern::new_with_val("Reader's Purification", "string/block/get", "awqwawqe",
+            constructors::value_1::<VectorIota, StringIota>("String", true, "Reader's Purification")),
+
+        Pattern::new_with_val("Whisper Reflection", "string/chat/caster", "waqa",
+                constructors::value_0::<StringIota>("String", false, "Whisper Reflection")),
+
+        Pattern::new_with_val("Listener's Reflection", "string/chat/all", "wded",
+            constructors::value_0::<StringIota>("String", false, "Listener's Reflection")),
+
+        Pattern::new_with_val("Sifter's Reflection", "string/chat/prefix/get", "ewded",
+            constructors::value_0::<StringIota>("String", true, "Sifter's Reflection")),
+
+        Pattern::new_with_val("Identity Reflection", "wisp/self", "dedwqqwdedwqqaw",
+            constructors::value_0::<EntityIota>("Entity", true, "Identity Reflection")),
+
+        //MoreIotas - Matrices
+        Pattern::new("Transformation Purification", "matrix/make", "awwaeawwaadwa", Box::new(matrix::make)),
+        Pattern::new("Restoration Purification", "matrix/unmake", "dwwdqdwwddawd", Box::new(matrix::unmake)),
+        Pattern::new("Identity Purification", "matrix/identity", "awwaeawwaqw", Box::new(matrix::identity)),
+        Pattern::new("Zero Distillation", "matrix/zero", "awwaeawwa", Box::new(matrix::zero)),
+        Pattern::new("Rotation Distillation", "matrix/rotation", "awwaeawwawawddw", Box::new(matrix::rotate)),
+        Pattern::new("Addition Distillation: Matrix", "matrix/add", "waawawaeawwaea", Box::new(matrix::add)),
+        Pattern::new("Multiplication Distillation: Matrix", "matrix/mul", "waqawawwaeaww", Box::new(matrix::multiply)),
+        Pattern::new("Transpose Purification", "matrix/transpose", "wwaeawwaede", Box::new(matrix::transpose)),
+        Pattern::new("Inverse Purification", "matrix/inverse", "wwdqdwwdqaq", Box::new(matrix::inverse)),
+        Pattern::new("Determinant Purification", "matrix/determinant", "aeawwaeawaw", Box::new(matrix::determinant)),
+        Pattern::new("Tower Distillation", "matrix/concat/vert", "awwaeawwawawdedwa", Box::new(matrix::concat_vertical)),
+        Pattern::new("Sprawling Distillation", "matrix/concat/hori", "dwwdqdwwdwdwaqawd", Box::new(matrix::concat_horizontal)),
+        Pattern::new("Toppling Gambit", "matrix/split/vert", "awdedwawawwaeawwa", Box::new(matrix::split_vertical)),
+        Pattern::new("Mitosis Gambit", "matrix/split/hori", "dwaqawdwdwwdqdwwd", Box::new(matrix::split_horizontal)),
+
+        //MoreIotas - Strings
+        Pattern::new("Concatenation Distillation", "string/add", "waawaqwawqq", Box::new(string::concat)),
+        Pattern::new("Separation Distillation", "string/split", "aqwaqa", Box::new(string::split)),
+        Pattern::new("Input Purification", "string/parse", "aqwaq", Box::new(string::parse)),
+        Pattern::new("Discoverer's Distillation", "string/find", "waqwwaqa", Box::new(string::find)),
+        Pattern::new("Winnowing Distillation", "string/sub", "aqwwaqwaad", Box::new(string::sub)),
+        Pattern::new("Length Distillation: Str", "string/len", "waqaeaq", Box::new(string::len)),
+        Pattern::new("Scrivener's Purification", "string/iota", "wawqwawaw", Box::new(string::display_iota)),
+        Pattern::new("Patternmaster's Purification", "string/action", "wdwewdwdw", Box::new(string::display_action)),
+        Pattern::new("Case Distillation", "string/case", "dwwdwwdwdd", Box::new(string::set_case)),
+
+        // Pattern::new("", "", "", Box::new(string::)),
+
+        //5D Casting - Continuum
+        Pattern::new("Selection Distillation", "continuum/get", "deeed", Box::new(continuum::get)),
+        Pattern::new("Selection Exaltation", "continuum/slice", "qaeaqwded", Box::new(continuum::slice)),
+        Pattern::new("Transmutation Distillation", "continuum/map", "dadadad", Box::new(continuum::map)),
+        Pattern::new("Natural Reflection", "continuum/stream/num", "edwaq", Box::new(continuum::number_stream)),
+        Pattern::new("Eternal Distillation", "continuum/stream/make", "aqqqaqwdaqqqaq", Box::new(continuum::make_stream)),
+        Pattern::new("Speaker's Decomposition", "continuum/deconstruct", "aaqwqaa", Box::new(continuum::deconstruct)),
+
+        //5D Casting - Cell
+        Pattern::new("Cell Purification", "cell/create", "aaaaaaaaaa", Box::new(cell::create)),
+        Pattern::new("Mutation Distillation", "cell/replace", "aaaaaaaaaaa", Box::new(cell::replace)),
+        Pattern::new("Retrieval Purification", "cell/unwrap", "aaaaaaaaaaaa", Box::new(cell::unwrap)),
+
+        //Macula
+        Pattern::new("Macula Gambit", "macula/dimensions", "aawawaa", Box::new(no_action)),
+        Pattern::new("Calligrapher's Purification", "visage/text/unbounded", "aaqdwdwd", Box::new(no_action)),
+        Pattern::new("Etch Visage", "macula/add", "wddaaddw", Box::new(no_action)),
+
+    ]
+}
+
+//attach mod names and argument types to known patterns, and add every other pattern
+//from the data so that it can at least be compiled
+fn attach_pattern_data(
+    registry: &mut PatternRegistry,
+    data: &[PatternData],
+    great_sigs: &HashMap<String, String>,
+) {
+    for entry in data {
+        match registry
+            .iter_mut()
+            .find(|pattern| pattern.internal_name == entry.internal_name)
+        {
+            Some(pattern) => {
+                pattern.start_dir = entry.start_dir;
+                pattern.mod_name = Some(entry.mod_name.clone());
+                pattern.args = entry.args.clone();
+            }
+            None => {
+                let signature = great_sigs
+                    .get(&entry.internal_name)
+                    .or(entry.signature.as_ref());
+
+                if let Some(signature) = signature {
+                    let mut pattern = Pattern::new(
+                        &entry.display_name,
+                        &entry.internal_name,
+                        signature,
+                        constructors::unsupported(&entry.display_name),
+                    );
+                    pattern.start_dir = entry.start_dir;
+                    pattern.mod_name = Some(entry.mod_name.clone());
+                    pattern.args = entry.args.clone();
+                    registry.push(pattern);
+                }
+            }
+        }
+    }
 }
 
 fn parse_bookkeeper_code(code: &str) -> String {
@@ -574,8 +664,95 @@ fn parse_bookkeeper_code(code: &str) -> String {
         .concat()
 }
 
-fn gen_number(num: f32) -> String {
-    generate_number_pattern_beam(num as i32, Bounds::new(100, 100, 100), 1, false)
-        .map(|x| x.pattern)
-        .unwrap_or("".to_string())
+//the inverse of `parse_bookkeeper_code`
+pub fn decode_bookkeeper(signature: &str) -> Option<String> {
+    let (mut code, mut rest) = match signature.strip_prefix('a') {
+        Some(rest) => ("v".to_string(), rest),
+        None => ("-".to_string(), signature),
+    };
+
+    while !rest.is_empty() {
+        let (segment, len) = match (code.ends_with('v'), rest.as_bytes()) {
+            (false, [b'w', ..]) => ('-', 1),
+            (false, [b'e', b'a', ..]) => ('v', 2),
+            (true, [b'e', ..]) => ('-', 1),
+            (true, [b'd', b'a', ..]) => ('v', 2),
+            _ => return None,
+        };
+        code.push(segment);
+        rest = &rest[len..];
+    }
+
+    Some(code)
+}
+
+//the number drawn by a Numerical Reflection, which starts with aqaa or dedd if it is negative
+pub fn decode_number(signature: &str) -> Option<f64> {
+    let (sign, digits) = match signature.split_at_checked(4)? {
+        ("aqaa", digits) => (1.0, digits),
+        ("dedd", digits) => (-1.0, digits),
+        _ => return None,
+    };
+
+    digits
+        .chars()
+        .try_fold(0.0, |number, angle| match angle {
+            'w' => Some(number + 1.0),
+            'q' => Some(number + 5.0),
+            'e' => Some(number + 10.0),
+            'a' => Some(number * 2.0),
+            'd' => Some(number / 2.0),
+            _ => None,
+        })
+        .map(|number| sign * number)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn decode_test() {
+        assert_eq!(decode_number("aqaaedwd"), Some(3.0));
+        assert_eq!(decode_number("deddeeaww"), Some(-42.0));
+        assert_eq!(decode_number("aqaa"), Some(0.0));
+        assert_eq!(decode_number("aqaqwd"), None);
+
+        for code in ["-", "v", "v-", "-v", "vv-v", "--vv-"] {
+            assert_eq!(
+                decode_bookkeeper(&parse_bookkeeper_code(code)).as_deref(),
+                Some(code)
+            );
+        }
+        assert_eq!(decode_bookkeeper("qaq"), None);
+
+        let registry = PatternRegistry::construct(&PatternRegistry::gen_default_great_sigs());
+        assert_eq!(
+            registry.decode_value("ae"),
+            Some(ActionValue::Bookkeeper("v-".to_string()))
+        );
+        //Reveal
+        assert_eq!(registry.decode_value("de"), None);
+    }
+
+    #[test]
+    fn unique_signature_test() {
+        //a builtin named differently from the mod would be added a second time from the data
+        let registry = PatternRegistry::construct(&PatternRegistry::gen_default_great_sigs());
+        let mut signatures = HashSet::new();
+        //5 Dimensional Casting's continuum patterns are drawn the same as the list ones on purpose
+        for pattern in registry.iter().filter(|pattern| {
+            !pattern.signature.is_empty() && !pattern.internal_name.starts_with("continuum/")
+        }) {
+            assert!(
+                signatures.insert(&pattern.signature),
+                "{} shares its signature {}",
+                pattern.internal_name,
+                pattern.signature
+            );
+        }
+        assert!(registry.find("2dup", &None).unwrap().mod_name.is_some());
+    }
 }
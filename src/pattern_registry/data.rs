@@ -0,0 +1,139 @@
+use std::sync::OnceLock;
+
+use serde_json::Value;
+
+use crate::iota::hex_casting::pattern::Direction;
+
+pub const DEFAULT_PATTERN_DATA: &str = include_str!("../../all_patterns.json");
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatternData {
+    pub display_name: String,
+    pub internal_name: String,
+    pub mod_name: String,
+    pub signature: Option<String>,
+    pub start_dir: Option<Direction>,
+    pub args: Option<PatternArgs>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatternArgs {
+    pub raw: String,
+    pub inputs: Vec<String>,
+    pub outputs: Vec<String>,
+}
+
+impl PatternArgs {
+    pub fn parse(raw: &str) -> PatternArgs {
+        let (inputs, outputs) = raw.split_once('→').unwrap_or((raw, ""));
+
+        PatternArgs {
+            raw: raw.to_string(),
+            inputs: split_arg_list(inputs),
+            outputs: split_arg_list(outputs),
+        }
+    }
+
+    //"many" means the pattern takes or leaves a variable number of iotas
+    pub fn arity(&self) -> Option<usize> {
+        if self.inputs.iter().any(|arg| arg == "many") {
+            None
+        } else {
+            Some(self.inputs.len())
+        }
+    }
+
+    pub fn output_count(&self) -> Option<usize> {
+        if self.outputs.iter().any(|arg| arg == "many") {
+            None
+        } else {
+            Some(self.outputs.len())
+        }
+    }
+}
+
+fn split_arg_list(list: &str) -> Vec<String> {
+    //commas inside brackets (eg. "(mote | item), vec") belong to a single argument
+    let mut args = vec![];
+    let mut current = String::new();
+    let mut depth = 0;
+
+    for char in list.chars() {
+        match char {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            ',' if depth == 0 => {
+                args.push(current.trim().to_string());
+                current = String::new();
+                continue;
+            }
+            _ => (),
+        }
+        current.push(char);
+    }
+    args.push(current.trim().to_string());
+
+    args.into_iter().filter(|arg| !arg.is_empty()).collect()
+}
+
+pub fn parse_pattern_data(source: &str) -> Result<Vec<PatternData>, String> {
+    let parsed: Value = serde_json::from_str(source).map_err(|err| err.to_string())?;
+    let entries = parsed
+        .as_object()
+        .ok_or("Expected pattern data to be an object of patterns".to_string())?;
+
+    entries
+        .iter()
+        .map(|(display_name, entry)| {
+            let get_str = |key: &str| entry.get(key).and_then(Value::as_str).map(str::to_string);
+
+            Ok(PatternData {
+                display_name: display_name.clone(),
+                internal_name: get_str("name")
+                    .ok_or(format!("Pattern '{display_name}' is missing a name"))?,
+                mod_name: get_str("modName").unwrap_or_default(),
+                signature: get_str("pattern"),
+                start_dir: get_str("direction")
+                    .map(|dir| dir.parse())
+                    .transpose()?,
+                args: get_str("args").map(|args| PatternArgs::parse(&args)),
+            })
+        })
+        .collect()
+}
+
+pub fn default_pattern_data() -> &'static [PatternData] {
+    static DEFAULT: OnceLock<Vec<PatternData>> = OnceLock::new();
+    DEFAULT.get_or_init(|| {
+        parse_pattern_data(DEFAULT_PATTERN_DATA).expect("bundled pattern data should be valid")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_args_test() {
+        let args = PatternArgs::parse("(mote | item), vec → bool");
+        assert_eq!(args.inputs, vec!["(mote | item)", "vec"]);
+        assert_eq!(args.outputs, vec!["bool"]);
+        assert_eq!(args.arity(), Some(2));
+
+        let args = PatternArgs::parse("many, number → many");
+        assert_eq!(args.arity(), None);
+        assert_eq!(args.output_count(), None);
+
+        let args = PatternArgs::parse("→ vector");
+        assert_eq!(args.arity(), Some(0));
+    }
+
+    #[test]
+    fn default_data_test() {
+        let data = default_pattern_data();
+        assert_eq!(data.len(), 362);
+        assert!(data
+            .iter()
+            .any(|entry| entry.mod_name == "HexKinetics"));
+    }
+}
@@ -0,0 +1,421 @@
+//finds the patterns that draw a number. Where a single Numerical Reflection can't draw it, the
+//number is built from several with arithmetic patterns, picking whatever draws the fewest lines
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+use hexnumgen::{generate_number_pattern_beam, Bounds};
+use serde_json::{Map, Value};
+
+use crate::iota::hex_casting::pattern::{Signature, SignatureExt};
+
+use super::decode_number;
+
+//the signatures of Numerical Reflections and the internal names of arithmetic patterns,
+//in the order they're cast
+pub type NumberEncoding = Vec<String>;
+
+//the arithmetic patterns an encoding can use, with their signatures for the cost
+const OPERATORS: [(&str, &str); 5] = [
+    ("add", "waaw"),
+    ("sub", "wddw"),
+    ("mul_dot", "waqaw"),
+    ("div_cross", "wdedw"),
+    ("pow_proj", "wedew"),
+];
+
+//the largest number given to the pattern search, anything bigger is built from smaller numbers
+const MAX_DIRECT: f64 = 1_000_000.0;
+//large integers are split into digits of this base
+const SPLIT_BASE: f64 = 1000.0;
+//how many times a single pattern can halve its number to draw a fraction
+const MAX_HALVINGS: i32 = 8;
+//the largest denominator tried when a fraction is drawn as a division
+const MAX_DENOMINATOR: i64 = 1000;
+//two Numerical Reflections and an arithmetic pattern draw at least this many lines
+const MIN_COMPOSITE_COST: usize = 15;
+
+thread_local! {
+    static CACHE: RefCell<Cache> = RefCell::new(Cache::default());
+}
+
+#[derive(Default)]
+struct Cache {
+    encodings: HashMap<u64, Option<NumberEncoding>>,
+    //whether anything was searched for since the cache was loaded
+    changed: bool,
+}
+
+//the cheapest way to draw a number, None if it can't be drawn
+pub fn encode(number: f64) -> Option<NumberEncoding> {
+    let cached = CACHE.with(|cache| cache.borrow().encodings.get(&number.to_bits()).cloned());
+    if let Some(encoding) = cached {
+        return encoding;
+    }
+
+    let encoding = search(number);
+    CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        cache.encodings.insert(number.to_bits(), encoding.clone());
+        cache.changed = true;
+    });
+    encoding
+}
+
+//a single Numerical Reflection for the number, for places it can't be split up like lists
+pub fn signature(number: f64) -> Option<String> {
+    match encode(number) {
+        Some(encoding) if encoding.len() == 1 => encoding.into_iter().next(),
+        _ => direct(number),
+    }
+}
+
+//the cost model: how many lines have to be drawn
+pub fn cost(encoding: &NumberEncoding) -> usize {
+    encoding
+        .iter()
+        .map(|step| operator(step).unwrap_or(step).len() + 1)
+        .sum()
+}
+
+//the number the patterns leave on the stack
+pub fn evaluate(encoding: &NumberEncoding) -> Option<f64> {
+    let mut stack: Vec<f64> = vec![];
+    for step in encoding {
+        let value = match operator(step) {
+            Some(_) => {
+                let (b, a) = (stack.pop()?, stack.pop()?);
+                match step.as_str() {
+                    "add" => a + b,
+                    "sub" => a - b,
+                    "mul_dot" => a * b,
+                    "div_cross" => a / b,
+                    _ => a.powf(b),
+                }
+            }
+            None => decode_number(step)?,
+        };
+        stack.push(value);
+    }
+    match stack[..] {
+        [number] => Some(number),
+        _ => None,
+    }
+}
+
+//whether the patterns can be drawn and leave the number on the stack
+fn is_valid(encoding: &NumberEncoding, number: f64) -> bool {
+    evaluate(encoding) == Some(number)
+        && encoding
+            .iter()
+            .filter(|step| operator(step).is_none())
+            .all(|step| Signature::from_sig(step).is_drawable())
+}
+
+fn operator(step: &str) -> Option<&'static str> {
+    OPERATORS
+        .iter()
+        .find(|(name, _)| *name == step)
+        .map(|(_, signature)| *signature)
+}
+
+fn search(number: f64) -> Option<NumberEncoding> {
+    if !number.is_finite() {
+        return None;
+    }
+
+    let direct = direct(number).map(|signature| vec![signature]);
+    if direct
+        .as_ref()
+        .is_some_and(|direct| cost(direct) <= MIN_COMPOSITE_COST)
+    {
+        return direct;
+    }
+
+    [
+        direct,
+        fraction(number),
+        scientific(number),
+        split(number),
+        binary(number, MAX_DIRECT),
+        negative(number),
+    ]
+    .into_iter()
+    .flatten()
+    .filter(|encoding| is_valid(encoding, number))
+    .min_by_key(cost)
+    //every number is stored as an integer times a power of two, so it can always be drawn that way
+    .or_else(|| binary(number, f64::MAX).filter(|encoding| is_valid(encoding, number)))
+}
+
+//a single pattern from the search, halving an integer to draw fractions like 0.25
+fn direct(number: f64) -> Option<String> {
+    let halvings =
+        (0..=MAX_HALVINGS).find(|halvings| (number * 2f64.powi(*halvings)).fract() == 0.0)?;
+    let scaled = number * 2f64.powi(halvings);
+    if scaled.abs() > MAX_DIRECT {
+        return None;
+    }
+
+    let generated =
+        generate_number_pattern_beam(scaled.abs() as i32, Bounds::new(100, 100, 100), 1, false)?;
+    //the digits after the prefix draw the same number either way up
+    let digits = generated.pattern.strip_prefix("aqaa")?;
+    let prefix = if scaled < 0.0 { "dedd" } else { "aqaa" };
+    let signature = format!("{prefix}{digits}{}", "d".repeat(halvings as usize));
+
+    let valid =
+        decode_number(&signature) == Some(number) && Signature::from_sig(&signature).is_drawable();
+    valid.then_some(signature)
+}
+
+//a fraction like 1/3 as a division of two integers
+fn fraction(number: f64) -> Option<NumberEncoding> {
+    if number.fract() == 0.0 {
+        return None;
+    }
+
+    //the convergents of the continued fraction are the closest fractions with small denominators
+    let (mut numerator, mut last_numerator) = (1i64, 0i64);
+    let (mut denominator, mut last_denominator) = (0i64, 1i64);
+    let mut rest = number;
+    loop {
+        let whole = rest.floor();
+        let next_numerator = (whole as i64)
+            .checked_mul(numerator)?
+            .checked_add(last_numerator)?;
+        let next_denominator = (whole as i64)
+            .checked_mul(denominator)?
+            .checked_add(last_denominator)?;
+        (last_numerator, numerator) = (numerator, next_numerator);
+        (last_denominator, denominator) = (denominator, next_denominator);
+
+        if denominator > MAX_DENOMINATOR {
+            return None;
+        }
+        if numerator as f64 / denominator as f64 == number {
+            return Some(
+                [
+                    encode(numerator as f64)?,
+                    encode(denominator as f64)?,
+                    vec!["div_cross".to_string()],
+                ]
+                .concat(),
+            );
+        }
+
+        rest = 1.0 / (rest - whole);
+        if !rest.is_finite() {
+            return None;
+        }
+    }
+}
+
+//a number with only a few digits like 1.5e20 or 0.001, as the digits times a power of ten
+fn scientific(number: f64) -> Option<NumberEncoding> {
+    let formatted = format!("{number:e}");
+    let (digits, exponent) = formatted.split_once('e')?;
+    let decimals = digits
+        .split_once('.')
+        .map_or(0, |(_, decimals)| decimals.len());
+    let digits: f64 = digits.replace('.', "").parse().ok()?;
+    let exponent = exponent.parse::<i32>().ok()? - decimals as i32;
+    if exponent == 0 || digits.abs() > MAX_DIRECT {
+        return None;
+    }
+
+    Some(
+        [
+            encode(digits)?,
+            vec![direct(10.0)?],
+            encode(exponent as f64)?,
+            vec!["pow_proj".to_string(), "mul_dot".to_string()],
+        ]
+        .concat(),
+    )
+}
+
+//a large integer with many digits, split into the ones above and below SPLIT_BASE
+fn split(number: f64) -> Option<NumberEncoding> {
+    if number.fract() != 0.0 || number.abs() <= MAX_DIRECT || number.abs() > 2f64.powi(53) {
+        return None;
+    }
+
+    let (high, low) = ((number / SPLIT_BASE).trunc(), number % SPLIT_BASE);
+    let mut encoding = [
+        encode(high)?,
+        encode(SPLIT_BASE)?,
+        vec!["mul_dot".to_string()],
+    ]
+    .concat();
+    if low != 0.0 {
+        encoding.extend(encode(low)?);
+        encoding.push("add".to_string());
+    }
+    Some(encoding)
+}
+
+//an odd integer up to max_mantissa times a power of two
+fn binary(number: f64, max_mantissa: f64) -> Option<NumberEncoding> {
+    if number == 0.0 {
+        return None;
+    }
+
+    let (mut mantissa, mut exponent) = (number, 0);
+    while mantissa.fract() != 0.0 {
+        mantissa *= 2.0;
+        exponent -= 1;
+    }
+    while mantissa % 2.0 == 0.0 {
+        mantissa /= 2.0;
+        exponent += 1;
+    }
+    if exponent == 0 || mantissa.abs() > max_mantissa {
+        return None;
+    }
+
+    Some(
+        [
+            encode(mantissa)?,
+            vec![direct(2.0)?],
+            encode(exponent as f64)?,
+            vec!["pow_proj".to_string(), "mul_dot".to_string()],
+        ]
+        .concat(),
+    )
+}
+
+//a negative number subtracted from zero, for when its digits don't fit the other way up
+fn negative(number: f64) -> Option<NumberEncoding> {
+    if number >= 0.0 {
+        return None;
+    }
+
+    Some(
+        [
+            vec![direct(0.0)?],
+            encode(-number)?,
+            vec!["sub".to_string()],
+        ]
+        .concat(),
+    )
+}
+
+//where the cache is kept when the config doesn't say
+pub fn default_cache_path() -> Option<PathBuf> {
+    let dir = env::var_os("XDG_CACHE_HOME")
+        .or_else(|| env::var_os("LOCALAPPDATA"))
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))?;
+    Some(dir.join("hexagon").join("numbers.json"))
+}
+
+//adds the encodings saved by an earlier run to the cache
+pub fn load_cache(path: &Path) -> io::Result<()> {
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err),
+    };
+    let entries: Map<String, Value> = serde_json::from_str(&source)?;
+
+    CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        for (number, encoding) in entries {
+            let (Ok(number), Value::Array(steps)) = (number.parse::<f64>(), encoding) else {
+                continue;
+            };
+            let encoding = steps
+                .iter()
+                .map(|step| step.as_str().map(str::to_string))
+                .collect::<Option<NumberEncoding>>()
+                //an edited cache can't make a hex draw the wrong number
+                .filter(|encoding| is_valid(encoding, number));
+            if let Some(encoding) = encoding {
+                cache
+                    .encodings
+                    .entry(number.to_bits())
+                    .or_insert(Some(encoding));
+            }
+        }
+    });
+    Ok(())
+}
+
+//writes the cache back if anything new was searched for
+pub fn save_cache(path: &Path) -> io::Result<()> {
+    CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        if !cache.changed {
+            return Ok(());
+        }
+
+        let entries: Map<String, Value> = cache
+            .encodings
+            .iter()
+            .filter_map(|(number, encoding)| {
+                let number = format!("{:?}", f64::from_bits(*number));
+                Some((number, Value::from(encoding.clone()?)))
+            })
+            .collect();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string(&Value::Object(entries))?)?;
+        cache.changed = false;
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_test() {
+        let steps = |steps: &[&str]| steps.iter().map(|step| step.to_string()).collect();
+
+        //entries that don't draw their number are ignored
+        let path = env::temp_dir().join("hexagon_numbers_test.json");
+        fs::write(
+            &path,
+            r#"{"2.0": ["aqaaw", "aqaaw", "add"], "7.5": ["aqaaw"]}"#,
+        )
+        .unwrap();
+        load_cache(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(encode(2.0), Some(steps(&["aqaaw", "aqaaw", "add"])));
+        assert_ne!(encode(7.5), Some(steps(&["aqaaw"])));
+        assert_eq!(evaluate(&encode(7.5).unwrap()), Some(7.5));
+
+        for number in [
+            0.0,
+            3.0,
+            -42.0,
+            0.5,
+            -0.75,
+            0.1,
+            1.0 / 3.0,
+            1e6,
+            1234567.0,
+            1.5e20,
+            2e-5,
+            1e300,
+            -1e-300,
+        ] {
+            let encoding = encode(number).unwrap_or_else(|| panic!("{number}"));
+            assert_eq!(evaluate(&encoding), Some(number), "{encoding:?}");
+        }
+        assert_eq!(encode(f64::NAN), None);
+        assert_eq!(encode(f64::INFINITY), None);
+
+        assert_eq!(cost(&steps(&["aqaaw"])), 6);
+        assert_eq!(
+            evaluate(&steps(&["aqaae", "aqaaw", "div_cross"])),
+            Some(10.0)
+        );
+    }
+}
@@ -1,4 +1,5 @@
 pub mod constructors;
+pub mod costs;
 pub mod five_dim_casting;
 pub mod hex_casting;
 pub mod hexal;
@@ -7,9 +8,13 @@ pub mod more_iotas;
 use std::rc::Rc;
 
 use crate::{
-    interpreter::{mishap::Mishap, state::State},
+    interpreter::{
+        mishap::Mishap,
+        state::{LogEntry, Stack, State},
+    },
+    iota::hex_casting::pattern::Direction,
     parser::ActionValue,
-    pattern_registry::PatternRegistry,
+    pattern_registry::{data::PatternArgs, PatternRegistry},
 };
 
 pub type ActionNoValueType =
@@ -27,12 +32,33 @@ pub enum ActionFunction {
     ActionWithValue(Rc<ActionWithValueType>),
 }
 
+//media is measured in dust, like in the hex book
+#[derive(Clone)]
+pub enum MediaCost {
+    Const(f64),
+    //the cost depends on the arguments, like the radius of an explosion
+    Formula(Rc<dyn Fn(&Stack) -> f64>),
+}
+
+impl MediaCost {
+    pub fn calculate(&self, stack: &Stack) -> f64 {
+        match self {
+            MediaCost::Const(cost) => *cost,
+            MediaCost::Formula(formula) => formula(stack),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct Pattern {
     pub display_name: String,
     pub internal_name: String,
     pub signature: String,
     pub action: ActionFunction,
+    pub start_dir: Option<Direction>,
+    pub mod_name: Option<String>,
+    pub args: Option<PatternArgs>,
+    pub cost: Option<MediaCost>,
 }
 
 impl Pattern {
@@ -47,6 +73,10 @@ impl Pattern {
             internal_name: internal_name.to_string(),
             signature: signature.to_string(),
             action: ActionFunction::ActionNoValue(Rc::new(action)),
+            start_dir: None,
+            mod_name: None,
+            args: None,
+            cost: None,
         }
     }
 
@@ -61,20 +91,45 @@ impl Pattern {
             internal_name: internal_name.to_string(),
             signature: signature.to_string(),
             action: ActionFunction::ActionWithValue(Rc::new(action)),
+            start_dir: None,
+            mod_name: None,
+            args: None,
+            cost: None,
         }
     }
 
+    pub fn with_cost(mut self, cost: MediaCost) -> Pattern {
+        self.cost = Some(cost);
+        self
+    }
+
     pub fn operate<'a>(
         &self,
         state: &'a mut State,
         pattern_registry: &PatternRegistry,
         value: &Option<ActionValue>,
     ) -> Result<&'a mut State, Mishap> {
-        match &self.action {
+        //the cost is worked out from the arguments before the spell uses them up, and like
+        //in the mod a spell the caster can't afford isn't cast at all
+        let cost = self.cost.as_ref().map(|cost| cost.calculate(&state.stack));
+        if let Some(cost) = cost {
+            state.check_media(cost)?;
+        }
+
+        let state = match &self.action {
             ActionFunction::ActionNoValue(action) => action(state, pattern_registry),
             ActionFunction::ActionWithValue(action) => {
                 action(state, pattern_registry, value.as_ref())
             }
+        }?;
+
+        if let Some(cost) = cost {
+            state.spend_media(cost)?;
+            state.log.push_back(LogEntry::Spell {
+                name: self.display_name.clone(),
+                cost,
+            });
         }
+        Ok(state)
     }
 }
@@ -288,3 +288,12 @@ pub fn push_const(iota: Rc<dyn Iota>) -> Box<ActionNoValueType> {
         },
     )
 }
+
+pub fn unsupported(display_name: &str) -> Box<ActionNoValueType> {
+    let name = display_name.to_string();
+    Box::new(
+        move |_: &mut State, _: &PatternRegistry| -> Result<&mut State, Mishap> {
+            Err(Mishap::UnsupportedAction { name: name.clone() })
+        },
+    )
+}
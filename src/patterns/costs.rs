@@ -0,0 +1,88 @@
+//media costs of spells that depend on their arguments, in dust
+use std::rc::Rc;
+
+use crate::{
+    interpreter::state::{Stack, StackExt},
+    iota::hex_casting::{number::NumberIota, vector::VectorIota},
+    patterns::MediaCost,
+};
+
+//arguments of the wrong type cost nothing, the spell mishaps before any media is spent
+fn number_arg(stack: &Stack, index: usize, arg_count: usize) -> f64 {
+    stack
+        .get_iota::<NumberIota>(index, arg_count)
+        .map_or(0.0, |num| *num)
+}
+
+fn vector_arg(stack: &Stack, index: usize, arg_count: usize) -> VectorIota {
+    stack
+        .get_iota::<VectorIota>(index, arg_count)
+        .map_or(VectorIota::zeros(), |vec| *vec)
+}
+
+pub fn explosion(fire: bool) -> MediaCost {
+    MediaCost::Formula(Rc::new(move |stack| {
+        let radius = number_arg(stack, 1, 2).clamp(0.0, 10.0);
+        3.0 * radius + if fire { 1.0 } else { 0.125 }
+    }))
+}
+
+pub fn impulse() -> MediaCost {
+    MediaCost::Formula(Rc::new(|stack| vector_arg(stack, 1, 2).norm_squared()))
+}
+
+//an amethyst shard for every two blocks
+pub fn blink() -> MediaCost {
+    MediaCost::Formula(Rc::new(|stack| {
+        5.0 * (number_arg(stack, 1, 2).abs() * 0.5).round()
+    }))
+}
+
+//for every second of duration, multiplied by the potency squared if the potion has one,
+//or cubed for the great spells
+pub fn potion(base: f64, potency: bool, cubic: bool) -> MediaCost {
+    MediaCost::Formula(Rc::new(move |stack| {
+        let arg_count = if potency { 3 } else { 2 };
+        let duration = number_arg(stack, 1, arg_count).max(0.0);
+        let potency = if potency {
+            number_arg(stack, 2, arg_count).clamp(1.0, 127.0)
+        } else {
+            1.0
+        };
+        base * duration * potency.powi(if cubic { 3 } else { 2 })
+    }))
+}
+
+pub fn flight() -> MediaCost {
+    MediaCost::Formula(Rc::new(|stack| {
+        0.25 * number_arg(stack, 1, 3).max(0.0) * number_arg(stack, 2, 3).max(0.0)
+    }))
+}
+
+//the media given to the wisp comes out of the caster's budget too
+pub fn summon_wisp() -> MediaCost {
+    MediaCost::Formula(Rc::new(|stack| 3.0 + number_arg(stack, 2, 3).max(0.0)))
+}
+
+#[cfg(test)]
+mod tests {
+    use im::vector;
+
+    use crate::iota::{hex_casting::null::NullIota, Iota};
+
+    use super::*;
+
+    #[test]
+    fn potion_test() {
+        let stack: Stack = vector![
+            Rc::new(NullIota) as Rc<dyn Iota>,
+            Rc::new(10.0),
+            Rc::new(2.0)
+        ];
+        //White Sun's Nadir and Zenith for 10 seconds at potency 2
+        assert_eq!(potion(0.1, true, false).calculate(&stack), 0.1 * 10.0 * 4.0);
+        assert_eq!(potion(1.0, true, true).calculate(&stack), 10.0 * 8.0);
+        //Blue Sun's Nadir for 2 seconds
+        assert_eq!(potion(0.2, false, false).calculate(&stack), 0.2 * 2.0);
+    }
+}
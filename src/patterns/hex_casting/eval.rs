@@ -32,9 +32,12 @@ pub fn eval<'a>(
 
     match arg {
         Either3::L(list) => {
-            state
-                .continuation
-                .push_back(ContinuationFrame::EndEval(FrameEndEval {}));
+            //a tail call is already inside a break boundary
+            if !matches!(state.continuation.last(), Some(ContinuationFrame::EndEval(_))) {
+                state
+                    .continuation
+                    .push_back(ContinuationFrame::EndEval(FrameEndEval {}));
+            }
             state
                 .continuation
                 .push_back(ContinuationFrame::Evaluate(FrameEvaluate {
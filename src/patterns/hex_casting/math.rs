@@ -1,4 +1,5 @@
 use im::{vector, Vector};
+use rand::Rng;
 use std::ops::Deref;
 use std::{f64::consts::PI, ops::Not, rc::Rc};
 
@@ -110,6 +111,20 @@ pub fn div_cross<'a>(
             .stack
             .get_iota_a_or_b::<NumberIota, VectorIota>(1, arg_count)?,
     );
+    let dividend = match &iotas {
+        (Either::L(num), Either::L(divisor)) if **divisor == 0.0 => Some(num.display()),
+        (Either::R(vec), Either::L(divisor)) | (Either::L(divisor), Either::R(vec))
+            if **divisor == 0.0 =>
+        {
+            Some(vec.display())
+        }
+        _ => None,
+    };
+    if let Some(dividend) = dividend {
+        return Err(Mishap::MathematicalError {
+            operation: format!("divide {dividend} by 0.000"),
+        });
+    }
     state.stack.remove_args(&arg_count);
 
     let operation_result: Rc<dyn Iota> = match iotas {
@@ -157,6 +172,13 @@ pub fn pow_proj<'a>(
             .stack
             .get_iota_a_or_b::<NumberIota, VectorIota>(1, arg_count)?,
     );
+    if let (Either::L(base), Either::L(exponent)) = &iotas {
+        if **base == 0.0 && **exponent < 0.0 {
+            return Err(Mishap::MathematicalError {
+                operation: format!("raise 0.000 to the power of {}", exponent.display()),
+            });
+        }
+    }
     state.stack.remove_args(&arg_count);
 
     let operation_result: Rc<dyn Iota> = match iotas {
@@ -582,6 +604,15 @@ pub fn logarithm<'a>(
         *state.stack.get_iota::<NumberIota>(0, arg_count)?,
         *state.stack.get_iota::<NumberIota>(1, arg_count)?,
     );
+    if iotas.0 == 0.0 || iotas.1 == 0.0 || iotas.1 == 1.0 {
+        return Err(Mishap::MathematicalError {
+            operation: format!(
+                "get the logarithm of {} in base {}",
+                iotas.0.display(),
+                iotas.1.display()
+            ),
+        });
+    }
 
     state.stack.remove_args(&arg_count);
 
@@ -601,6 +632,11 @@ pub fn modulo<'a>(
         *state.stack.get_iota::<NumberIota>(0, arg_count)?,
         *state.stack.get_iota::<NumberIota>(1, arg_count)?,
     );
+    if iotas.1 == 0.0 {
+        return Err(Mishap::MathematicalError {
+            operation: format!("take {} modulo 0.000", iotas.0.display()),
+        });
+    }
 
     state.stack.remove_args(&arg_count);
 
@@ -833,7 +869,7 @@ pub fn random<'a>(
     state: &'a mut State,
     _pattern_registry: &PatternRegistry,
 ) -> Result<&'a mut State, Mishap> {
-    let rand = rand::random::<f64>();
+    let rand = state.rng.gen::<f64>();
 
     state.stack.push_back(Rc::new(rand));
 
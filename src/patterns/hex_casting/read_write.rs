@@ -65,7 +65,6 @@ pub fn craft_trinket<'a>(
         state.stack.get_iota::<EntityIota>(0, arg_count)?,
         state.stack.get_iota::<ListIota>(1, arg_count)?,
     );
-    state.stack.remove_args(&arg_count);
 
     let player = state.entities.get_mut("Caster").unwrap();
 
@@ -73,6 +72,7 @@ pub fn craft_trinket<'a>(
         Holding::Trinket(None) => Box::new(Holding::Trinket(Some(iotas.1))),
         _ => Err(Mishap::HoldingIncorrectItem)?,
     };
+    state.stack.remove_args(&arg_count);
 
     Ok(state)
 }
@@ -86,7 +86,6 @@ pub fn craft_cypher<'a>(
         state.stack.get_iota::<EntityIota>(0, arg_count)?,
         state.stack.get_iota::<ListIota>(1, arg_count)?,
     );
-    state.stack.remove_args(&arg_count);
 
     let player = state.entities.get_mut("Caster").unwrap();
 
@@ -94,6 +93,7 @@ pub fn craft_cypher<'a>(
         Holding::Trinket(None) => Box::new(Holding::Cypher(Some(iotas.1))),
         _ => Err(Mishap::HoldingIncorrectItem)?,
     };
+    state.stack.remove_args(&arg_count);
 
     Ok(state)
 }
@@ -107,7 +107,6 @@ pub fn craft_artifact<'a>(
         state.stack.get_iota::<EntityIota>(0, arg_count)?,
         state.stack.get_iota::<ListIota>(1, arg_count)?,
     );
-    state.stack.remove_args(&arg_count);
 
     let player = state.entities.get_mut("Caster").unwrap();
 
@@ -115,6 +114,7 @@ pub fn craft_artifact<'a>(
         Holding::Trinket(None) => Box::new(Holding::Artifact(Some(iotas.1))),
         _ => Err(Mishap::HoldingIncorrectItem)?,
     };
+    state.stack.remove_args(&arg_count);
 
     Ok(state)
 }
@@ -141,7 +141,6 @@ pub fn write<'a>(
 ) -> Result<&'a mut State, Mishap> {
     let arg_count = 1;
     let iota = state.stack.get_any_iota(0, arg_count)?;
-    state.stack.remove_args(&arg_count);
 
     let player = state.entities.get_mut("Caster").unwrap();
 
@@ -149,6 +148,7 @@ pub fn write<'a>(
         Holding::Focus(_) => Box::new(Holding::Focus(Some(iota))),
         _ => Err(Mishap::HoldingIncorrectItem)?,
     };
+    state.stack.remove_args(&arg_count);
 
     Ok(state)
 }
@@ -200,7 +200,6 @@ pub fn akashic_read<'a>(
         state.stack.get_iota::<VectorIota>(0, arg_count)?,
         state.stack.get_iota::<PatternIota>(1, arg_count)?,
     );
-    state.stack.remove_args(&arg_count);
 
     let location = &[(iotas.0).x as i32, (iotas.0).y as i32, (iotas.0).z as i32];
 
@@ -210,6 +209,7 @@ pub fn akashic_read<'a>(
         Some(library) => library.get(&iotas.1.signature).unwrap_or(&null).clone(),
         None => Err(Mishap::NoAkashicRecord{location: iotas.0})?,
     };
+    state.stack.remove_args(&arg_count);
 
     state.stack.push_back(operation_result);
 
@@ -226,7 +226,6 @@ pub fn akashic_write<'a>(
         (*state.stack.get_iota::<PatternIota>(1, arg_count)?).clone(),
         state.stack.get_any_iota(2, arg_count)?.clone(),
     );
-    state.stack.remove_args(&arg_count);
 
     let location = &[(iotas.0).x as i32, (iotas.0).y as i32, (iotas.0).z as i32];
 
@@ -234,6 +233,7 @@ pub fn akashic_write<'a>(
         Some(library) => library.insert((iotas.1).signature, iotas.2),
         None => Err(Mishap::NoAkashicRecord{location: iotas.0})?,
     };
+    state.stack.remove_args(&arg_count);
 
     Ok(state)
 }
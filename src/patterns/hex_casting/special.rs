@@ -1,13 +1,13 @@
 use std::rc::Rc;
 
 use im::{vector, Vector};
-use rand::{seq::SliceRandom, thread_rng};
+use rand::seq::SliceRandom;
 
 use crate::{
     interpreter::{
         mishap::Mishap,
         push_pattern,
-        state::{StackExt, State},
+        state::{LogEntry, StackExt, State},
     },
     iota::hex_casting::pattern::PatternIota,
     parser::{ActionValue, Location},
@@ -113,7 +113,7 @@ pub fn print<'a>(
     _pattern_registry: &PatternRegistry,
 ) -> Result<&'a mut State, Mishap> {
     let iota = state.stack.get_any_iota(0, 1)?;
-    println!("{}", iota.display());
+    state.log.push_back(LogEntry::Reveal(iota));
     Ok(state)
 }
 
@@ -129,10 +129,8 @@ pub fn beep<'a>(
     }
 
     let notes = ["beep", "boop"];
-    let mut rng = thread_rng();
-
-    let note = notes.choose(&mut rng).unwrap();
-    println!("{note}");
+    let note = notes.choose(&mut *state.rng).unwrap();
+    state.log.push_back(LogEntry::Note(note.to_string()));
 
     Ok(state)
 }
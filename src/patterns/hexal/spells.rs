@@ -6,7 +6,7 @@ use crate::{
     interpreter::{
         continuation::iota_list_to_ast_node_list,
         mishap::Mishap,
-        state::{Entity, EntityType, Holding, StackExt, State, Wisp},
+        state::{Entity, EntityType, Holding, LogEntry, StackExt, State, Wisp},
     },
     iota::{
         hex_casting::{
@@ -28,20 +28,25 @@ pub fn particles<'a>(
         .get_iota_a_or_b::<VectorIota, ListIota>(0, arg_count)?;
     state.stack.remove_args(&arg_count);
 
-    match arg {
-        crate::interpreter::state::Either::L(_) => (),
+    let positions = match arg {
+        crate::interpreter::state::Either::L(position) => Vector::unit(*position),
         crate::interpreter::state::Either::R(list) => {
+            let mut positions = Vector::new();
             for iota in (*list).clone() {
-                iota.clone()
+                let position = iota
+                    .clone()
                     .downcast_rc::<VectorIota>()
                     .map_err(|_| Mishap::IncorrectIota {
                         index: 0,
                         expected: "List of vectors".to_string(),
                         received: iota.clone(),
                     })?;
+                positions.push_back(*position);
             }
+            positions
         }
-    }
+    };
+    state.log.push_back(LogEntry::Particles(positions));
 
     Ok(state)
 }
@@ -0,0 +1,352 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, BufRead, Write},
+    rc::Rc,
+    thread,
+    time::Duration,
+};
+
+use im::Vector;
+use owo_colors::OwoColorize;
+
+use crate::{
+    compiler::compile_node,
+    interpreter::{
+        continuation::{ContinuationFrame, FrameEvaluate},
+        error::print_interpreter_error,
+        init_state,
+        mishap::Mishap,
+        run_wisps,
+        state::State,
+        step_vm, WISP_TICK,
+    },
+    iota::{
+        hex_casting::{list::ListIota, null::NullIota},
+        Iota,
+    },
+    parse_config::{parse_config, Config},
+    parser::{parse_with_macros, AstNode, Location, Macros, ParseErrors},
+    pattern_registry::{PatternRegistry, PatternRegistryExt},
+};
+
+const REPL_HELP: &str = "\
+Commands:
+  :stack                  Print the stack
+  :undo                   Undo the last input
+  :reset                  Start over with an empty stack and no variables or macros
+  :config <path>          Load a config file and start over
+  :help                   Print help
+  :quit                   Stop the repl
+
+Anything else is run as hexagon code. Lines with unclosed brackets are continued on the next line.
+Wisps tick for a second after every input, and carry on after the next one.";
+
+//ticks the wisps get after every input, so a cyclic wisp doesn't block the repl
+const WISP_TICKS_PER_INPUT: usize = 20;
+
+#[derive(Debug)]
+pub enum ReplError {
+    Parse(ParseErrors),
+    Mishap(Mishap, Location, String),
+}
+
+//keeps a single state alive across inputs, so a hex can be written a few patterns at a time
+pub struct Repl {
+    pub state: State,
+    config: Config,
+    pattern_registry: PatternRegistry,
+    macros: Macros,
+    //the state and macros before every input, for undo
+    history: Vec<(State, Macros)>,
+    //how long to wait between wisp ticks
+    wisp_tick: Duration,
+}
+
+impl Repl {
+    pub fn new(config: Config) -> Repl {
+        let pattern_registry = PatternRegistry::from_config(&config);
+        let mut repl = Repl {
+            state: State::default(),
+            config,
+            pattern_registry,
+            macros: HashMap::new(),
+            history: vec![],
+            wisp_tick: WISP_TICK,
+        };
+        repl.reset();
+        repl.history.clear();
+        repl
+    }
+
+    pub fn reset(&mut self) {
+        self.history.push((self.state.clone(), self.macros.clone()));
+        //an empty program can't fail to compile
+        self.state = init_state(
+            AstNode::Program(vec![]),
+            &self.config,
+            &self.pattern_registry,
+            &HashMap::new(),
+        )
+        .unwrap();
+        self.macros = HashMap::new();
+    }
+
+    pub fn set_config(&mut self, config: Config) {
+        self.pattern_registry = PatternRegistry::from_config(&config);
+        self.config = config;
+        self.reset();
+    }
+
+    //returns false if there is nothing to undo
+    pub fn undo(&mut self) -> bool {
+        match self.history.pop() {
+            Some((state, macros)) => {
+                self.state = state;
+                self.macros = macros;
+                true
+            }
+            None => false,
+        }
+    }
+
+    //a mishap changes the stack the way it does in-game and stops the rest of the input
+    pub fn eval(&mut self, source: &str) -> Result<(), ReplError> {
+        let (ast, macros, _) = parse_with_macros(
+            source,
+            "<repl>",
+            &self.pattern_registry,
+            &mut self.config.entities.clone(),
+            self.macros.clone(),
+        )
+        .map_err(ReplError::Parse)?;
+
+        self.history.push((self.state.clone(), self.macros.clone()));
+        self.macros = macros;
+
+        let result = self.run(ast);
+        if result.is_err() {
+            self.state.continuation.clear();
+        }
+        result
+            .map_err(|(mishap, location, caused_by)| ReplError::Mishap(mishap, location, caused_by))
+    }
+
+    fn run(&mut self, ast: AstNode) -> Result<(), (Mishap, Location, String)> {
+        //new variables are given a place in the ravenmind
+        compile_node(
+            &ast,
+            &mut self.state.heap,
+            0,
+            &self.pattern_registry,
+            &self.macros,
+        )?;
+        if let Some(ravenmind) = self
+            .state
+            .ravenmind
+            .as_ref()
+            .and_then(|ravenmind| ravenmind.clone().downcast_rc::<ListIota>().ok())
+        {
+            let mut ravenmind = (*ravenmind).clone();
+            let null: Rc<dyn Iota> = Rc::new(NullIota);
+            while ravenmind.len() < self.state.heap.len() {
+                ravenmind.push_back(null.clone());
+            }
+            self.state.ravenmind = Some(Rc::new(ravenmind));
+        }
+
+        let AstNode::Program(nodes) = ast else {
+            unreachable!()
+        };
+        //the limits apply to every input on its own
+        self.state.op_count = 0;
+        self.state
+            .continuation
+            .push_back(ContinuationFrame::Evaluate(FrameEvaluate {
+                nodes_queue: Vector::from(nodes),
+            }));
+
+        while !self.state.continuation.is_empty() {
+            step_vm(&mut self.state, &self.pattern_registry, &self.macros)?;
+        }
+        //wisps tick about as often as they do in-game, the ones still alive are left for the next input
+        let mut ticks = 0;
+        let wisp_tick = self.wisp_tick;
+        match run_wisps(&mut self.state, &self.pattern_registry, &self.macros, &mut |_| {
+            thread::sleep(wisp_tick);
+            ticks += 1;
+            ticks < WISP_TICKS_PER_INPUT
+        }) {
+            Err((Mishap::Interrupted, ..)) => Ok(()),
+            result => result,
+        }
+    }
+}
+
+//whether the input has brackets that haven't been closed yet
+fn is_incomplete(source: &str) -> bool {
+    let depth = source.chars().fold(0, |depth, char| match char {
+        '{' | '[' | '(' => depth + 1,
+        '}' | ']' | ')' => depth - 1,
+        _ => depth,
+    });
+    depth > 0
+}
+
+pub fn serve(config: Config) -> io::Result<()> {
+    let mut repl = Repl::new(config);
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+
+    println!(
+        "{}",
+        "Hexagon repl, type ':help' for a list of commands".bold()
+    );
+
+    loop {
+        print!("{} ", "(hexagon)".magenta().bold());
+        io::stdout().flush()?;
+
+        let Some(mut input) = lines.next().transpose()? else {
+            //end of input
+            return Ok(());
+        };
+        while is_incomplete(&input) {
+            print!("{} ", "...".magenta().bold());
+            io::stdout().flush()?;
+            match lines.next().transpose()? {
+                Some(line) => input = format!("{input}\n{line}"),
+                None => return Ok(()),
+            }
+        }
+
+        let (command, arg) = input.trim().split_once(' ').unwrap_or((input.trim(), ""));
+        match command {
+            "" => (),
+            ":stack" => println!("{}", repl.state.stack.display()),
+            ":undo" => {
+                if repl.undo() {
+                    print_state(&repl.state);
+                } else {
+                    eprintln!("{} nothing to undo", "Error:".red().bold());
+                }
+            }
+            ":reset" => {
+                repl.reset();
+                print_state(&repl.state);
+            }
+            ":config" => match fs::read_to_string(arg.trim())
+                .map_err(|err| err.to_string())
+                .and_then(parse_config)
+            {
+                Ok(config) => {
+                    repl.set_config(config);
+                    println!("Loaded {}", arg.trim());
+                }
+                Err(err) => eprintln!("{} {}: {err}", "Error:".red().bold(), arg.trim()),
+            },
+            ":help" => println!("{REPL_HELP}"),
+            ":quit" => return Ok(()),
+            _ if command.starts_with(':') => eprintln!(
+                "{} unknown command '{command}', type ':help' for a list of commands",
+                "Error:".red().bold()
+            ),
+            _ => {
+                let printed = repl.state.log.len();
+                let mishaps = repl.state.mishaps.len();
+                let result = repl.eval(&input);
+
+                for line in repl
+                    .state
+                    .log
+                    .iter()
+                    .skip(printed)
+                    .filter_map(|entry| entry.output())
+                {
+                    println!("{line}");
+                }
+                //mishaps that casting carried on after, like the ones of wisps
+                for mishap in repl.state.mishaps.iter().skip(mishaps) {
+                    let (mishap, _, caused_by) = mishap.clone();
+                    print_interpreter_error((mishap, Location::Unknown, caused_by), &[]);
+                }
+                match result {
+                    Ok(()) => (),
+                    Err(ReplError::Parse(errors)) => {
+                        for err in errors {
+                            eprintln!("{}\n{}", "Parsing Error:".red().bold(), err);
+                        }
+                        continue;
+                    }
+                    //the line of the mishap could be in an earlier input, so it isn't shown
+                    Err(ReplError::Mishap(mishap, _, caused_by)) => {
+                        print_interpreter_error((mishap, Location::Unknown, caused_by), &[])
+                    }
+                }
+                print_state(&repl.state);
+            }
+        }
+    }
+}
+
+fn print_state(state: &State) {
+    println!("{} {}", "stack:".bold(), state.stack.display());
+    let ravenmind = state
+        .ravenmind
+        .as_ref()
+        .map_or("None".to_string(), |ravenmind| ravenmind.display());
+    println!("{} {ravenmind}", "ravenmind:".bold());
+    for (name, value) in state.variables() {
+        println!("{} = {value}", name.bold());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repl_test() {
+        let mut repl = Repl::new(Config::default());
+        repl.eval("Numerical Reflection: 1\nStore($x)").unwrap();
+        repl.eval("#define Double (SOUTH_EAST edd) {\n    Gemini Decomposition\n    Additive Distillation\n}").unwrap();
+        repl.eval("$x\nDouble").unwrap();
+        assert_eq!(repl.state.stack.display(), "[2.000]");
+        assert_eq!(
+            repl.state.variables(),
+            vec![("$x".to_string(), "1.000".to_string())]
+        );
+
+        //the stack is left the way the mishap changed it
+        assert!(matches!(
+            repl.eval("Additive Distillation"),
+            Err(ReplError::Mishap(Mishap::NotEnoughIotas { .. }, ..))
+        ));
+        assert_eq!(repl.state.stack.display(), "[2.000, Garbage]");
+        assert!(repl.undo());
+        assert_eq!(repl.state.stack.display(), "[2.000]");
+
+        assert!(matches!(
+            repl.eval("Numerical Reflection:"),
+            Err(ReplError::Parse(_))
+        ));
+        repl.reset();
+        assert!(repl.state.stack.is_empty());
+        assert!(repl.undo());
+        assert_eq!(repl.state.stack.display(), "[2.000]");
+    }
+
+    #[test]
+    fn wisp_test() {
+        let mut repl = Repl::new(Config::default());
+        repl.wisp_tick = Duration::ZERO;
+
+        //a cyclic wisp doesn't stop the repl, it carries on after the next input
+        let wisp = "{\n    Numerical Reflection: 1\n    Reveal\n}\nVector Reflection Zero\nNumerical Reflection: 10\nSummon Cyclic Wisp";
+        repl.eval(wisp).unwrap();
+        assert_eq!(repl.state.log.len(), WISP_TICKS_PER_INPUT + 1);
+        assert_eq!(repl.state.wisps.len(), 1);
+        repl.eval("Numerical Reflection: 2").unwrap();
+        assert_eq!(repl.state.log.len(), 2 * WISP_TICKS_PER_INPUT + 1);
+    }
+}
@@ -0,0 +1,429 @@
+//golden file tests: hexes with comments saying what running them should result in, like
+//  //expect stack: [1, (0, 0, 0)]
+//  //expect ravenmind: [Null]
+//  //expect output: "hello"
+//  //expect mishap at 3: Expected 1 or more arguments but the stack was only 0 tall
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use owo_colors::OwoColorize;
+use pest::Parser;
+
+use crate::{
+    cli::ExitStatus,
+    interpreter::{
+        interpret,
+        mishap::Mishap,
+        state::{LogEntry, State},
+    },
+    iota::Iota,
+    parse_config::Config,
+    parser::{parse, parse_iota, HexParser, Location, Rule, SourceFile},
+    pattern_registry::{PatternRegistry, PatternRegistryExt},
+};
+
+const ANNOTATION: &str = "//expect ";
+
+#[derive(Debug, Default)]
+pub struct Expectations {
+    pub stack: Option<Rc<dyn Iota>>,
+    pub ravenmind: Option<Rc<dyn Iota>>,
+    //only checked if there is an output annotation, every line printed has to be listed
+    pub output: Option<Vec<String>>,
+    //every mishap has to be expected, a hex without mishap annotations must not mishap
+    pub mishaps: Vec<ExpectedMishap>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExpectedMishap {
+    pub message: String,
+    //an included file (the test file if there isn't one), line and column
+    pub location: Option<(Option<String>, usize, Option<usize>)>,
+}
+
+impl ExpectedMishap {
+    fn matches(&self, mishap: &Mishap, location: &Location, sources: &[SourceFile]) -> bool {
+        let got_col = match location {
+            Location::Line(_, col) | Location::File(_, _, col) => Some(*col),
+            Location::Unknown | Location::List(_) => None,
+        };
+        let location_matches = match (&self.location, location.file_line()) {
+            (None, _) => true,
+            (Some((path, line, col)), Some((got_file, got_line))) => {
+                //files are found the same way as the debugger's breakpoints
+                let file_matches = match path {
+                    Some(path) => sources.get(got_file).is_some_and(|source| {
+                        got_file != 0 && (source.path == *path || source.path.ends_with(path))
+                    }),
+                    None => got_file == 0,
+                };
+                file_matches && *line == got_line && col.is_none_or(|col| Some(col) == got_col)
+            }
+            (Some(_), None) => false,
+        };
+        location_matches && self.message == mishap.error_message()
+    }
+
+    fn display(&self) -> String {
+        let file = |path: &Option<String>| {
+            path.as_ref()
+                .map_or(String::new(), |path| format!("{path}:"))
+        };
+        match &self.location {
+            Some((path, line, Some(col))) => {
+                format!("{} at {}{line}:{col}", self.message, file(path))
+            }
+            Some((path, line, None)) => format!("{} at {}{line}", self.message, file(path)),
+            None => self.message.clone(),
+        }
+    }
+}
+
+pub fn parse_expectations(
+    source: &str,
+    pattern_registry: &PatternRegistry,
+    config: &Config,
+) -> Result<Expectations, String> {
+    let mut expectations = Expectations::default();
+
+    for (index, line) in source.lines().enumerate() {
+        let Some(annotation) = line.trim().strip_prefix(ANNOTATION) else {
+            continue;
+        };
+        let line_number = index + 1;
+        let error = |message: String| format!("line {line_number}: {message}");
+
+        let (kind, rest) = match annotation.find([' ', ':']) {
+            Some(index) => annotation.split_at(index),
+            None => (annotation, ""),
+        };
+
+        let (location, value) = match rest.strip_prefix(" at ") {
+            Some(rest) if kind == "mishap" => {
+                let (location, value) = rest
+                    .split_once(": ")
+                    .ok_or(error("expected ':' after the location".to_string()))?;
+                (Some(parse_location(location).map_err(error)?), value.trim())
+            }
+            _ => {
+                let value = rest
+                    .strip_prefix(':')
+                    .ok_or(error(format!("expected ':' after '{kind}'")))?;
+                (None, value.trim())
+            }
+        };
+
+        match kind {
+            "stack" => {
+                expectations.stack =
+                    Some(parse_expected_iota(value, pattern_registry, config).map_err(error)?)
+            }
+            "ravenmind" => {
+                expectations.ravenmind =
+                    Some(parse_expected_iota(value, pattern_registry, config).map_err(error)?)
+            }
+            "output" => expectations
+                .output
+                .get_or_insert(vec![])
+                .push(parse_output(value)),
+            "mishap" => expectations.mishaps.push(ExpectedMishap {
+                message: value.to_string(),
+                location,
+            }),
+            _ => Err(error(format!(
+                "unknown expectation '{kind}' (expected one of: stack, ravenmind, output, mishap)"
+            )))?,
+        }
+    }
+
+    Ok(expectations)
+}
+
+fn parse_location(location: &str) -> Result<(Option<String>, usize, Option<usize>), String> {
+    let invalid = || {
+        format!(
+            "invalid location '{location}' (expected [<file>:]<line> or [<file>:]<line>:<column>)"
+        )
+    };
+    let number = |part: &str| part.parse::<usize>().map_err(|_| invalid());
+
+    let parts: Vec<&str> = location.rsplitn(3, ':').collect();
+    match parts[..] {
+        [line] => Ok((None, number(line)?, None)),
+        //the first part is a line if it's a number and a file if it isn't
+        [col, line] if line.parse::<usize>().is_ok() => {
+            Ok((None, number(line)?, Some(number(col)?)))
+        }
+        [line, path] => Ok((Some(path.to_string()), number(line)?, None)),
+        [col, line, path] => Ok((Some(path.to_string()), number(line)?, Some(number(col)?))),
+        _ => Err(invalid()),
+    }
+}
+
+fn parse_expected_iota(
+    value: &str,
+    pattern_registry: &PatternRegistry,
+    config: &Config,
+) -> Result<Rc<dyn Iota>, String> {
+    let invalid = || format!("invalid iota '{value}'");
+    let pair = HexParser::parse(Rule::Iota, value)
+        .map_err(|_| invalid())?
+        .next()
+        .unwrap();
+
+    //the whole value has to be an iota, not just the start of it
+    if pair.as_str().len() != value.len() {
+        Err(invalid())?
+    }
+
+    parse_iota(
+        pair,
+        pattern_registry,
+        &mut config.entities.clone(),
+        &HashMap::new(),
+    )
+    .map_err(|_| invalid())
+}
+
+//output lines can be quoted to keep leading or trailing whitespace
+fn parse_output(value: &str) -> String {
+    if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+        snailquote::unescape(value).unwrap_or(value.to_string())
+    } else {
+        value.to_string()
+    }
+}
+
+//runs a hex and returns how its result differs from what was expected, nothing if it passed
+pub fn run_test(source: &str, source_path: &str, config: &Config) -> Vec<String> {
+    let pattern_registry = PatternRegistry::from_config(config);
+
+    let expectations = match parse_expectations(source, &pattern_registry, config) {
+        Ok(expectations) => expectations,
+        Err(err) => return vec![err],
+    };
+
+    let parse_result = parse(
+        source,
+        source_path,
+        &pattern_registry,
+        &mut config.entities.clone(),
+    );
+    let (ast, macros, sources) = match parse_result {
+        Ok(result) => result,
+        Err(errors) => return errors.iter().map(|err| err.to_string()).collect(),
+    };
+
+    //the last mishap is the one that stopped the hex, if any did
+    let (state, mishaps) = match interpret(ast, config, macros) {
+        Ok(state) => {
+            let mishaps = state.mishaps.clone();
+            (state, mishaps)
+        }
+        Err((mishap, location, caused_by, state)) => {
+            let mut mishaps = state.mishaps.clone();
+            mishaps.push_back((mishap, location, caused_by));
+            (state, mishaps)
+        }
+    };
+
+    let mut failures = vec![];
+    let mut compare = |what: &str, expected: String, got: String| {
+        failures.push(format!("{what}\n  expected: {expected}\n  got:      {got}"))
+    };
+
+    if let Some(expected) = &expectations.stack {
+        let got: Rc<dyn Iota> = Rc::new(state.stack.clone());
+        if !expected.tolerates_other(got.as_ref()) {
+            compare("stack", expected.display(), got.display());
+        }
+    }
+
+    if let Some(expected) = &expectations.ravenmind {
+        let matches = state
+            .ravenmind
+            .as_ref()
+            .is_some_and(|got| expected.tolerates_other(got.as_ref()));
+        if !matches {
+            compare("ravenmind", expected.display(), display_ravenmind(&state));
+        }
+    }
+
+    if let Some(expected) = &expectations.output {
+        let got: Vec<String> = state.log.iter().filter_map(LogEntry::output).collect();
+        if *expected != got {
+            compare("output", format!("{expected:?}"), format!("{got:?}"));
+        }
+    }
+
+    let mishaps_match =
+        expectations.mishaps.len() == mishaps.len()
+            && expectations.mishaps.iter().zip(mishaps.iter()).all(
+                |(expected, (mishap, location, _))| expected.matches(mishap, location, &sources),
+            );
+    if !mishaps_match {
+        let expected: Vec<String> = expectations
+            .mishaps
+            .iter()
+            .map(ExpectedMishap::display)
+            .collect();
+        let got: Vec<String> = mishaps
+            .iter()
+            .map(|(mishap, location, _)| display_mishap(mishap, location, &sources))
+            .collect();
+        compare("mishaps", display_list(&expected), display_list(&got));
+    }
+
+    failures
+}
+
+fn display_ravenmind(state: &State) -> String {
+    state
+        .ravenmind
+        .as_ref()
+        .map_or("None".to_string(), |ravenmind| ravenmind.display())
+}
+
+fn display_mishap(mishap: &Mishap, location: &Location, sources: &[SourceFile]) -> String {
+    match location {
+        Location::Line(line, col) => format!("{} at {line}:{col}", mishap.error_message()),
+        Location::File(file, line, col) => format!(
+            "{} at {}:{line}:{col}",
+            mishap.error_message(),
+            sources[*file].path
+        ),
+        Location::Unknown | Location::List(_) => mishap.error_message(),
+    }
+}
+
+fn display_list(items: &[String]) -> String {
+    if items.is_empty() {
+        "none".to_string()
+    } else {
+        items.join(", ")
+    }
+}
+
+//a file given directly is always run, in a directory only annotated hexes are
+pub fn discover(path: &Path) -> io::Result<Vec<PathBuf>> {
+    if path.is_file() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let mut tests = vec![];
+    let mut entries = fs::read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+
+    for entry in entries {
+        if entry.is_dir() {
+            tests.append(&mut discover(&entry)?);
+        } else if entry.extension().is_some_and(|ext| ext == "hexagon")
+            && fs::read_to_string(&entry)?.contains(ANNOTATION)
+        {
+            tests.push(entry);
+        }
+    }
+
+    Ok(tests)
+}
+
+pub fn test(path: &str, config: &Config, quiet: bool) -> ExitStatus {
+    let tests = match discover(Path::new(path)) {
+        Ok(tests) => tests,
+        Err(err) => {
+            eprintln!("{} {path}: {err}", "Error:".red().bold());
+            return ExitStatus::Io;
+        }
+    };
+
+    if tests.is_empty() {
+        println!("No tests found in {path}");
+        return ExitStatus::Success;
+    }
+
+    //tests have to give the same result every time they are run
+    let mut config = config.clone();
+    config.seed = config.seed.or(Some(0));
+
+    let mut failed = 0;
+    for test in &tests {
+        let test_path = test.display().to_string();
+        let failures = match fs::read_to_string(test) {
+            Ok(source) => run_test(&source, &test_path, &config),
+            Err(err) => vec![err.to_string()],
+        };
+
+        if failures.is_empty() {
+            if !quiet {
+                println!("{} {test_path}", "PASS".green().bold());
+            }
+        } else {
+            failed += 1;
+            println!("{} {test_path}", "FAIL".red().bold());
+            for failure in failures {
+                println!("  {}", failure.replace('\n', "\n  "));
+            }
+        }
+    }
+
+    if !quiet {
+        println!();
+    }
+    println!("{} passed, {failed} failed", tests.len() - failed);
+    if failed == 0 {
+        ExitStatus::Success
+    } else {
+        ExitStatus::Mishap
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn run_test_test() {
+        let config = Config::default();
+        let source = "//expect stack: [1, 3]\n//expect output: 3.000\nNumerical Reflection: 1\nNumerical Reflection: 3\nReveal";
+        assert!(run_test(source, "", &config).is_empty());
+
+        let source = "//expect ravenmind: [2]\n//expect mishap at 4:1: Attempted to divide 1.000 by 0.000\nNumerical Reflection: 2\nStore($x)\nNumerical Reflection: 1\nNumerical Reflection: 0\nDivision Distillation";
+        assert_eq!(
+            run_test(source, "", &config),
+            vec!["mishaps\n  expected: Attempted to divide 1.000 by 0.000 at 4:1\n  got:      Attempted to divide 1.000 by 0.000 at 7:1"]
+        );
+
+        let failures = run_test("//expect stack: [1]\nNumerical Reflection: 2", "", &config);
+        assert_eq!(
+            failures,
+            vec!["stack\n  expected: [1.000]\n  got:      [2.000]"]
+        );
+
+        //mishaps in included files are expected with the file's path
+        let dir = std::env::temp_dir().join("hexagon_test_runner_test");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("lib.hexagon"),
+            "Numerical Reflection: 1\nAdditive Distillation",
+        )
+        .unwrap();
+        let main_path = dir.join("main.hexagon").display().to_string();
+        let source = "//expect mishap at lib.hexagon:2:1: Expected 2 or more arguments but the stack was only 1 tall\n#include \"lib.hexagon\"";
+        assert!(run_test(source, &main_path, &config).is_empty());
+        let source = "//expect mishap at 2:1: Expected 2 or more arguments but the stack was only 1 tall\n#include \"lib.hexagon\"";
+        assert_eq!(run_test(source, &main_path, &config).len(), 1);
+
+        let failures = run_test("//expect stack [1]", "", &config);
+        assert_eq!(failures, vec!["line 1: expected ':' after 'stack'"]);
+        assert_eq!(
+            run_test("//expect stack: [1", "", &config),
+            vec!["line 1: invalid iota '[1'"]
+        );
+    }
+}